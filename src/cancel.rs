@@ -0,0 +1,128 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Cancellation of long operations, built on an event pair.
+
+use {AsHandleRef, Deadline, EventPair, EventPairOpts, HandleRef, Signals, Status};
+use is_asserted;
+
+/// Create a linked cancellation token and receiver.
+///
+/// Either can be sent to another process, by unwrapping it with `into_event_pair` and wrapping
+/// it again with `From` on the other side.
+pub fn cancellation() -> Result<(CancellationToken, CancellationReceiver), Status> {
+    let (token, receiver) = EventPair::create(EventPairOpts::Default)?;
+    Ok((CancellationToken(token), CancellationReceiver(receiver)))
+}
+
+/// The side of a cancellation which requests it, by calling `cancel` or by being dropped.
+#[derive(Debug, Eq, PartialEq)]
+pub struct CancellationToken(EventPair);
+
+impl CancellationToken {
+    /// Request cancellation. This succeeds even if the receiver has already been dropped, as
+    /// there is then nothing left to cancel.
+    pub fn cancel(&self) -> Result<(), Status> {
+        match self.0.notify_peer() {
+            Err(Status::ErrPeerClosed) => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Unwrap the underlying event pair.
+    pub fn into_event_pair(self) -> EventPair {
+        self.0
+    }
+}
+
+impl AsHandleRef for CancellationToken {
+    fn as_handle_ref(&self) -> HandleRef {
+        self.0.as_handle_ref()
+    }
+}
+
+impl From<EventPair> for CancellationToken {
+    fn from(pair: EventPair) -> Self {
+        CancellationToken(pair)
+    }
+}
+
+/// The side of a cancellation which observes it, held by whoever is doing the work.
+#[derive(Debug, Eq, PartialEq)]
+pub struct CancellationReceiver(EventPair);
+
+impl CancellationReceiver {
+    /// The signals, any of which is asserted on the receiver once cancellation has been
+    /// requested. Wait for these to observe cancellation asynchronously, for example on a port.
+    pub fn signals() -> Signals {
+        Signals::EPAIR_SIGNALED | Signals::EPAIR_CLOSED
+    }
+
+    /// Whether cancellation has been requested, either with `CancellationToken::cancel` or by
+    /// the token being dropped.
+    pub fn is_cancelled(&self) -> Result<bool, Status> {
+        is_asserted(self.as_handle_ref(), Self::signals())
+    }
+
+    /// Wait until cancellation is requested, or `deadline` passes. Fails with `ErrTimedOut` if
+    /// the deadline passes first.
+    pub fn wait(&self, deadline: Deadline) -> Result<(), Status> {
+        self.0.wait_handle(Self::signals(), deadline).map(|_| ())
+    }
+
+    /// Unwrap the underlying event pair.
+    pub fn into_event_pair(self) -> EventPair {
+        self.0
+    }
+}
+
+impl AsHandleRef for CancellationReceiver {
+    fn as_handle_ref(&self) -> HandleRef {
+        self.0.as_handle_ref()
+    }
+}
+
+impl From<EventPair> for CancellationReceiver {
+    fn from(pair: EventPair) -> Self {
+        CancellationReceiver(pair)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Duration;
+    use std::thread;
+
+    #[test]
+    fn cancel() {
+        let (token, receiver) = cancellation().unwrap();
+        assert_eq!(receiver.is_cancelled(), Ok(false));
+        assert_eq!(receiver.wait(Deadline::after(Duration::from_millis(10))),
+            Err(Status::ErrTimedOut));
+        assert_eq!(token.cancel(), Ok(()));
+        assert_eq!(receiver.is_cancelled(), Ok(true));
+
+        // Cancelling once the receiver has gone away is not an error.
+        drop(receiver);
+        assert_eq!(token.cancel(), Ok(()));
+    }
+
+    #[test]
+    fn drop_token_cancels() {
+        let (token, receiver) = cancellation().unwrap();
+        let worker = thread::spawn(move || receiver.wait(Deadline::INFINITE));
+        drop(token);
+        assert_eq!(worker.join().unwrap(), Ok(()));
+    }
+
+    #[test]
+    fn rewrapped() {
+        let (token, receiver) = cancellation().unwrap();
+        let token = CancellationToken::from(token.into_event_pair());
+        let receiver = CancellationReceiver::from(receiver.into_event_pair());
+        assert_eq!(token.cancel(), Ok(()));
+        assert_eq!(receiver.wait(Deadline::INFINITE), Ok(()));
+    }
+}
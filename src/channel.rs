@@ -4,17 +4,20 @@
 
 //! Type-safe bindings for Zircon channel objects.
 
-use {AsHandleRef, HandleBased, Handle, HandleRef, INVALID_HANDLE, Peered, Status, Time, usize_into_u32, size_to_u32_sat};
-use {sys, handle_drop, into_result};
-use std::mem;
+use {AsHandleRef, Deadline, HandleBased, Handle, HandleRef, INVALID_HANDLE, ObjectType, Peered, Rights, Signals, Status, TypedHandle, usize_into_u32, size_to_u32_sat};
+use {sys, handle_drop, into_result, leak_check, retry_when_ready, with_gathered};
+use std::{error, fmt, mem, vec};
+use std::io::IoSlice;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
 
 /// An object representing a Zircon
 /// [channel](https://fuchsia.googlesource.com/zircon/+/master/docs/objects/channel.md).
 ///
 /// As essentially a subtype of `Handle`, it can be freely interconverted.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Eq, PartialEq)]
 pub struct Channel(Handle);
-impl_handle_based!(Channel);
+impl_handle_based!(Channel, ObjectType::Channel);
 impl Peered for Channel {}
 
 impl Channel {
@@ -30,8 +33,8 @@ impl Channel {
             let mut handle1 = 0;
             let status = sys::zx_channel_create(opts as u32, &mut handle0, &mut handle1);
             into_result(status, ||
-                (Self::from(Handle(handle0)),
-                    Self::from(Handle(handle1))))
+                (Self::from(Handle::owned(handle0)),
+                    Self::from(Handle::owned(handle1))))
         }
     }
 
@@ -81,26 +84,225 @@ impl Channel {
         }
     }
 
+    /// Read all of the messages currently queued on the channel, appending them to `bufs`, and
+    /// return the number read. Stops without error once the channel is empty.
+    ///
+    /// If a read fails for any other reason, such as the peer having been closed, the error is
+    /// returned; any messages read before the failure have still been appended to `bufs`.
+    pub fn drain(&self, opts: u32, bufs: &mut Vec<MessageBuf>) -> Result<usize, Status> {
+        let mut count = 0;
+        let mut buf = MessageBuf::new();
+        while self.try_read(opts, &mut buf)? {
+            bufs.push(mem::replace(&mut buf, MessageBuf::new()));
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// An iterator over the messages received on the channel, which blocks until each message
+    /// arrives, and ends once the peer has been closed and all of its messages have been read.
+    /// If reading fails for any other reason, the error is returned and the iterator ends.
+    pub fn messages(&self) -> Messages {
+        Messages { channel: self, done: false }
+    }
+
+    /// Read a message from a channel if one is available, without waiting.
+    ///
+    /// Returns `Ok(true)` if a message was read into `buf`, and `Ok(false)` if the channel was
+    /// empty (rather than the `ErrShouldWait` error returned by `read`).
+    pub fn try_read(&self, opts: u32, buf: &mut MessageBuf) -> Result<bool, Status> {
+        match self.read(opts, buf) {
+            Ok(()) => Ok(true),
+            Err(Status::ErrShouldWait) => Ok(false),
+            Err(status) => Err(status),
+        }
+    }
+
+    /// Read a message from a channel, waiting until one is available or the deadline passes.
+    ///
+    /// If the peer has been closed and no messages remain, fails with `ErrPeerClosed`; messages
+    /// written before the peer was closed are still returned first.
+    pub fn read_deadline(&self, opts: u32, buf: &mut MessageBuf, deadline: Deadline)
+        -> Result<(), Status>
+    {
+        loop {
+            match self.read(opts, buf) {
+                Err(Status::ErrShouldWait) => {
+                    let observed = self.wait_handle(
+                        Signals::CHANNEL_READABLE | Signals::CHANNEL_PEER_CLOSED, deadline)?;
+                    if !observed.contains(Signals::CHANNEL_READABLE) {
+                        return Err(Status::ErrPeerClosed);
+                    }
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Read a message from a channel into caller-provided buffers, without allocating. Returns
+    /// the number of bytes and handles received, which are stored at the start of `bytes` and
+    /// `handles`. Any handles already in `handles` are closed first.
+    ///
+    /// If the message does not fit, fails with `ErrBufferTooSmall` and leaves it queued on the
+    /// channel.
+    pub fn read_into(&self, opts: u32, bytes: &mut [u8], handles: &mut [Handle])
+        -> Result<(usize, usize), Status>
+    {
+        for handle in handles.iter_mut() {
+            *handle = Handle::invalid();
+        }
+        let mut num_bytes = 0;
+        let mut num_handles = 0;
+        let status = unsafe {
+            sys::zx_channel_read(self.raw_handle(), opts, bytes.as_mut_ptr(),
+                handles.as_mut_ptr() as *mut sys::zx_handle_t, size_to_u32_sat(bytes.len()),
+                size_to_u32_sat(handles.len()), &mut num_bytes, &mut num_handles)
+        };
+        into_result(status, || {
+            for handle in &handles[..num_handles as usize] {
+                leak_check::track(handle.0);
+            }
+            (num_bytes as usize, num_handles as usize)
+        })
+    }
+
+    /// Read a message from a channel, also recording the type and rights of each received
+    /// handle, which can then be retrieved with `MessageBuf::handle_info`. This lets the
+    /// receiver check that a handle is what it expects before using it.
+    ///
+    /// The handle information is looked up with `zx_object_get_info` after the message has been
+    /// read. As with `read`, this can cause internal reallocations in the `MessageBuf`.
+    ///
+    /// If looking up the information fails, the error is returned, but the message has still
+    /// been read: `buf` holds its bytes and handles, with no handle information recorded for any
+    /// of them, just as if it had been read with `read`.
+    pub fn read_etc(&self, opts: u32, buf: &mut MessageBuf) -> Result<(), Status> {
+        self.read(opts, buf)?;
+        let infos = buf.handles.iter().map(|&raw_handle| {
+            let handle_ref = HandleRef { handle: raw_handle, phantom: Default::default() };
+            handle_ref.basic_info()
+                .map(|info| HandleInfo { object_type: info.object_type, rights: info.rights })
+        }).collect::<Result<Vec<_>, Status>>()?;
+        buf.handle_infos = infos;
+        Ok(())
+    }
+
+    /// Split the channel into a writing half and a reading half, which can be owned by
+    /// different threads. Both halves share the same underlying handle, which is closed when
+    /// both have been dropped.
+    pub fn split(self) -> (ChannelWriter, ChannelReader) {
+        let channel = Arc::new(self);
+        (ChannelWriter(channel.clone()), ChannelReader(channel))
+    }
+
+    /// Check that a message of the given size is within the limits of
+    /// `CHANNEL_MAX_MSG_BYTES` and `CHANNEL_MAX_MSG_HANDLES`, so it can be written to a channel.
+    pub fn check_message_size(n_bytes: usize, n_handles: usize) -> Result<(), MessageTooLarge> {
+        if n_bytes > CHANNEL_MAX_MSG_BYTES || n_handles > CHANNEL_MAX_MSG_HANDLES {
+            Err(MessageTooLarge { n_bytes, n_handles })
+        } else {
+            Ok(())
+        }
+    }
+
     /// Write a message to a channel. Wraps the
     /// [zx_channel_write](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/channel_write.md)
     /// syscall.
+    ///
+    /// Messages which are too large fail with `ErrOutOfRange` without calling into the kernel;
+    /// use `write_checked` for an error which gives the offending size.
     pub fn write(&self, bytes: &[u8], handles: &mut Vec<Handle>, opts: u32)
             -> Result<(), Status>
     {
-        let n_bytes = try!(usize_into_u32(bytes.len()).map_err(|_| Status::ErrOutOfRange));
-        let n_handles = try!(usize_into_u32(handles.len()).map_err(|_| Status::ErrOutOfRange));
-        unsafe {
-            let status = sys::zx_channel_write(self.raw_handle(), opts, bytes.as_ptr(), n_bytes,
-                handles.as_ptr() as *const sys::zx_handle_t, n_handles);
-            into_result(status, || {
-                // Handles were successfully transferred, forget them on sender side
-                handles.set_len(0);
-            })
+        self.write_checked(bytes, handles, opts).map_err(Status::from)
+    }
+
+    /// Write a message to a channel, as with `write`, but failing with
+    /// `ChannelWriteError::TooLarge` (which gives the size of the message) rather than a bare
+    /// `ErrOutOfRange` if it exceeds `CHANNEL_MAX_MSG_BYTES` or `CHANNEL_MAX_MSG_HANDLES`.
+    pub fn write_checked(&self, bytes: &[u8], handles: &mut Vec<Handle>, opts: u32)
+            -> Result<(), ChannelWriteError>
+    {
+        Channel::check_message_size(bytes.len(), handles.len())
+            .map_err(ChannelWriteError::TooLarge)?;
+        let n_bytes = bytes.len() as u32;
+        let n_handles = handles.len() as u32;
+        let status = unsafe {
+            sys::zx_channel_write(self.raw_handle(), opts, bytes.as_ptr(), n_bytes,
+                handles.as_ptr() as *const sys::zx_handle_t, n_handles)
+        };
+        into_result(status, || {
+            // Handles were successfully transferred, forget them on sender side
+            for handle in handles.iter() {
+                leak_check::untrack(handle.0);
+            }
+            unsafe { handles.set_len(0); }
+        }).map_err(ChannelWriteError::Status)
+    }
+
+    /// Write a message to a channel, waiting for it to become writable if necessary. Fails with
+    /// `ErrTimedOut` if it does not become writable before the deadline, or `ErrPeerClosed` if
+    /// the peer is closed.
+    pub fn write_deadline(&self, bytes: &[u8], handles: &mut Vec<Handle>, opts: u32,
+        deadline: Deadline) -> Result<(), Status>
+    {
+        retry_when_ready(self.as_handle_ref(), Signals::CHANNEL_WRITABLE,
+            Signals::CHANNEL_PEER_CLOSED, deadline, || self.write(bytes, handles, opts))
+    }
+
+    /// Write a message made up of several byte slices to a channel, as if they had been
+    /// concatenated. The slices are gathered into a buffer which is reused between calls on the
+    /// same thread, so this does not usually allocate.
+    pub fn write_vectored(&self, bufs: &[IoSlice], handles: &mut Vec<Handle>, opts: u32)
+        -> Result<(), Status>
+    {
+        let n_bytes = bufs.iter().map(|buf| buf.len()).sum();
+        Channel::check_message_size(n_bytes, handles.len())?;
+        with_gathered(bufs, |bytes| self.write(bytes, handles, opts))
+    }
+
+    /// Write a message to a channel, transferring each handle as described by its
+    /// `HandleDisposition`. This allows a handle to be sent with reduced rights, or a duplicate
+    /// to be sent while the caller keeps the original, without separate calls by the caller.
+    ///
+    /// The dispositions are applied (using `zx_handle_duplicate` or `zx_handle_replace`) before
+    /// the message is written with
+    /// [zx_channel_write](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/channel_write.md).
+    /// If any step fails, all handles moved into the call are closed.
+    pub fn write_etc(&self, bytes: &[u8], dispositions: Vec<HandleDisposition>, opts: u32)
+            -> Result<(), Status>
+    {
+        let mut handles = Vec::with_capacity(dispositions.len());
+        for disposition in dispositions {
+            let handle = match disposition.operation {
+                HandleOp::Move(handle) => if disposition.rights == Rights::SAME_RIGHTS {
+                    handle
+                } else {
+                    handle.replace(disposition.rights)?
+                },
+                HandleOp::Duplicate(handle_ref) => handle_ref.duplicate(disposition.rights)?,
+            };
+            handles.push(handle);
         }
+        self.write(bytes, &mut handles, opts)
     }
 
-    /// Send a message consisting of the given bytes and handles to a channel and await a reply. The
-    /// bytes should start with a four byte 'txid' which is used to identify the matching reply.
+    /// Allocate a transaction id ('txid'). Each call returns a different nonzero value, so
+    /// transactions on the same channel from within this process never share a txid. Both
+    /// `call` and `Transactions::send` take their txids from here.
+    pub fn next_txid() -> u32 {
+        loop {
+            let txid = NEXT_TXID.fetch_add(1, Ordering::Relaxed);
+            if txid != 0 {
+                return txid;
+            }
+        }
+    }
+
+    /// Send a message consisting of a transaction id ('txid') followed by the given bytes, along
+    /// with the given handles, to a channel and await a reply. `call` allocates a fresh txid with
+    /// `next_txid` and writes it ahead of `body`, which is left untouched; the reply in `buf`
+    /// starts with the same txid, which is used to identify it.
     ///
     /// Wraps the
     /// [zx_channel_call](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/channel_call.md)
@@ -113,7 +315,16 @@ impl Channel {
     /// On failure returns the both the main and read status.
     ///
     /// [read]: struct.Channel.html#method.read
-    pub fn call(&self, options: u32, timeout: Time, bytes: &[u8], handles: &mut Vec<Handle>,
+    pub fn call(&self, options: u32, deadline: Deadline, body: &[u8], handles: &mut Vec<Handle>,
+        buf: &mut MessageBuf) -> Result<(), (Status, Status)>
+    {
+        let txid: [u8; TXID_LEN] = Channel::next_txid().to_ne_bytes();
+        with_gathered(&[IoSlice::new(&txid), IoSlice::new(body)],
+            |bytes| self.call_raw(options, deadline, bytes, handles, buf))
+    }
+
+    // Send a message whose bytes already start with a txid and await a reply.
+    fn call_raw(&self, options: u32, deadline: Deadline, bytes: &[u8], handles: &mut Vec<Handle>,
         buf: &mut MessageBuf) -> Result<(), (Status, Status)>
     {
         let write_num_bytes = try!(usize_into_u32(bytes.len()).map_err(
@@ -137,13 +348,16 @@ impl Channel {
         let mut actual_read_handles: u32 = 0;
         let mut read_status = sys::ZX_OK;
         let status = unsafe {
-            sys::zx_channel_call(self.raw_handle(), options, timeout, &args, &mut actual_read_bytes,
+            sys::zx_channel_call(self.raw_handle(), options, deadline.into_nanos(), &args, &mut actual_read_bytes,
                 &mut actual_read_handles, &mut read_status)
         };
         if status == sys::ZX_OK || status == sys::ZX_ERR_TIMED_OUT || status == sys::ZX_ERR_CALL_FAILED
         {
             // Handles were successfully transferred, even if we didn't get a response, so forget
             // them on the sender side.
+            for handle in handles.iter() {
+                leak_check::untrack(handle.0);
+            }
             unsafe { handles.set_len(0); }
         }
         unsafe {
@@ -172,6 +386,198 @@ impl Default for ChannelOpts {
     }
 }
 
+/// How a handle is to be transferred by `Channel::write_etc`.
+#[derive(Debug)]
+pub enum HandleOp<'a> {
+    /// Transfer the handle itself, which is consumed.
+    Move(Handle),
+    /// Transfer a duplicate of the handle, leaving the original with the caller.
+    Duplicate(HandleRef<'a>),
+}
+
+/// A handle to be transferred by `Channel::write_etc`, together with the rights the receiver
+/// should get.
+#[derive(Debug)]
+pub struct HandleDisposition<'a> {
+    /// The handle, and whether to move or duplicate it.
+    pub operation: HandleOp<'a>,
+    /// The rights of the transferred handle, or `Rights::SAME_RIGHTS` to keep those of the
+    /// original.
+    pub rights: Rights,
+}
+
+/// The type and rights of a handle received by `Channel::read_etc`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct HandleInfo {
+    /// The type of the object.
+    pub object_type: ObjectType,
+    /// The rights of the received handle.
+    pub rights: Rights,
+}
+
+/// A blocking iterator over the messages received on a channel, created by
+/// `Channel::messages`.
+#[derive(Debug)]
+pub struct Messages<'a> {
+    channel: &'a Channel,
+    done: bool,
+}
+
+impl<'a> Iterator for Messages<'a> {
+    type Item = Result<MessageBuf, Status>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let mut buf = MessageBuf::new();
+        match self.channel.read_deadline(0, &mut buf, Deadline::INFINITE) {
+            Ok(()) => Some(Ok(buf)),
+            Err(Status::ErrPeerClosed) => {
+                self.done = true;
+                None
+            }
+            Err(status) => {
+                self.done = true;
+                Some(Err(status))
+            }
+        }
+    }
+}
+
+/// The writing half of a channel, created by `Channel::split`.
+#[derive(Debug, Clone)]
+pub struct ChannelWriter(Arc<Channel>);
+
+impl ChannelWriter {
+    /// Write a message to the channel. See `Channel::write`.
+    pub fn write(&self, bytes: &[u8], handles: &mut Vec<Handle>, opts: u32)
+        -> Result<(), Status>
+    {
+        self.0.write(bytes, handles, opts)
+    }
+
+    /// Write a message to the channel, with a disposition for each handle. See
+    /// `Channel::write_etc`.
+    pub fn write_etc(&self, bytes: &[u8], dispositions: Vec<HandleDisposition>, opts: u32)
+        -> Result<(), Status>
+    {
+        self.0.write_etc(bytes, dispositions, opts)
+    }
+}
+
+impl AsHandleRef for ChannelWriter {
+    fn as_handle_ref(&self) -> HandleRef {
+        self.0.as_handle_ref()
+    }
+}
+
+/// The reading half of a channel, created by `Channel::split`.
+#[derive(Debug)]
+pub struct ChannelReader(Arc<Channel>);
+
+impl ChannelReader {
+    /// Read a message from the channel. See `Channel::read`.
+    pub fn read(&self, opts: u32, buf: &mut MessageBuf) -> Result<(), Status> {
+        self.0.read(opts, buf)
+    }
+
+    /// Read a message from the channel, recording the type and rights of each handle. See
+    /// `Channel::read_etc`.
+    pub fn read_etc(&self, opts: u32, buf: &mut MessageBuf) -> Result<(), Status> {
+        self.0.read_etc(opts, buf)
+    }
+
+    /// Read a message from the channel, waiting until one is available or the deadline passes.
+    /// See `Channel::read_deadline`.
+    pub fn read_deadline(&self, opts: u32, buf: &mut MessageBuf, deadline: Deadline)
+        -> Result<(), Status>
+    {
+        self.0.read_deadline(opts, buf, deadline)
+    }
+
+    /// Read a message from the channel if one is available. See `Channel::try_read`.
+    pub fn try_read(&self, opts: u32, buf: &mut MessageBuf) -> Result<bool, Status> {
+        self.0.try_read(opts, buf)
+    }
+
+    /// Read all of the messages currently queued on the channel. See `Channel::drain`.
+    pub fn drain(&self, opts: u32, bufs: &mut Vec<MessageBuf>) -> Result<usize, Status> {
+        self.0.drain(opts, bufs)
+    }
+}
+
+impl AsHandleRef for ChannelReader {
+    fn as_handle_ref(&self) -> HandleRef {
+        self.0.as_handle_ref()
+    }
+}
+
+/// The maximum number of bytes in a channel message.
+pub const CHANNEL_MAX_MSG_BYTES: usize = sys::ZX_CHANNEL_MAX_MSG_BYTES as usize;
+
+/// The maximum number of handles in a channel message.
+pub const CHANNEL_MAX_MSG_HANDLES: usize = sys::ZX_CHANNEL_MAX_MSG_HANDLES as usize;
+
+/// A message which is too large to be written to a channel, as reported by
+/// `Channel::check_message_size`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct MessageTooLarge {
+    /// The number of bytes in the message.
+    pub n_bytes: usize,
+    /// The number of handles in the message.
+    pub n_handles: usize,
+}
+
+impl fmt::Display for MessageTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.n_bytes > CHANNEL_MAX_MSG_BYTES {
+            write!(f, "channel message of {} bytes exceeds the limit of {} bytes",
+                self.n_bytes, CHANNEL_MAX_MSG_BYTES)
+        } else {
+            write!(f, "channel message with {} handles exceeds the limit of {} handles",
+                self.n_handles, CHANNEL_MAX_MSG_HANDLES)
+        }
+    }
+}
+
+impl error::Error for MessageTooLarge {}
+
+impl From<MessageTooLarge> for Status {
+    fn from(_: MessageTooLarge) -> Status {
+        Status::ErrOutOfRange
+    }
+}
+
+/// An error from `Channel::write_checked`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ChannelWriteError {
+    /// The message exceeds the limits on channel messages, so was not written.
+    TooLarge(MessageTooLarge),
+    /// The kernel failed to write the message.
+    Status(Status),
+}
+
+impl fmt::Display for ChannelWriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ChannelWriteError::TooLarge(ref too_large) => too_large.fmt(f),
+            ChannelWriteError::Status(status) => write!(f, "channel write failed: {:?}", status),
+        }
+    }
+}
+
+impl error::Error for ChannelWriteError {}
+
+impl From<ChannelWriteError> for Status {
+    fn from(error: ChannelWriteError) -> Status {
+        match error {
+            ChannelWriteError::TooLarge(too_large) => too_large.into(),
+            ChannelWriteError::Status(status) => status,
+        }
+    }
+}
+
 /// A buffer for _receiving_ messages from a channel.
 ///
 /// A `MessageBuf` is essentially a byte buffer and a vector of
@@ -184,6 +590,7 @@ impl Default for ChannelOpts {
 pub struct MessageBuf {
     bytes: Vec<u8>,
     handles: Vec<sys::zx_handle_t>,
+    handle_infos: Vec<HandleInfo>,
 }
 
 impl MessageBuf {
@@ -202,6 +609,13 @@ impl MessageBuf {
         ensure_capacity(&mut self.handles, n_handles);
     }
 
+    /// Remove the contents of the message buffer, closing any handles which have not been taken,
+    /// but keeping the allocated capacity so that the buffer can be reused.
+    pub fn clear(&mut self) {
+        self.reset_handles();
+        self.bytes.clear();
+    }
+
     /// Get a reference to the bytes of the message buffer, as a `&[u8]` slice.
     pub fn bytes(&self) -> &[u8] {
         self.bytes.as_slice()
@@ -222,14 +636,66 @@ impl MessageBuf {
             if *handleref == INVALID_HANDLE {
                 None
             } else {
-                Some(Handle(mem::replace(handleref, INVALID_HANDLE)))
+                Some(Handle::owned(mem::replace(handleref, INVALID_HANDLE)))
             }
         )
     }
 
+    /// Take the handle at the specified index from the message buffer as a specific type,
+    /// checking that it refers to an object of that type. Fails with `ErrNotFound` if there is
+    /// no handle at the index, or `ErrWrongType` if it is of a different type, in which case
+    /// the handle is left in the buffer.
+    ///
+    /// The type is as recorded by `Channel::read_etc` if available, and is otherwise looked up
+    /// with `zx_object_get_info`.
+    pub fn take_typed_handle<T: TypedHandle>(&mut self, index: usize) -> Result<T, Status> {
+        let raw_handle = match self.handles.get(index) {
+            Some(&raw_handle) if raw_handle != INVALID_HANDLE => raw_handle,
+            _ => return Err(Status::ErrNotFound),
+        };
+        let object_type = match self.handle_info(index) {
+            Some(info) => info.object_type,
+            None => HandleRef { handle: raw_handle, phantom: Default::default() }
+                .basic_info()?.object_type,
+        };
+        if object_type != T::OBJECT_TYPE {
+            return Err(Status::ErrWrongType);
+        }
+        self.take_handle(index).map(T::from).ok_or(Status::ErrNotFound)
+    }
+
+    /// Get the type and rights of the handle at the specified index, if the message was read
+    /// with `Channel::read_etc`. Unlike `take_handle`, this is still available after the handle
+    /// has been taken.
+    pub fn handle_info(&self, index: usize) -> Option<HandleInfo> {
+        self.handle_infos.get(index).cloned()
+    }
+
+    /// Take all of the handles remaining in the message buffer, in order, together with their
+    /// type and rights, leaving the buffer without handles.
+    ///
+    /// The type and rights are those recorded by `Channel::read_etc`; for messages read in other
+    /// ways, they are looked up with `zx_object_get_info` as the handles are taken.
+    pub fn drain_handles(&mut self) -> vec::IntoIter<(Handle, HandleInfo)> {
+        let mut drained = Vec::with_capacity(self.handles.len());
+        for index in 0..self.handles.len() {
+            let info = self.handle_info(index);
+            if let Some(handle) = self.take_handle(index) {
+                let info = info.unwrap_or_else(|| match handle.basic_info() {
+                    Ok(info) => HandleInfo { object_type: info.object_type, rights: info.rights },
+                    Err(_) => HandleInfo { object_type: ObjectType::Unknown, rights: Rights::NONE },
+                });
+                drained.push((handle, info));
+            }
+        }
+        self.handles.clear();
+        self.handle_infos.clear();
+        drained.into_iter()
+    }
+
     fn drop_handles(&mut self) {
         for &handle in &self.handles {
-            if handle != 0 {
+            if handle != INVALID_HANDLE {
                 handle_drop(handle);
             }
         }
@@ -238,6 +704,7 @@ impl MessageBuf {
     fn reset_handles(&mut self) {
         self.drop_handles();
         self.handles.clear();
+        self.handle_infos.clear();
     }
 }
 
@@ -247,6 +714,50 @@ impl Drop for MessageBuf {
     }
 }
 
+// The next transaction id to be returned by `Channel::next_txid`.
+static NEXT_TXID: AtomicU32 = AtomicU32::new(1);
+
+// The number of bytes at the start of a message which hold its txid.
+pub(crate) const TXID_LEN: usize = 4;
+
+/// A pool of message buffers, so that servers handling many messages can reuse buffers (and
+/// their capacity) rather than allocating new ones.
+#[derive(Debug)]
+pub struct MessageBufPool {
+    bufs: Vec<MessageBuf>,
+    max_pooled: usize,
+}
+
+impl MessageBufPool {
+    /// Create an empty pool which will hold on to at most `max_pooled` buffers.
+    pub fn new(max_pooled: usize) -> Self {
+        MessageBufPool { bufs: Vec::new(), max_pooled }
+    }
+
+    /// Get an empty buffer from the pool, or a new one if the pool is empty.
+    pub fn get(&mut self) -> MessageBuf {
+        self.bufs.pop().unwrap_or_default()
+    }
+
+    /// Return a buffer to the pool, clearing it. If the pool is full, the buffer is dropped.
+    pub fn put(&mut self, mut buf: MessageBuf) {
+        if self.bufs.len() < self.max_pooled {
+            buf.clear();
+            self.bufs.push(buf);
+        }
+    }
+
+    /// The number of buffers currently in the pool.
+    pub fn len(&self) -> usize {
+        self.bufs.len()
+    }
+
+    /// Returns true if there are no buffers in the pool.
+    pub fn is_empty(&self) -> bool {
+        self.bufs.is_empty()
+    }
+}
+
 fn ensure_capacity<T>(vec: &mut Vec<T>, size: usize) {
     let len = vec.len();
     if size > len {
@@ -257,7 +768,7 @@ fn ensure_capacity<T>(vec: &mut Vec<T>, size: usize) {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use {Duration, ZX_CHANNEL_READABLE, ZX_CHANNEL_WRITABLE, ZX_RIGHT_SAME_RIGHTS, ZX_SIGNAL_LAST_HANDLE, Vmo, VmoOpts};
+    use {Duration, Event, EventOpts, Rights, Signals, Vmo, VmoOpts};
     use deadline_after;
     use std::thread;
 
@@ -295,7 +806,7 @@ mod tests {
         let vmo = Vmo::create(hello_length as u64, VmoOpts::Default).unwrap();
 
         // Duplicate VMO handle and send it down the channel.
-        let duplicate_vmo_handle = vmo.duplicate_handle(ZX_RIGHT_SAME_RIGHTS).unwrap().into();
+        let duplicate_vmo_handle = vmo.duplicate_handle(Rights::SAME_RIGHTS).unwrap().into();
         let mut handles_to_send: Vec<Handle> = vec![duplicate_vmo_handle];
         assert!(p1.write(b"", &mut handles_to_send, 0).is_ok());
         // Handle should be removed from vector.
@@ -322,16 +833,85 @@ mod tests {
         assert_eq!(read_vec, b"hello");
     }
 
+    #[test]
+    fn channel_write_etc() {
+        let (p1, p2) = Channel::create(ChannelOpts::Normal).unwrap();
+        let vmo = Vmo::create(5, VmoOpts::Default).unwrap();
+        let moved_vmo = vmo.duplicate_handle(Rights::SAME_RIGHTS).unwrap();
+
+        // Send a read-only duplicate, and move a second handle with reduced rights.
+        let dispositions = vec![
+            HandleDisposition {
+                operation: HandleOp::Duplicate(vmo.as_handle_ref()),
+                rights: Rights::READ,
+            },
+            HandleDisposition {
+                operation: HandleOp::Move(moved_vmo.into_handle()),
+                rights: Rights::READ | Rights::WRITE,
+            },
+        ];
+        assert!(p1.write_etc(b"", dispositions, 0).is_ok());
+
+        let mut buf = MessageBuf::new();
+        assert!(p2.read(0, &mut buf).is_ok());
+        assert_eq!(buf.n_handles(), 2);
+        let read_only = Vmo::from(buf.take_handle(0).unwrap());
+        let read_write = Vmo::from(buf.take_handle(1).unwrap());
+        assert_eq!(read_only.write(b"hello", 0), Err(Status::ErrAccessDenied));
+        assert_eq!(read_write.write(b"hello", 0), Ok(5));
+
+        // The original handle should still be usable.
+        let mut read_vec = vec![0; 5];
+        assert_eq!(vmo.read(&mut read_vec, 0), Ok(5));
+        assert_eq!(read_vec, b"hello");
+    }
+
+    #[test]
+    fn channel_read_etc() {
+        let (p1, p2) = Channel::create(ChannelOpts::Normal).unwrap();
+        let vmo = Vmo::create(5, VmoOpts::Default).unwrap();
+        let mut handles = vec![vmo.duplicate_handle(Rights::READ | Rights::TRANSFER).unwrap().into()];
+        assert!(p1.write(b"vmo", &mut handles, 0).is_ok());
+
+        let mut buf = MessageBuf::new();
+        assert!(p2.read_etc(0, &mut buf).is_ok());
+        assert_eq!(buf.bytes(), b"vmo");
+        let info = buf.handle_info(0).unwrap();
+        assert_eq!(info.object_type, ObjectType::Vmo);
+        assert_eq!(info.rights, Rights::READ | Rights::TRANSFER);
+        assert!(buf.handle_info(1).is_none());
+
+        // The info is still available once the handle has been taken.
+        assert!(buf.take_handle(0).is_some());
+        assert_eq!(buf.handle_info(0), Some(info));
+    }
+
+    #[test]
+    fn channel_signal_peer() {
+        let ten_ms = Duration::from_millis(10);
+        let (p1, p2) = Channel::create(ChannelOpts::Normal).unwrap();
+
+        // Signalling the peer should be visible on the other end only.
+        assert!(p1.signal_peer(Signals::NONE, Signals::USER_0).is_ok());
+        assert!(p2.wait_handle(Signals::USER_0, deadline_after(ten_ms)).is_ok());
+        assert_eq!(p1.wait_handle(Signals::USER_0, deadline_after(ten_ms)),
+            Err(Status::ErrTimedOut));
+
+        // Channel state signals belong to the kernel and can't be raised by hand.
+        assert_eq!(p1.signal_peer(Signals::NONE, Signals::CHANNEL_READABLE),
+            Err(Status::ErrInvalidArgs));
+    }
+
     #[test]
     fn channel_call_timeout() {
-        let ten_ms: Duration = 10_000_000;
+        let ten_ms = Duration::from_millis(10);
 
         // Create a pair of channels and a virtual memory object.
         let (p1, p2) = Channel::create(ChannelOpts::Normal).unwrap();
         let vmo = Vmo::create(0 as u64, VmoOpts::Default).unwrap();
 
         // Duplicate VMO handle and send it along with the call.
-        let duplicate_vmo_handle = vmo.duplicate_handle(ZX_RIGHT_SAME_RIGHTS).unwrap().into();
+        let duplicate_vmo_handle = vmo.duplicate_handle(Rights::SAME_RIGHTS).unwrap().into();
         let mut handles_to_send: Vec<Handle> = vec![duplicate_vmo_handle];
         let mut buf = MessageBuf::new();
         assert_eq!(p1.call(0, deadline_after(ten_ms), b"call", &mut handles_to_send, &mut buf),
@@ -340,41 +920,279 @@ mod tests {
         // still sent over the channel.
         assert!(handles_to_send.is_empty());
 
-        // Should be able to read call even though it timed out waiting for a response.
+        // Should be able to read call even though it timed out waiting for a response. Its txid
+        // comes before the body.
         let mut buf = MessageBuf::new();
         assert!(p2.read(0, &mut buf).is_ok());
-        assert_eq!(buf.bytes(), b"call");
+        assert!(&buf.bytes()[..4] != &[0, 0, 0, 0]);
+        assert_eq!(&buf.bytes()[4..], b"call");
         assert_eq!(buf.n_handles(), 1);
     }
 
+    #[test]
+    fn channel_call_empty_body() {
+        let ten_ms = Duration::from_millis(10);
+        let (p1, p2) = Channel::create(ChannelOpts::Normal).unwrap();
+        let mut empty = vec![];
+        let mut buf = MessageBuf::new();
+        assert_eq!(p1.call(0, deadline_after(ten_ms), b"", &mut empty, &mut buf),
+            Err((Status::ErrTimedOut, Status::NoError)));
+        assert_eq!(p2.try_read(0, &mut buf), Ok(true));
+        assert_eq!(buf.bytes().len(), 4);
+    }
+
     #[test]
     fn channel_call() {
-        let hundred_ms: Duration = 100_000_000;
+        let hundred_ms = Duration::from_millis(100);
 
         // Create a pair of channels
         let (p1, p2) = Channel::create(ChannelOpts::Normal).unwrap();
 
         // Start a new thread to respond to the call.
         let server = thread::spawn(move || {
-            assert_eq!(p2.wait_handle(ZX_CHANNEL_READABLE, deadline_after(hundred_ms)),
-                Ok(ZX_CHANNEL_READABLE | ZX_CHANNEL_WRITABLE | ZX_SIGNAL_LAST_HANDLE));
+            assert_eq!(p2.wait_handle(Signals::CHANNEL_READABLE, deadline_after(hundred_ms)),
+                Ok(Signals::CHANNEL_READABLE | Signals::CHANNEL_WRITABLE | Signals::LAST_HANDLE));
             let mut buf = MessageBuf::new();
             assert_eq!(p2.read(0, &mut buf), Ok(()));
-            assert_eq!(buf.bytes(), b"txidcall");
+            assert_eq!(&buf.bytes()[4..], b"call");
             assert_eq!(buf.n_handles(), 0);
+            let mut response = buf.bytes()[..4].to_vec();
+            response.extend_from_slice(b"response");
             let mut empty = vec![];
-            assert_eq!(p2.write(b"txidresponse", &mut empty, 0), Ok(()));
+            assert_eq!(p2.write(&response, &mut empty, 0), Ok(()));
+            response
         });
 
         // Make the call.
         let mut empty = vec![];
         let mut buf = MessageBuf::new();
         buf.ensure_capacity_bytes(12);
-        assert_eq!(p1.call(0, deadline_after(hundred_ms), b"txidcall", &mut empty, &mut buf),
+        assert_eq!(p1.call(0, deadline_after(hundred_ms), b"call", &mut empty, &mut buf),
             Ok(()));
-        assert_eq!(buf.bytes(), b"txidresponse");
+        assert_eq!(&buf.bytes()[4..], b"response");
+        assert_eq!(buf.n_handles(), 0);
+
+        assert_eq!(server.join().unwrap(), buf.bytes());
+    }
+
+    #[test]
+    fn channel_read_deadline() {
+        let ten_ms = Duration::from_millis(10);
+        let (p1, p2) = Channel::create(ChannelOpts::Normal).unwrap();
+        let mut buf = MessageBuf::new();
+        assert_eq!(p2.read_deadline(0, &mut buf, deadline_after(ten_ms)), Err(Status::ErrTimedOut));
+
+        let mut empty = vec![];
+        assert!(p1.write(b"hello", &mut empty, 0).is_ok());
+        drop(p1);
+        assert_eq!(p2.read_deadline(0, &mut buf, deadline_after(ten_ms)), Ok(()));
+        assert_eq!(buf.bytes(), b"hello");
+        assert_eq!(p2.read_deadline(0, &mut buf, deadline_after(ten_ms)),
+            Err(Status::ErrPeerClosed));
+    }
+
+    #[test]
+    fn message_buf_drain_handles() {
+        let (p1, p2) = Channel::create(ChannelOpts::Normal).unwrap();
+        let mut buf = MessageBuf::new();
+        for &read_etc in &[false, true] {
+            let vmo = Vmo::create(0, VmoOpts::Default).unwrap();
+            let (c1, _c2) = Channel::create(ChannelOpts::Normal).unwrap();
+            let mut handles = vec![vmo.into_handle(), c1.into_handle()];
+            assert!(p1.write(b"", &mut handles, 0).is_ok());
+            if read_etc {
+                assert!(p2.read_etc(0, &mut buf).is_ok());
+            } else {
+                assert!(p2.read(0, &mut buf).is_ok());
+            }
+            let types: Vec<_> = buf.drain_handles().map(|(_, info)| info.object_type).collect();
+            assert_eq!(types, [ObjectType::Vmo, ObjectType::Channel]);
+            assert_eq!(buf.n_handles(), 0);
+            assert!(buf.take_handle(0).is_none());
+        }
+    }
+
+    #[test]
+    fn message_buf_clear_and_pool() {
+        let (p1, p2) = Channel::create(ChannelOpts::Normal).unwrap();
+        let event = Event::create(EventOpts::Default).unwrap();
+        let mut handles = vec![event.duplicate_handle(Rights::SAME_RIGHTS).unwrap().into_handle()];
+        assert!(p1.write(b"hello", &mut handles, 0).is_ok());
+
+        let mut pool = MessageBufPool::new(1);
+        let mut buf = pool.get();
+        assert!(p2.read(0, &mut buf).is_ok());
+        let capacity = buf.bytes.capacity();
+        pool.put(buf);
+        assert_eq!(pool.len(), 1);
+        pool.put(MessageBuf::new());
+        assert_eq!(pool.len(), 1);
+
+        // The returned buffer is empty, but keeps its capacity, and its handle has been closed.
+        let buf = pool.get();
+        assert!(pool.is_empty());
+        assert_eq!(buf.bytes(), b"");
         assert_eq!(buf.n_handles(), 0);
+        assert_eq!(buf.bytes.capacity(), capacity);
+        assert!(event.wait_handle(Signals::LAST_HANDLE, Deadline::INFINITE).is_ok());
+    }
+
+    #[test]
+    fn channel_drain() {
+        let (p1, p2) = Channel::create(ChannelOpts::Normal).unwrap();
+        let mut bufs = vec![];
+        assert_eq!(p2.drain(0, &mut bufs), Ok(0));
+
+        let mut empty = vec![];
+        for message in &[b"one", b"two"] {
+            assert!(p1.write(*message, &mut empty, 0).is_ok());
+        }
+        assert_eq!(p2.drain(0, &mut bufs), Ok(2));
+        assert_eq!(bufs[0].bytes(), b"one");
+        assert_eq!(bufs[1].bytes(), b"two");
 
-        assert!(server.join().is_ok());
+        assert!(p1.write(b"three", &mut empty, 0).is_ok());
+        drop(p1);
+        assert_eq!(p2.drain(0, &mut bufs), Err(Status::ErrPeerClosed));
+        assert_eq!(bufs.len(), 3);
+        assert_eq!(bufs[2].bytes(), b"three");
+    }
+
+    #[test]
+    fn channel_try_read() {
+        let (p1, p2) = Channel::create(ChannelOpts::Normal).unwrap();
+        let mut buf = MessageBuf::new();
+        assert_eq!(p2.try_read(0, &mut buf), Ok(false));
+
+        let mut empty = vec![];
+        assert!(p1.write(b"hello", &mut empty, 0).is_ok());
+        assert_eq!(p2.try_read(0, &mut buf), Ok(true));
+        assert_eq!(buf.bytes(), b"hello");
+
+        drop(p1);
+        assert_eq!(p2.try_read(0, &mut buf), Err(Status::ErrPeerClosed));
+    }
+
+    #[test]
+    fn channel_message_size() {
+        assert_eq!(Channel::check_message_size(CHANNEL_MAX_MSG_BYTES, CHANNEL_MAX_MSG_HANDLES),
+            Ok(()));
+        let error = Channel::check_message_size(CHANNEL_MAX_MSG_BYTES + 1, 0).unwrap_err();
+        assert_eq!(error.to_string(),
+            "channel message of 65537 bytes exceeds the limit of 65536 bytes");
+        let error = Channel::check_message_size(0, 65).unwrap_err();
+        assert_eq!(error.to_string(),
+            "channel message with 65 handles exceeds the limit of 64 handles");
+
+        let (p1, _p2) = Channel::create(ChannelOpts::Normal).unwrap();
+        let mut empty = vec![];
+        assert_eq!(p1.write(&vec![0; CHANNEL_MAX_MSG_BYTES + 1], &mut empty, 0),
+            Err(Status::ErrOutOfRange));
+        assert_eq!(p1.write_checked(&vec![0; CHANNEL_MAX_MSG_BYTES + 1], &mut empty, 0),
+            Err(ChannelWriteError::TooLarge(MessageTooLarge {
+                n_bytes: CHANNEL_MAX_MSG_BYTES + 1,
+                n_handles: 0,
+            })));
+        assert_eq!(p1.write_checked(b"hello", &mut empty, 0), Ok(()));
+    }
+
+    #[test]
+    fn channel_split() {
+        let (p1, p2) = Channel::create(ChannelOpts::Normal).unwrap();
+        let (writer, reader) = p1.split();
+        let writer_thread = thread::spawn(move || {
+            let mut empty = vec![];
+            assert!(writer.write(b"ping", &mut empty, 0).is_ok());
+        });
+        let mut buf = MessageBuf::new();
+        assert!(p2.read_deadline(0, &mut buf, Deadline::INFINITE).is_ok());
+        assert_eq!(buf.bytes(), b"ping");
+        assert!(writer_thread.join().is_ok());
+
+        // The channel stays open until the reader is also dropped.
+        let mut empty = vec![];
+        assert!(p2.write(b"pong", &mut empty, 0).is_ok());
+        assert!(reader.read(0, &mut buf).is_ok());
+        assert_eq!(buf.bytes(), b"pong");
+        drop(reader);
+        assert_eq!(p2.read(0, &mut buf), Err(Status::ErrPeerClosed));
+    }
+
+    #[test]
+    fn channel_messages() {
+        let (p1, p2) = Channel::create(ChannelOpts::Normal).unwrap();
+        let client = thread::spawn(move || {
+            for message in &[b"one", b"two"] {
+                assert!(p1.write(*message, &mut vec![], 0).is_ok());
+            }
+        });
+        let received: Vec<_> = p2.messages().map(|buf| buf.unwrap().bytes().to_vec()).collect();
+        assert_eq!(received, [b"one", b"two"]);
+        assert!(client.join().is_ok());
+    }
+
+    #[test]
+    fn message_buf_take_typed_handle() {
+        let (p1, p2) = Channel::create(ChannelOpts::Normal).unwrap();
+        let vmo = Vmo::create(0, VmoOpts::Default).unwrap();
+        assert!(p1.write(b"", &mut vec![vmo.into_handle()], 0).is_ok());
+
+        let mut buf = MessageBuf::new();
+        assert!(p2.read(0, &mut buf).is_ok());
+        assert_eq!(buf.take_typed_handle::<Event>(0).unwrap_err(), Status::ErrWrongType);
+        let vmo = buf.take_typed_handle::<Vmo>(0).unwrap();
+        assert_eq!(vmo.get_size(), Ok(0));
+        assert_eq!(buf.take_typed_handle::<Vmo>(0).unwrap_err(), Status::ErrNotFound);
+        assert_eq!(buf.take_typed_handle::<Vmo>(1).unwrap_err(), Status::ErrNotFound);
+    }
+
+    #[test]
+    fn channel_read_into() {
+        let (p1, p2) = Channel::create(ChannelOpts::Normal).unwrap();
+        let event = Event::create(EventOpts::Default).unwrap();
+        let mut handles = vec![event.into_handle()];
+        assert!(p1.write(b"hello", &mut handles, 0).is_ok());
+
+        let mut bytes = [0; 4];
+        let mut handles = [Handle::invalid(), Handle::invalid()];
+        assert_eq!(p2.read_into(0, &mut bytes, &mut handles), Err(Status::ErrBufferTooSmall));
+        let mut bytes = [0; 8];
+        assert_eq!(p2.read_into(0, &mut bytes, &mut handles), Ok((5, 1)));
+        assert_eq!(&bytes[..5], b"hello");
+        assert!(!handles[0].is_invalid());
+        assert!(handles[1].is_invalid());
+        assert_eq!(handles[0].object_type(), Ok(ObjectType::Event));
+    }
+
+    #[test]
+    fn channel_write_vectored() {
+        let (p1, p2) = Channel::create(ChannelOpts::Normal).unwrap();
+        let mut buf = MessageBuf::new();
+        for body in &[&b"body"[..], &b"longer body"[..]] {
+            let slices = [IoSlice::new(b"header:"), IoSlice::new(body)];
+            assert!(p1.write_vectored(&slices, &mut vec![], 0).is_ok());
+            assert!(p2.read(0, &mut buf).is_ok());
+            assert_eq!(&buf.bytes()[..7], b"header:");
+            assert_eq!(&buf.bytes()[7..], *body);
+        }
+    }
+
+    #[test]
+    fn channel_write_deadline() {
+        let ten_ms = Duration::from_millis(10);
+        let (p1, p2) = Channel::create(ChannelOpts::Normal).unwrap();
+        assert!(p1.write_deadline(b"hello", &mut vec![], 0, deadline_after(ten_ms)).is_ok());
+        drop(p2);
+        assert_eq!(p1.write_deadline(b"hello", &mut vec![], 0, deadline_after(ten_ms)),
+            Err(Status::ErrPeerClosed));
+    }
+
+    #[test]
+    fn channel_next_txid() {
+        let txid1 = Channel::next_txid();
+        let txid2 = Channel::next_txid();
+        assert!(txid1 != 0);
+        assert!(txid2 != 0);
+        assert!(txid1 != txid2);
     }
 }
@@ -0,0 +1,249 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! A helper for debugging a process through its debugger exception port.
+
+use {AsHandleRef, Deadline, ExceptionPacket, ExceptionPortOpts, ExceptionType, GeneralRegs};
+use {PacketContents, Port, PortOpts, Process, Rights, Status, Task, Thread};
+use {sys, into_result};
+
+/// A debugger attached to a process, which is sent every exception raised in it, as well as
+/// reports of its threads starting and exiting.
+///
+/// Each exception stops the thread which raised it, and is handed out by `wait` as a
+/// `StoppedThread`, whose registers and process memory can be inspected before it is resumed.
+/// The debugger is detached when dropped.
+#[derive(Debug)]
+pub struct Debugger<'a> {
+    process: &'a Process,
+    port: Port,
+    attached: bool,
+}
+
+impl<'a> Debugger<'a> {
+    /// Attach to `process` by binding its debugger exception port. Fails with
+    /// `ErrAlreadyBound` if another debugger is attached.
+    pub fn attach(process: &'a Process) -> Result<Debugger<'a>, Status> {
+        let port = Port::create(PortOpts::Default)?;
+        process.bind_exception_port(&port, 0, ExceptionPortOpts::Debugger)?;
+        Ok(Debugger { process, port, attached: true })
+    }
+
+    /// The process being debugged.
+    pub fn process(&self) -> &'a Process {
+        self.process
+    }
+
+    /// Wait for the next exception in the process, returning the thread which raised it.
+    /// Fails with `ErrTimedOut` if the deadline passes first.
+    ///
+    /// Exceptions raised by threads which have since died, such as ones killed while stopped,
+    /// are skipped, as there is no thread left to resume.
+    pub fn wait(&self, deadline: Deadline) -> Result<StoppedThread<'a>, Status> {
+        loop {
+            let packet = self.port.wait(deadline)?;
+            let exception = match packet.contents() {
+                PacketContents::Exception(exception) => exception,
+                _ => continue,
+            };
+            let thread = match self.process.get_child(exception.tid(), Rights::SAME_RIGHTS) {
+                Ok(thread) => Thread::from(thread),
+                Err(Status::ErrNotFound) | Err(Status::ErrBadState) => continue,
+                Err(status) => return Err(status),
+            };
+            return Ok(StoppedThread { process: self.process, thread, exception, resumed: false });
+        }
+    }
+
+    /// Detach from the process, returning any error from the kernel. Dropping the debugger
+    /// also detaches it, but ignores errors.
+    pub fn detach(mut self) -> Result<(), Status> {
+        self.attached = false;
+        self.process.unbind_exception_port(ExceptionPortOpts::Debugger)
+    }
+}
+
+impl<'a> Drop for Debugger<'a> {
+    fn drop(&mut self) {
+        if self.attached {
+            let _ = self.process.unbind_exception_port(ExceptionPortOpts::Debugger);
+        }
+    }
+}
+
+/// A thread stopped in an exception, as reported to a `Debugger`.
+///
+/// The thread stays stopped until it is resumed, either with `resume`, which treats the
+/// exception as handled, or with `resume_try_next`, which passes it on to the next exception
+/// port, as dropping it does too.
+#[derive(Debug)]
+pub struct StoppedThread<'a> {
+    process: &'a Process,
+    thread: Thread,
+    exception: ExceptionPacket,
+    resumed: bool,
+}
+
+impl<'a> StoppedThread<'a> {
+    /// The stopped thread.
+    pub fn thread(&self) -> &Thread {
+        &self.thread
+    }
+
+    /// The exception the thread stopped in.
+    pub fn exception(&self) -> &ExceptionPacket {
+        &self.exception
+    }
+
+    /// The type of the exception, or `None` if it is not a type this crate knows about.
+    pub fn exception_type(&self) -> Option<ExceptionType> {
+        self.exception.exception_type()
+    }
+
+    /// The general purpose registers of the thread.
+    pub fn registers(&self) -> Result<sys::zx_thread_state_general_regs_t, Status> {
+        self.thread.read_state::<GeneralRegs>()
+    }
+
+    /// Change the general purpose registers of the thread, taking effect when it is resumed.
+    pub fn set_registers(&self, regs: &sys::zx_thread_state_general_regs_t)
+        -> Result<(), Status>
+    {
+        self.thread.write_state::<GeneralRegs>(regs)
+    }
+
+    /// Read memory of the thread's process. See `Process::read_memory`.
+    pub fn read_memory(&self, vaddr: usize, buffer: &mut [u8]) -> Result<usize, Status> {
+        self.process.read_memory(vaddr, buffer)
+    }
+
+    /// Write memory of the thread's process. See `Process::write_memory`.
+    pub fn write_memory(&self, vaddr: usize, data: &[u8]) -> Result<usize, Status> {
+        self.process.write_memory(vaddr, data)
+    }
+
+    /// Resume the thread, treating the exception as handled, so that it continues from its
+    /// registers, as changed by `set_registers`.
+    ///
+    /// Wraps the
+    /// [zx_task_resume](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/task_resume.md)
+    /// syscall with `ZX_RESUME_EXCEPTION`.
+    pub fn resume(mut self) -> Result<(), Status> {
+        self.resume_with(sys::ZX_RESUME_EXCEPTION)
+    }
+
+    /// Resume the thread, passing the exception on to the next exception port, such as that of
+    /// the thread or process. If there is none, the process is killed.
+    ///
+    /// Wraps the
+    /// [zx_task_resume](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/task_resume.md)
+    /// syscall with `ZX_RESUME_EXCEPTION | ZX_RESUME_TRY_NEXT`.
+    pub fn resume_try_next(mut self) -> Result<(), Status> {
+        self.resume_with(sys::ZX_RESUME_EXCEPTION | sys::ZX_RESUME_TRY_NEXT)
+    }
+
+    fn resume_with(&mut self, opts: u32) -> Result<(), Status> {
+        self.resumed = true;
+        let status = unsafe { sys::zx_task_resume(self.thread.raw_handle(), opts) };
+        into_result(status, || ())
+    }
+}
+
+impl<'a> Drop for StoppedThread<'a> {
+    fn drop(&mut self) {
+        if !self.resumed {
+            let _ = self.resume_with(sys::ZX_RESUME_EXCEPTION | sys::ZX_RESUME_TRY_NEXT);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Duration, Mapping, ReadWrite, Signals, ThreadOpts, ThreadState, Vmo, VmoOpts};
+    use {deadline_after, nanosleep, process_self};
+    use std::ptr;
+
+    // The address the faulting thread writes to.
+    const FAULT_ADDR: usize = 0x10;
+
+    extern "C" fn fault(_arg1: usize, _arg2: usize) -> ! {
+        unsafe {
+            ptr::write_volatile(FAULT_ADDR as *mut u8, 0);
+            Thread::exit()
+        }
+    }
+
+    // Start a thread in the current process which faults straight away, returning it and the
+    // mapping of its stack.
+    fn start_faulter() -> (Thread, Mapping<'static, ReadWrite>) {
+        let thread = Thread::create(&process_self(), "faulter", ThreadOpts::Default).unwrap();
+        let vmo = Vmo::create(16 * 4096, VmoOpts::Default).unwrap();
+        let stack = unsafe { vmo.map::<ReadWrite>(0, 16 * 4096) }.unwrap();
+        let top = stack.addr() + stack.len();
+        let sp = if cfg!(target_arch = "x86_64") { top - 8 } else { top };
+        unsafe {
+            thread.start(fault as *const () as usize, sp, 0, 0).unwrap();
+        }
+        (thread, stack)
+    }
+
+    #[test]
+    fn stop_on_fault() {
+        let process = process_self();
+        let debugger = Debugger::attach(&process).unwrap();
+        assert_eq!(Debugger::attach(&process).unwrap_err(), Status::ErrAlreadyBound);
+
+        let (thread, stack) = start_faulter();
+        let koid = thread.basic_info().unwrap().koid;
+
+        // Other threads of the test harness may start or exit meanwhile; let them carry on.
+        let stopped = loop {
+            let stopped = debugger.wait(Deadline::INFINITE).unwrap();
+            if stopped.exception().tid() == koid
+                && stopped.exception_type() == Some(ExceptionType::FatalPageFault) {
+                break stopped;
+            }
+            stopped.resume().unwrap();
+        };
+        assert!(stopped.registers().is_ok());
+        let mut byte = [0];
+        assert_eq!(stopped.read_memory(stack.as_ptr() as usize, &mut byte), Ok(1));
+        assert_eq!(stopped.read_memory(FAULT_ADDR, &mut byte), Err(Status::ErrNoMemory));
+
+        assert_eq!(stopped.thread().kill(), Ok(()));
+        drop(stopped);
+        assert_eq!(debugger.detach(), Ok(()));
+    }
+
+    #[test]
+    fn skip_dead_threads() {
+        let process = process_self();
+        let debugger = Debugger::attach(&process).unwrap();
+        let (thread, _stack) = start_faulter();
+        let koid = thread.basic_info().unwrap().koid;
+
+        // Once the thread has stopped in its fault, kill it before the exception is collected.
+        while thread.info().unwrap().state != Some(ThreadState::Blocked) {
+            nanosleep(deadline_after(Duration::from_millis(1)));
+        }
+        assert_eq!(thread.kill(), Ok(()));
+        assert!(thread.wait_handle(Signals::THREAD_TERMINATED, Deadline::INFINITE).is_ok());
+
+        // Its exception is skipped, rather than failing the wait.
+        loop {
+            match debugger.wait(deadline_after(Duration::from_millis(10))) {
+                Ok(stopped) => {
+                    assert!(stopped.exception().tid() != koid);
+                    stopped.resume().unwrap();
+                }
+                Err(status) => {
+                    assert_eq!(status, Status::ErrTimedOut);
+                    break;
+                }
+            }
+        }
+        assert_eq!(debugger.detach(), Ok(()));
+    }
+}
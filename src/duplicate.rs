@@ -0,0 +1,131 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Helpers for duplicating handles.
+
+use {AsHandleRef, Handle, HandleBased, Rights, Status};
+
+/// A builder for duplicating a handle while removing some of its rights, created by
+/// `HandleBased::duplicate_with`.
+///
+/// The rights of the new handle are computed from the current rights of the original handle,
+/// so for example
+///
+/// ```no_run
+/// # use zircon::{HandleBased, Rights, Vmo, VmoOpts};
+/// # let vmo = Vmo::create(0, VmoOpts::Default).unwrap();
+/// let read_only = vmo.duplicate_with().remove(Rights::WRITE).same_rights_otherwise().build();
+/// ```
+///
+/// produces a handle with all of the rights of `vmo` except `WRITE`.
+#[derive(Debug)]
+pub struct DuplicateOptions<'a, T: 'a> {
+    handle: &'a T,
+    remove: Rights,
+    retain: Option<Rights>,
+}
+
+impl<'a, T: HandleBased> DuplicateOptions<'a, T> {
+    pub(crate) fn new(handle: &'a T) -> Self {
+        DuplicateOptions { handle, remove: Rights::NONE, retain: None }
+    }
+
+    /// Remove the given rights from the new handle.
+    pub fn remove(mut self, rights: Rights) -> Self {
+        self.remove |= rights;
+        self
+    }
+
+    /// Restrict the new handle to (at most) the given rights.
+    pub fn retain(mut self, rights: Rights) -> Self {
+        self.retain = Some(self.retain.map_or(rights, |retain| retain & rights));
+        self
+    }
+
+    /// Keep all rights of the original handle other than those explicitly removed, undoing any
+    /// earlier call to `retain`.
+    pub fn same_rights_otherwise(mut self) -> Self {
+        self.retain = None;
+        self
+    }
+
+    /// The rights the new handle will have, given the rights of the original handle.
+    pub fn rights_for(&self, current: Rights) -> Rights {
+        let rights = current - self.remove;
+        match self.retain {
+            Some(retain) => rights & retain,
+            None => rights,
+        }
+    }
+
+    /// Look up the rights of the original handle and create the duplicate.
+    ///
+    /// Wraps the
+    /// [zx_object_get_info](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_get_info.md)
+    /// syscall with `ZX_INFO_HANDLE_BASIC`, followed by
+    /// [zx_handle_duplicate](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/handle_duplicate.md).
+    pub fn build(self) -> Result<T, Status> {
+        let current = self.handle.basic_info()?.rights;
+        self.handle.duplicate_handle(self.rights_for(current))
+    }
+}
+
+/// Duplicate each of a set of handles with the given rights.
+///
+/// Either all of the handles are duplicated, or none are: if any duplication fails, the
+/// duplicates already made are closed and the error is returned.
+///
+/// Wraps the
+/// [zx_handle_duplicate](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/handle_duplicate.md)
+/// syscall.
+pub fn duplicate_handles(handles: &[&dyn AsHandleRef], rights: Rights)
+    -> Result<Vec<Handle>, Status>
+{
+    // If a duplication fails, dropping the partial vector closes the earlier duplicates.
+    handles.iter().map(|handle| handle.as_handle_ref().duplicate(rights)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Event, EventOpts, Vmo, VmoOpts};
+
+    #[test]
+    fn rights_for() {
+        let vmo = Vmo::create(0, VmoOpts::Default).unwrap();
+        let rwx = Rights::READ | Rights::WRITE | Rights::EXECUTE;
+        assert_eq!(vmo.duplicate_with().remove(Rights::WRITE).rights_for(rwx),
+            Rights::READ | Rights::EXECUTE);
+        assert_eq!(vmo.duplicate_with().retain(Rights::READ | Rights::WRITE).rights_for(rwx),
+            Rights::READ | Rights::WRITE);
+        assert_eq!(vmo.duplicate_with().retain(Rights::READ).same_rights_otherwise()
+            .rights_for(rwx), rwx);
+    }
+
+    #[test]
+    fn duplicate_without_write() {
+        let vmo = Vmo::create(5, VmoOpts::Default).unwrap();
+        let read_only = vmo.duplicate_with().remove(Rights::WRITE).same_rights_otherwise()
+            .build().unwrap();
+        let rights = read_only.basic_info().unwrap().rights;
+        assert!(rights.contains(Rights::READ));
+        assert!(!rights.contains(Rights::WRITE));
+        assert_eq!(rights, vmo.basic_info().unwrap().rights - Rights::WRITE);
+        assert_eq!(read_only.write(b"hello", 0), Err(Status::ErrAccessDenied));
+    }
+
+    #[test]
+    fn duplicate_several_handles() {
+        let vmo = Vmo::create(0, VmoOpts::Default).unwrap();
+        let event = Event::create(EventOpts::Default).unwrap();
+        let duplicates = duplicate_handles(&[&vmo, &event], Rights::SAME_RIGHTS).unwrap();
+        assert_eq!(duplicates.len(), 2);
+        assert_eq!(duplicates[0].basic_info().unwrap().koid, vmo.basic_info().unwrap().koid);
+        assert_eq!(duplicates[1].basic_info().unwrap().koid, event.basic_info().unwrap().koid);
+
+        let invalid = Handle::invalid();
+        assert_eq!(duplicate_handles(&[&vmo, &invalid], Rights::SAME_RIGHTS),
+            Err(Status::ErrBadHandle));
+    }
+}
@@ -4,16 +4,17 @@
 
 //! Type-safe bindings for Zircon event objects.
 
-use {AsHandleRef, Cookied, HandleBased, Handle, HandleRef, Status};
+use {AsHandleRef, Cookied, Deadline, HandleBased, Handle, HandleRef, ObjectType, Signals};
+use Status;
 use {sys, into_result};
 
 /// An object representing a Zircon
 /// [event object](https://fuchsia.googlesource.com/zircon/+/master/docs/objects/event.md).
 ///
 /// As essentially a subtype of `Handle`, it can be freely interconverted.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Eq, PartialEq)]
 pub struct Event(Handle);
-impl_handle_based!(Event);
+impl_handle_based!(Event, ObjectType::Event);
 impl Cookied for Event {}
 
 impl Event {
@@ -23,7 +24,26 @@ impl Event {
     pub fn create(options: EventOpts) -> Result<Event, Status> {
         let mut out = 0;
         let status = unsafe { sys::zx_event_create(options as u32, &mut out) };
-        into_result(status, || Self::from(Handle(out)))
+        into_result(status, || Self::from(Handle::owned(out)))
+    }
+
+    /// Assert `Signals::EVENT_SIGNALED` on the event, waking anyone waiting for it. It stays
+    /// asserted until `clear` is called.
+    ///
+    /// The user signals can be set with `signal_handle`, which does not allow this one.
+    pub fn signal(&self) -> Result<(), Status> {
+        self.as_handle_ref().signal(Signals::NONE, Signals::EVENT_SIGNALED)
+    }
+
+    /// Deassert `Signals::EVENT_SIGNALED` on the event.
+    pub fn clear(&self) -> Result<(), Status> {
+        self.as_handle_ref().signal(Signals::EVENT_SIGNALED, Signals::NONE)
+    }
+
+    /// Wait until `Signals::EVENT_SIGNALED` is asserted on the event, or `deadline` passes.
+    /// Fails with `ErrTimedOut` if the deadline passes first.
+    pub fn wait_signaled(&self, deadline: Deadline) -> Result<(), Status> {
+        self.wait_handle(Signals::EVENT_SIGNALED, deadline).map(|_| ())
     }
 }
 
@@ -40,3 +60,40 @@ impl Default for EventOpts {
         EventOpts::Default
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signal_and_clear() {
+        let event = Event::create(EventOpts::Default).unwrap();
+        let now = Deadline::from_nanos(0);
+        assert_eq!(event.wait_handle(Signals::EVENT_SIGNALED, now), Err(Status::ErrTimedOut));
+
+        assert_eq!(event.signal(), Ok(()));
+        assert_eq!(event.wait_handle(Signals::EVENT_SIGNALED, now),
+            Ok(Signals::EVENT_SIGNALED | Signals::LAST_HANDLE));
+
+        assert_eq!(event.clear(), Ok(()));
+        assert_eq!(event.wait_handle(Signals::EVENT_SIGNALED, now), Err(Status::ErrTimedOut));
+    }
+
+    #[test]
+    fn wait_signaled() {
+        let event = Event::create(EventOpts::Default).unwrap();
+        assert_eq!(event.wait_signaled(Deadline::from_nanos(0)), Err(Status::ErrTimedOut));
+        assert_eq!(event.signal(), Ok(()));
+        assert_eq!(event.wait_signaled(Deadline::INFINITE), Ok(()));
+    }
+
+    #[test]
+    fn user_signals() {
+        let event = Event::create(EventOpts::Default).unwrap();
+        assert_eq!(event.signal_handle(Signals::NONE, Signals::USER_1), Ok(()));
+        assert_eq!(event.wait_handle(Signals::USER_1, Deadline::from_nanos(0)),
+            Ok(Signals::USER_1 | Signals::LAST_HANDLE));
+        assert_eq!(event.signal_handle(Signals::NONE, Signals::EVENT_SIGNALED),
+            Err(Status::ErrInvalidArgs));
+    }
+}
@@ -3,17 +3,24 @@
 // found in the LICENSE file.
 
 //! Type-safe bindings for Zircon event pairs.
+//!
+//! An event pair is commonly used as a liveness token: one end is handed to another party,
+//! which keeps it for as long as it is interested, and the holder of the other end learns that
+//! the party has gone away, whether by closing the handle or by exiting, when
+//! `Signals::EPAIR_CLOSED` is asserted. Use `wait_peer_closed` to block until then, or wait for
+//! the signal asynchronously on a port alongside other work.
 
-use {AsHandleRef, Cookied, HandleBased, Handle, HandleRef, Peered, Status};
-use {sys, into_result};
+use {AsHandleRef, Cookied, Deadline, HandleBased, Handle, HandleRef, ObjectType, Peered};
+use {Signals, Status};
+use {sys, into_result, is_asserted};
 
 /// An object representing a Zircon
 /// [event pair](https://fuchsia.googlesource.com/zircon/+/master/docs/concepts.md#Other-IPC_Events_Event-Pairs_and-User-Signals).
 ///
 /// As essentially a subtype of `Handle`, it can be freely interconverted.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Eq, PartialEq)]
 pub struct EventPair(Handle);
-impl_handle_based!(EventPair);
+impl_handle_based!(EventPair, ObjectType::EventPair);
 impl Peered for EventPair {}
 impl Cookied for EventPair {}
 
@@ -26,8 +33,41 @@ impl EventPair {
         let mut out1 = 0;
         let status = unsafe { sys::zx_eventpair_create(options as u32, &mut out0, &mut out1) };
         into_result(status, ||
-            (Self::from(Handle(out0)),
-                Self::from(Handle(out1))))
+            (Self::from(Handle::owned(out0)),
+                Self::from(Handle::owned(out1))))
+    }
+
+    /// Assert `Signals::EPAIR_SIGNALED` on the peer. It stays asserted until `clear_peer` is
+    /// called.
+    ///
+    /// The user signals can be set on the peer with `signal_peer`, which does not allow this one.
+    pub fn notify_peer(&self) -> Result<(), Status> {
+        self.signal_peer_unchecked(Signals::NONE, Signals::EPAIR_SIGNALED)
+    }
+
+    /// Deassert `Signals::EPAIR_SIGNALED` on the peer.
+    pub fn clear_peer(&self) -> Result<(), Status> {
+        self.signal_peer_unchecked(Signals::EPAIR_SIGNALED, Signals::NONE)
+    }
+
+    /// Whether the other end of the pair has been closed.
+    pub fn is_peer_closed(&self) -> Result<bool, Status> {
+        is_asserted(self.as_handle_ref(), Signals::EPAIR_CLOSED)
+    }
+
+    /// Wait until the other end of the pair has been closed, or `deadline` passes. Fails with
+    /// `ErrTimedOut` if the deadline passes first.
+    pub fn wait_peer_closed(&self, deadline: Deadline) -> Result<(), Status> {
+        self.wait_handle(Signals::EPAIR_CLOSED, deadline).map(|_| ())
+    }
+
+    fn signal_peer_unchecked(&self, clear_mask: Signals, set_mask: Signals)
+        -> Result<(), Status>
+    {
+        let status = unsafe {
+            sys::zx_object_signal_peer(self.raw_handle(), clear_mask.bits(), set_mask.bits())
+        };
+        into_result(status, || ())
     }
 }
 
@@ -48,28 +88,53 @@ impl Default for EventPairOpts {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use {Duration, ZX_SIGNAL_LAST_HANDLE, ZX_SIGNAL_NONE, ZX_USER_SIGNAL_0};
+    use Duration;
     use deadline_after;
 
     #[test]
     fn wait_and_signal_peer() {
         let (p1, p2) = EventPair::create(EventPairOpts::Default).unwrap();
-        let eighty_ms: Duration = 80_000_000;
+        let eighty_ms = Duration::from_millis(80);
 
         // Waiting on one without setting any signal should time out.
-        assert_eq!(p2.wait_handle(ZX_USER_SIGNAL_0, deadline_after(eighty_ms)), Err(Status::ErrTimedOut));
+        assert_eq!(p2.wait_handle(Signals::USER_0, deadline_after(eighty_ms)), Err(Status::ErrTimedOut));
 
         // If we set a signal, we should be able to wait for it.
-        assert!(p1.signal_peer(ZX_SIGNAL_NONE, ZX_USER_SIGNAL_0).is_ok());
-        assert_eq!(p2.wait_handle(ZX_USER_SIGNAL_0, deadline_after(eighty_ms)).unwrap(),
-            ZX_USER_SIGNAL_0 | ZX_SIGNAL_LAST_HANDLE);
+        assert!(p1.signal_peer(Signals::NONE, Signals::USER_0).is_ok());
+        assert_eq!(p2.wait_handle(Signals::USER_0, deadline_after(eighty_ms)).unwrap(),
+            Signals::USER_0 | Signals::LAST_HANDLE);
 
         // Should still work, signals aren't automatically cleared.
-        assert_eq!(p2.wait_handle(ZX_USER_SIGNAL_0, deadline_after(eighty_ms)).unwrap(),
-            ZX_USER_SIGNAL_0 | ZX_SIGNAL_LAST_HANDLE);
+        assert_eq!(p2.wait_handle(Signals::USER_0, deadline_after(eighty_ms)).unwrap(),
+            Signals::USER_0 | Signals::LAST_HANDLE);
 
         // Now clear it, and waiting should time out again.
-        assert!(p1.signal_peer(ZX_USER_SIGNAL_0, ZX_SIGNAL_NONE).is_ok());
-        assert_eq!(p2.wait_handle(ZX_USER_SIGNAL_0, deadline_after(eighty_ms)), Err(Status::ErrTimedOut));
+        assert!(p1.signal_peer(Signals::USER_0, Signals::NONE).is_ok());
+        assert_eq!(p2.wait_handle(Signals::USER_0, deadline_after(eighty_ms)), Err(Status::ErrTimedOut));
+    }
+
+    #[test]
+    fn notify_peer() {
+        let (p1, p2) = EventPair::create(EventPairOpts::Default).unwrap();
+        let now = Deadline::from_nanos(0);
+        assert_eq!(p1.notify_peer(), Ok(()));
+        assert!(p2.wait_handle(Signals::EPAIR_SIGNALED, now).is_ok());
+        assert_eq!(p1.wait_handle(Signals::EPAIR_SIGNALED, now), Err(Status::ErrTimedOut));
+
+        assert_eq!(p1.clear_peer(), Ok(()));
+        assert_eq!(p2.wait_handle(Signals::EPAIR_SIGNALED, now), Err(Status::ErrTimedOut));
+    }
+
+    #[test]
+    fn peer_closed() {
+        let (p1, p2) = EventPair::create(EventPairOpts::Default).unwrap();
+        assert_eq!(p1.is_peer_closed(), Ok(false));
+        assert_eq!(p1.wait_peer_closed(deadline_after(Duration::from_millis(10))),
+            Err(Status::ErrTimedOut));
+
+        drop(p2);
+        assert_eq!(p1.is_peer_closed(), Ok(true));
+        assert_eq!(p1.wait_peer_closed(Deadline::INFINITE), Ok(()));
+        assert_eq!(p1.notify_peer(), Err(Status::ErrPeerClosed));
     }
 }
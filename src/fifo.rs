@@ -4,15 +4,23 @@
 
 //! Type-safe bindings for Zircon fifo objects.
 
-use {AsHandleRef, HandleBased, Handle, HandleRef, Status};
-use {sys, into_result};
+use {AsHandleRef, Deadline, HandleBased, Handle, HandleRef, ObjectType, Peered, Signals};
+use Status;
+use {sys, into_result, retry_when_ready};
 
 /// An object representing a Zircon fifo.
 ///
+/// A fifo carries fixed-size elements, whose size is given when it is created. Reads and writes
+/// transfer whole elements only, and as many as fit, so they may transfer fewer than asked for;
+/// the number actually transferred is returned. `TypedFifo`, which knows the element size, also
+/// has `write_all_deadline` and `read_exact_deadline`, which loop until everything is
+/// transferred.
+///
 /// As essentially a subtype of `Handle`, it can be freely interconverted.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Eq, PartialEq)]
 pub struct Fifo(Handle);
-impl_handle_based!(Fifo);
+impl_handle_based!(Fifo, ObjectType::Fifo);
+impl Peered for Fifo {}
 
 impl Fifo {
     /// Create a pair of fifos and return their endpoints. Writing to one endpoint enqueues an
@@ -27,12 +35,16 @@ impl Fifo {
         let status = unsafe {
             sys::zx_fifo_create(elem_count, elem_size, options as u32, &mut out0, &mut out1)
         };
-        into_result(status, || (Self::from(Handle(out0)), Self::from(Handle(out1))))
+        into_result(status, || (Self::from(Handle::owned(out0)), Self::from(Handle::owned(out1))))
     }
 
     /// Attempts to write some number of elements into the fifo. The number of bytes written will be
     /// rounded down to a multiple of the fifo's element size.
-    /// Return value (on success) is number of elements actually written.
+    /// Return value (on success) is number of elements actually written, which is fewer than
+    /// given if the fifo does not have room for them all.
+    ///
+    /// Fails with `ErrOutOfRange` if `bytes` is shorter than one element, `ErrShouldWait` if the
+    /// fifo is full, or `ErrPeerClosed` if the other end has been closed.
     ///
     /// Wraps
     /// [zx_fifo_write](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/fifo_write.md).
@@ -45,9 +57,22 @@ impl Fifo {
         into_result(status, || num_entries_written)
     }
 
+    /// Writes some number of elements into the fifo, waiting for it to become writable if
+    /// necessary. Return value (on success) is number of elements actually written. Fails with
+    /// `ErrTimedOut` if it does not become writable before the deadline, or `ErrPeerClosed` if
+    /// the peer is closed.
+    pub fn write_deadline(&self, bytes: &[u8], deadline: Deadline) -> Result<u32, Status> {
+        retry_when_ready(self.as_handle_ref(), Signals::FIFO_WRITABLE, Signals::FIFO_PEER_CLOSED,
+            deadline, || self.write(bytes))
+    }
+
     /// Attempts to read some number of elements out of the fifo. The number of bytes read will
     /// always be a multiple of the fifo's element size.
-    /// Return value (on success) is number of elements actually read.
+    /// Return value (on success) is number of elements actually read, which is fewer than
+    /// `bytes` has room for if the fifo holds fewer.
+    ///
+    /// Fails with `ErrOutOfRange` if `bytes` is shorter than one element, `ErrShouldWait` if the
+    /// fifo is empty, or `ErrPeerClosed` if it is empty and the other end has been closed.
     ///
     /// Wraps
     /// [zx_fifo_read](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/fifo_read.md).
@@ -59,6 +84,15 @@ impl Fifo {
         };
         into_result(status, || num_entries_read)
     }
+
+    /// Reads some number of elements out of the fifo, waiting for it to become readable if
+    /// necessary. Return value (on success) is number of elements actually read. Fails with
+    /// `ErrTimedOut` if it does not become readable before the deadline, or `ErrPeerClosed` if
+    /// it is empty and the peer is closed.
+    pub fn read_deadline(&self, bytes: &mut [u8], deadline: Deadline) -> Result<u32, Status> {
+        retry_when_ready(self.as_handle_ref(), Signals::FIFO_READABLE, Signals::FIFO_PEER_CLOSED,
+            deadline, || self.read(bytes))
+    }
 }
 
 /// Options for creating a fifo pair.
@@ -78,6 +112,8 @@ impl Default for FifoOpts {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use {Duration, deadline_after};
+    use std::thread;
 
     #[test]
     fn fifo_basic() {
@@ -104,4 +140,68 @@ mod tests {
         // Reading again should fail as the fifo is empty.
         assert_eq!(fifo2.read(&mut read_vec), Err(Status::ErrShouldWait));
     }
+
+    #[test]
+    fn fifo_partial_read() {
+        let (fifo1, fifo2) = Fifo::create(4, 2, FifoOpts::Default).unwrap();
+        assert_eq!(fifo1.write(b"abcdef"), Ok(3));
+
+        // A buffer with room for one and a half elements reads only one.
+        let mut read_vec = vec![0; 3];
+        assert_eq!(fifo2.read(&mut read_vec), Ok(1));
+        assert_eq!(&read_vec[..2], b"ab");
+        assert_eq!(fifo2.read(&mut read_vec[..1]), Err(Status::ErrOutOfRange));
+
+        // What was written before the other end closed can still be read.
+        drop(fifo1);
+        let mut read_vec = vec![0; 8];
+        assert_eq!(fifo2.read(&mut read_vec), Ok(2));
+        assert_eq!(&read_vec[..4], b"cdef");
+        assert_eq!(fifo2.read(&mut read_vec), Err(Status::ErrPeerClosed));
+    }
+
+    #[test]
+    fn fifo_signal_peer() {
+        let (fifo1, fifo2) = Fifo::create(1, 1, FifoOpts::Default).unwrap();
+        assert_eq!(fifo1.signal_peer(Signals::NONE, Signals::USER_0), Ok(()));
+        assert!(fifo2.wait_handle(Signals::USER_0, deadline_after(Duration::from_millis(10)))
+            .is_ok());
+    }
+
+    #[test]
+    fn fifo_write_deadline() {
+        let ten_ms = Duration::from_millis(10);
+        let (fifo1, fifo2) = Fifo::create(2, 2, FifoOpts::Default).unwrap();
+        assert_eq!(fifo1.write_deadline(b"abcd", deadline_after(ten_ms)), Ok(2));
+
+        // The fifo is full, so this times out until the other end reads.
+        assert_eq!(fifo1.write_deadline(b"ef", deadline_after(ten_ms)), Err(Status::ErrTimedOut));
+        let mut read_vec = vec![0; 2];
+        assert_eq!(fifo2.read(&mut read_vec), Ok(1));
+        assert_eq!(fifo1.write_deadline(b"ef", deadline_after(ten_ms)), Ok(1));
+
+        drop(fifo2);
+        assert_eq!(fifo1.write_deadline(b"gh", deadline_after(ten_ms)),
+            Err(Status::ErrPeerClosed));
+    }
+
+    #[test]
+    fn fifo_read_deadline() {
+        let ten_ms = Duration::from_millis(10);
+        let (fifo1, fifo2) = Fifo::create(2, 2, FifoOpts::Default).unwrap();
+        let mut read_vec = vec![0; 4];
+        assert_eq!(fifo2.read_deadline(&mut read_vec, deadline_after(ten_ms)),
+            Err(Status::ErrTimedOut));
+
+        let writer = thread::spawn(move || {
+            assert_eq!(fifo1.write(b"ab"), Ok(1));
+        });
+        assert_eq!(fifo2.read_deadline(&mut read_vec, Deadline::INFINITE), Ok(1));
+        assert_eq!(&read_vec[..2], b"ab");
+
+        // Once the writer has gone, reading fails instead of waiting.
+        writer.join().unwrap();
+        assert_eq!(fifo2.read_deadline(&mut read_vec, Deadline::INFINITE),
+            Err(Status::ErrPeerClosed));
+    }
 }
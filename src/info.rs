@@ -0,0 +1,382 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Type-safe queries for the Zircon `zx_object_get_info` syscall.
+
+use {HandleRef, Koid, ObjectType, Rights, Status};
+use {sys, into_result};
+use std::{cmp, mem};
+
+/// A topic which may be queried with `zx_object_get_info`, together with the type of the records
+/// the kernel returns for it.
+///
+/// # Safety
+///
+/// The kernel writes records directly into a buffer of `Record` values, so `Record` must have
+/// exactly the layout of the C record type the kernel uses for `TOPIC`, and every bit pattern
+/// the kernel may write must be a valid `Record`.
+pub unsafe trait ObjectQuery {
+    /// The topic passed to the kernel.
+    const TOPIC: sys::zx_object_info_topic_t;
+    /// The type of the records returned for this topic.
+    type Record: Copy;
+}
+
+/// Query the kernel for information about an object, returning all of the available records.
+///
+/// The size of the buffer is negotiated with the kernel: if more records are available than fit
+/// in the buffer (which can happen if, say, a job gains children between calls), the buffer is
+/// grown and the query repeated.
+///
+/// Wraps the
+/// [zx_object_get_info](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_get_info.md)
+/// syscall.
+pub fn object_get_info<Q: ObjectQuery>(handle: HandleRef) -> Result<Vec<Q::Record>, Status> {
+    let mut records: Vec<Q::Record> = Vec::new();
+    loop {
+        let mut actual = 0;
+        let mut avail = 0;
+        let status = unsafe {
+            sys::zx_object_get_info(handle.raw_handle(), Q::TOPIC,
+                records.as_mut_ptr() as *mut u8, records.capacity() * mem::size_of::<Q::Record>(),
+                &mut actual, &mut avail)
+        };
+        // Topics with a single record fail, rather than returning nothing, if there is no room.
+        if status != sys::ZX_ERR_BUFFER_TOO_SMALL {
+            into_result(status, || ())?;
+            if actual == avail {
+                unsafe { records.set_len(actual); }
+                return Ok(records);
+            }
+        }
+        records.reserve(cmp::max(avail, records.capacity() + 1));
+    }
+}
+
+/// Query the kernel for information about an object, for topics which always return exactly
+/// one record.
+///
+/// Wraps the
+/// [zx_object_get_info](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_get_info.md)
+/// syscall.
+pub fn object_get_info_single<Q: ObjectQuery>(handle: HandleRef) -> Result<Q::Record, Status> {
+    let mut actual = 0;
+    let mut avail = 0;
+    unsafe {
+        let mut record: Q::Record = mem::zeroed();
+        let status = sys::zx_object_get_info(handle.raw_handle(), Q::TOPIC,
+            &mut record as *mut Q::Record as *mut u8, mem::size_of::<Q::Record>(),
+            &mut actual, &mut avail);
+        into_result(status, || record)
+    }
+}
+
+/// Basic information about a handle and the object it refers to.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct HandleBasicInfo {
+    /// The koid of the object.
+    pub koid: Koid,
+    /// The rights of the handle.
+    pub rights: Rights,
+    /// The type of the object.
+    pub object_type: ObjectType,
+    /// The koid of a related object, such as the other end of a channel, or zero.
+    pub related_koid: Koid,
+}
+
+impl From<sys::zx_info_handle_basic_t> for HandleBasicInfo {
+    fn from(info: sys::zx_info_handle_basic_t) -> Self {
+        HandleBasicInfo {
+            koid: info.koid,
+            rights: Rights::from_raw(info.rights),
+            object_type: ObjectType::from_raw(info.type_),
+            related_koid: info.related_koid,
+        }
+    }
+}
+
+/// Query for basic information about a handle (`ZX_INFO_HANDLE_BASIC`).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct HandleBasicQuery;
+
+unsafe impl ObjectQuery for HandleBasicQuery {
+    const TOPIC: sys::zx_object_info_topic_t = sys::ZX_INFO_HANDLE_BASIC;
+    type Record = sys::zx_info_handle_basic_t;
+}
+
+/// Query for the koids of the child jobs of a job (`ZX_INFO_JOB_CHILDREN`).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct JobChildrenQuery;
+
+unsafe impl ObjectQuery for JobChildrenQuery {
+    const TOPIC: sys::zx_object_info_topic_t = sys::ZX_INFO_JOB_CHILDREN;
+    type Record = Koid;
+}
+
+/// Query for the koids of the processes in a job (`ZX_INFO_JOB_PROCESSES`).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct JobProcessesQuery;
+
+unsafe impl ObjectQuery for JobProcessesQuery {
+    const TOPIC: sys::zx_object_info_topic_t = sys::ZX_INFO_JOB_PROCESSES;
+    type Record = Koid;
+}
+
+/// Query for the koids of the threads in a process (`ZX_INFO_PROCESS_THREADS`).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ProcessThreadsQuery;
+
+unsafe impl ObjectQuery for ProcessThreadsQuery {
+    const TOPIC: sys::zx_object_info_topic_t = sys::ZX_INFO_PROCESS_THREADS;
+    type Record = Koid;
+}
+
+/// Information about the buffers of a socket.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SocketInfo {
+    /// The options the socket was created with.
+    pub options: u32,
+    /// The maximum number of bytes which can be queued for this end to read.
+    pub rx_buf_max: usize,
+    /// The number of bytes queued for this end to read.
+    pub rx_buf_size: usize,
+    /// The maximum number of bytes which can be queued for the peer to read.
+    pub tx_buf_max: usize,
+    /// The number of bytes written by this end which the peer has not yet read.
+    pub tx_buf_size: usize,
+}
+
+impl From<sys::zx_info_socket_t> for SocketInfo {
+    fn from(info: sys::zx_info_socket_t) -> Self {
+        SocketInfo {
+            options: info.options,
+            rx_buf_max: info.rx_buf_max,
+            rx_buf_size: info.rx_buf_size,
+            tx_buf_max: info.tx_buf_max,
+            tx_buf_size: info.tx_buf_size,
+        }
+    }
+}
+
+/// Query for information about the buffers of a socket (`ZX_INFO_SOCKET`).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SocketQuery;
+
+unsafe impl ObjectQuery for SocketQuery {
+    const TOPIC: sys::zx_object_info_topic_t = sys::ZX_INFO_SOCKET;
+    type Record = sys::zx_info_socket_t;
+}
+
+/// Information about a virtual memory object, for memory accounting.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct VmoInfo {
+    /// The koid of the object.
+    pub koid: Koid,
+    /// The name of the object.
+    pub name: String,
+    /// The size of the object in bytes.
+    pub size_bytes: u64,
+    /// The koid of the object this is a clone of, or zero.
+    pub parent_koid: Koid,
+    /// The number of clones of the object.
+    pub num_children: usize,
+    /// The number of times the object is mapped.
+    pub num_mappings: usize,
+    /// The number of processes with a handle to or mapping of the object.
+    pub share_count: usize,
+    /// The `ZX_INFO_VMO_*` flags describing the object.
+    pub flags: u32,
+    /// The number of bytes of memory currently committed to the object.
+    pub committed_bytes: u64,
+    /// The rights of the handle used for the query.
+    pub rights: Rights,
+}
+
+impl VmoInfo {
+    /// Whether the object can be resized.
+    pub fn is_resizable(&self) -> bool {
+        self.flags & sys::ZX_INFO_VMO_RESIZABLE != 0
+    }
+
+    /// Whether the object is a copy-on-write clone of another.
+    pub fn is_cow_clone(&self) -> bool {
+        self.flags & sys::ZX_INFO_VMO_IS_COW_CLONE != 0
+    }
+}
+
+impl From<sys::zx_info_vmo_t> for VmoInfo {
+    fn from(info: sys::zx_info_vmo_t) -> Self {
+        let name_len = info.name.iter().position(|&b| b == 0).unwrap_or(info.name.len());
+        VmoInfo {
+            koid: info.koid,
+            name: String::from_utf8_lossy(&info.name[..name_len]).into_owned(),
+            size_bytes: info.size_bytes,
+            parent_koid: info.parent_koid,
+            num_children: info.num_children,
+            num_mappings: info.num_mappings,
+            share_count: info.share_count,
+            flags: info.flags,
+            committed_bytes: info.committed_bytes,
+            rights: Rights::from_raw(info.handle_rights),
+        }
+    }
+}
+
+/// Query for information about a virtual memory object (`ZX_INFO_VMO`).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct VmoQuery;
+
+unsafe impl ObjectQuery for VmoQuery {
+    const TOPIC: sys::zx_object_info_topic_t = sys::ZX_INFO_VMO;
+    type Record = sys::zx_info_vmo_t;
+}
+
+/// The state of a process.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ProcessInfo {
+    /// The code the process exited with, if it has exited.
+    pub return_code: i64,
+    /// Whether the process has been started.
+    pub started: bool,
+    /// Whether the process has exited.
+    pub exited: bool,
+    /// Whether a debugger is attached to the process.
+    pub debugger_attached: bool,
+}
+
+impl From<sys::zx_info_process_t> for ProcessInfo {
+    fn from(info: sys::zx_info_process_t) -> Self {
+        ProcessInfo {
+            return_code: info.return_code,
+            started: info.started,
+            exited: info.exited,
+            debugger_attached: info.debugger_attached,
+        }
+    }
+}
+
+/// Query for the state of a process (`ZX_INFO_PROCESS`).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ProcessQuery;
+
+unsafe impl ObjectQuery for ProcessQuery {
+    const TOPIC: sys::zx_object_info_topic_t = sys::ZX_INFO_PROCESS;
+    type Record = sys::zx_info_process_t;
+}
+
+/// The state of a thread.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ThreadInfo {
+    /// What the thread is doing, or `None` if the kernel reported a state this crate does not
+    /// know about.
+    pub state: Option<ThreadState>,
+}
+
+impl From<sys::zx_info_thread_t> for ThreadInfo {
+    fn from(info: sys::zx_info_thread_t) -> Self {
+        ThreadInfo { state: ThreadState::from_raw(info.state) }
+    }
+}
+
+/// What a thread is doing.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ThreadState {
+    /// The thread has been created but not started.
+    New = sys::ZX_THREAD_STATE_NEW,
+    /// The thread is running, or ready to run.
+    Running = sys::ZX_THREAD_STATE_RUNNING,
+    /// The thread is suspended.
+    Suspended = sys::ZX_THREAD_STATE_SUSPENDED,
+    /// The thread is blocked in a syscall or an exception.
+    Blocked = sys::ZX_THREAD_STATE_BLOCKED,
+    /// The thread is exiting.
+    Dying = sys::ZX_THREAD_STATE_DYING,
+    /// The thread has exited.
+    Dead = sys::ZX_THREAD_STATE_DEAD,
+}
+
+impl ThreadState {
+    /// Convert from a `ZX_THREAD_STATE_*` value, returning `None` if it is not one this crate
+    /// knows about.
+    pub fn from_raw(raw: u32) -> Option<ThreadState> {
+        match raw {
+            sys::ZX_THREAD_STATE_NEW => Some(ThreadState::New),
+            sys::ZX_THREAD_STATE_RUNNING => Some(ThreadState::Running),
+            sys::ZX_THREAD_STATE_SUSPENDED => Some(ThreadState::Suspended),
+            sys::ZX_THREAD_STATE_BLOCKED => Some(ThreadState::Blocked),
+            sys::ZX_THREAD_STATE_DYING => Some(ThreadState::Dying),
+            sys::ZX_THREAD_STATE_DEAD => Some(ThreadState::Dead),
+            _ => None,
+        }
+    }
+}
+
+/// Query for the state of a thread (`ZX_INFO_THREAD`).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ThreadQuery;
+
+unsafe impl ObjectQuery for ThreadQuery {
+    const TOPIC: sys::zx_object_info_topic_t = sys::ZX_INFO_THREAD;
+    type Record = sys::zx_info_thread_t;
+}
+
+/// The memory usage of a task.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TaskStats {
+    /// The total size of the memory mapped into the task's address space, in bytes.
+    pub mem_mapped_bytes: usize,
+    /// The committed memory mapped only by this task, in bytes.
+    pub mem_private_bytes: usize,
+    /// The committed memory mapped by this task and at least one other, in bytes.
+    pub mem_shared_bytes: usize,
+    /// The task's share of `mem_shared_bytes`, each page divided evenly between the tasks
+    /// mapping it, in bytes.
+    pub mem_scaled_shared_bytes: usize,
+}
+
+impl From<sys::zx_info_task_stats_t> for TaskStats {
+    fn from(info: sys::zx_info_task_stats_t) -> Self {
+        TaskStats {
+            mem_mapped_bytes: info.mem_mapped_bytes,
+            mem_private_bytes: info.mem_private_bytes,
+            mem_shared_bytes: info.mem_shared_bytes,
+            mem_scaled_shared_bytes: info.mem_scaled_shared_bytes,
+        }
+    }
+}
+
+/// Query for the memory usage of a task (`ZX_INFO_TASK_STATS`).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TaskStatsQuery;
+
+unsafe impl ObjectQuery for TaskStatsQuery {
+    const TOPIC: sys::zx_object_info_topic_t = sys::ZX_INFO_TASK_STATS;
+    type Record = sys::zx_info_task_stats_t;
+}
+
+/// The range of addresses covered by a virtual memory address region.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct VmarInfo {
+    /// The address of the start of the region.
+    pub base: usize,
+    /// The length of the region in bytes.
+    pub len: usize,
+}
+
+impl From<sys::zx_info_vmar_t> for VmarInfo {
+    fn from(info: sys::zx_info_vmar_t) -> Self {
+        VmarInfo { base: info.base, len: info.len }
+    }
+}
+
+/// Query for the range of addresses covered by a virtual memory address region
+/// (`ZX_INFO_VMAR`).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct VmarQuery;
+
+unsafe impl ObjectQuery for VmarQuery {
+    const TOPIC: sys::zx_object_info_topic_t = sys::ZX_INFO_VMAR;
+    type Record = sys::zx_info_vmar_t;
+}
@@ -4,11 +4,249 @@
 
 //! Type-safe bindings for Zircon job.
 
-use {AsHandleRef, HandleBased, Handle, HandleRef};
+use {AsHandleRef, HandleBased, Handle, HandleRef, JobChildrenQuery, JobProcessesQuery, Koid};
+use {ObjectType, Rights, Status, Task, Unowned, object_get_child, sys, into_result, usize_into_u32};
 
 /// An object representing a Zircon job.
 ///
 /// As essentially a subtype of `Handle`, it can be freely interconverted.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Eq, PartialEq)]
 pub struct Job(Handle);
-impl_handle_based!(Job);
\ No newline at end of file
+impl_handle_based!(Job, ObjectType::Job);
+impl Task for Job {}
+
+impl Job {
+    /// Create a new job as a child of `parent`. Killing a job kills all of the jobs and
+    /// processes within it.
+    ///
+    /// Wraps the
+    /// [zx_job_create](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/job_create.md)
+    /// syscall.
+    pub fn create(parent: &Job, opts: JobOpts) -> Result<Job, Status> {
+        let mut out = 0;
+        let status = unsafe { sys::zx_job_create(parent.raw_handle(), opts as u32, &mut out) };
+        into_result(status, || Job::from(Handle::owned(out)))
+    }
+
+    /// Set the policy for what the processes in this job, and in jobs created within it later,
+    /// may do. Each entry of `policies` gives the action the kernel takes when a process meets
+    /// the condition. With `JobPolicyOpts::Relative`, conditions whose policy was already set by
+    /// a parent job are left alone; with `JobPolicyOpts::Absolute`, trying to change them fails.
+    ///
+    /// Policy can only be set on a job which has no processes or child jobs yet.
+    ///
+    /// Wraps the
+    /// [zx_job_set_policy](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/job_set_policy.md)
+    /// syscall with `ZX_JOB_POL_BASIC`.
+    pub fn set_policy(&self, opts: JobPolicyOpts, policies: &[(PolicyCondition, PolicyAction)])
+        -> Result<(), Status>
+    {
+        let count = usize_into_u32(policies.len()).map_err(|_| Status::ErrOutOfRange)?;
+        let policies: Vec<sys::zx_policy_basic_t> = policies.iter()
+            .map(|&(condition, action)| sys::zx_policy_basic_t {
+                condition: condition as u32,
+                policy: action as u32,
+            })
+            .collect();
+        let status = unsafe {
+            sys::zx_job_set_policy(self.raw_handle(), opts as u32, sys::ZX_JOB_POL_BASIC,
+                policies.as_ptr() as *const u8, count)
+        };
+        into_result(status, || ())
+    }
+
+    /// Get the koids of the child jobs of this job.
+    ///
+    /// Wraps the
+    /// [zx_object_get_info](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_get_info.md)
+    /// syscall with `ZX_INFO_JOB_CHILDREN`.
+    pub fn children(&self) -> Result<Vec<Koid>, Status> {
+        self.get_info::<JobChildrenQuery>()
+    }
+
+    /// Get the koids of the processes directly within this job.
+    ///
+    /// Wraps the
+    /// [zx_object_get_info](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_get_info.md)
+    /// syscall with `ZX_INFO_JOB_PROCESSES`.
+    pub fn processes(&self) -> Result<Vec<Koid>, Status> {
+        self.get_info::<JobProcessesQuery>()
+    }
+
+    /// Get a handle to a child job or process of this job, given its koid.
+    ///
+    /// Wraps the
+    /// [zx_object_get_child](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_get_child.md)
+    /// syscall.
+    pub fn get_child(&self, koid: Koid, rights: Rights) -> Result<Handle, Status> {
+        object_get_child(self.as_handle_ref(), koid, rights)
+    }
+}
+
+/// The default job of the current process, in which it should create new processes, as provided
+/// by the runtime. The handle is invalid if the runtime did not provide one.
+///
+/// The handle is borrowed from the runtime and is not closed when dropped.
+pub fn job_default() -> Unowned<'static, Job> {
+    unsafe { Unowned::from_raw_handle(sys::zx_job_default()) }
+}
+
+/// Options for creating a job.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum JobOpts {
+    /// Default options.
+    Default = 0,
+}
+
+impl Default for JobOpts {
+    fn default() -> Self {
+        JobOpts::Default
+    }
+}
+
+/// Whether a policy set with `Job::set_policy` may override the policy of a parent job.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum JobPolicyOpts {
+    /// Conditions whose policy is already set by a parent job are left unchanged.
+    Relative = sys::ZX_JOB_POL_RELATIVE,
+    /// Fail if any condition's policy is already set differently by a parent job.
+    Absolute = sys::ZX_JOB_POL_ABSOLUTE,
+}
+
+impl Default for JobPolicyOpts {
+    fn default() -> Self {
+        JobPolicyOpts::Relative
+    }
+}
+
+/// Something a process may do, which a job's policy can restrict.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PolicyCondition {
+    /// Using a handle which is invalid or has been closed.
+    BadHandle = sys::ZX_POL_BAD_HANDLE,
+    /// Using a handle with a syscall which does not apply to its type of object.
+    WrongObject = sys::ZX_POL_WRONG_OBJECT,
+    /// Mapping memory both writable and executable.
+    VmarWx = sys::ZX_POL_VMAR_WX,
+    /// Creating any kind of object; the specific conditions below take precedence over it.
+    NewAny = sys::ZX_POL_NEW_ANY,
+    /// Creating a virtual memory object.
+    NewVmo = sys::ZX_POL_NEW_VMO,
+    /// Creating a channel.
+    NewChannel = sys::ZX_POL_NEW_CHANNEL,
+    /// Creating an event.
+    NewEvent = sys::ZX_POL_NEW_EVENT,
+    /// Creating an event pair.
+    NewEventPair = sys::ZX_POL_NEW_EVPAIR,
+    /// Creating a port.
+    NewPort = sys::ZX_POL_NEW_PORT,
+    /// Creating a socket.
+    NewSocket = sys::ZX_POL_NEW_SOCKET,
+    /// Creating a fifo.
+    NewFifo = sys::ZX_POL_NEW_FIFO,
+    /// Creating a timer.
+    NewTimer = sys::ZX_POL_NEW_TIMER,
+    /// Creating a process.
+    NewProcess = sys::ZX_POL_NEW_PROCESS,
+}
+
+/// What the kernel does when a process meets a `PolicyCondition`.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PolicyAction {
+    /// Allow it.
+    Allow = sys::ZX_POL_ACTION_ALLOW,
+    /// Fail the syscall, usually with `ErrAccessDenied`.
+    Deny = sys::ZX_POL_ACTION_DENY,
+    /// Allow it, but raise an exception on the thread first.
+    AllowException = sys::ZX_POL_ACTION_ALLOW_EXCEPTION,
+    /// Fail the syscall, and raise an exception on the thread.
+    DenyException = sys::ZX_POL_ACTION_DENY_EXCEPTION,
+    /// Kill the process.
+    Kill = sys::ZX_POL_ACTION_KILL,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {process_self, thread_self, Deadline, Duration, Process, ProcessOpts, Signals};
+
+    #[test]
+    fn kill_job() {
+        let job = Job::create(&job_default(), JobOpts::Default).unwrap();
+        let (process, _vmar) = Process::create(&job, "doomed", ProcessOpts::Default).unwrap();
+        assert_eq!(job.suspend().unwrap_err(), Status::ErrNotSupported);
+        assert_eq!(job.kill(), Ok(()));
+        assert!(job.wait_handle(Signals::TASK_TERMINATED, Deadline::INFINITE).is_ok());
+        assert!(process.wait_handle(Signals::TASK_TERMINATED, Deadline::INFINITE).is_ok());
+    }
+
+    #[test]
+    fn set_policy() {
+        let job = Job::create(&job_default(), JobOpts::Default).unwrap();
+        assert_eq!(job.set_policy(JobPolicyOpts::Relative, &[
+            (PolicyCondition::NewProcess, PolicyAction::Deny),
+            (PolicyCondition::BadHandle, PolicyAction::Kill),
+        ]), Ok(()));
+
+        // Policy cannot be changed once the job has children.
+        let _child = Job::create(&job, JobOpts::Default).unwrap();
+        assert_eq!(job.set_policy(JobPolicyOpts::Relative,
+            &[(PolicyCondition::NewVmo, PolicyAction::Deny)]), Err(Status::ErrBadState));
+    }
+
+    #[test]
+    fn kill_on_drop() {
+        let job = Job::create(&job_default(), JobOpts::Default).unwrap();
+        let guarded = Job::create(&job, JobOpts::Default).unwrap().kill_on_drop();
+        let (process, _vmar) = Process::create(&guarded, "doomed", ProcessOpts::Default).unwrap();
+        drop(guarded);
+        assert!(process.wait_handle(Signals::TASK_TERMINATED, Deadline::INFINITE).is_ok());
+
+        let disarmed = Job::create(&job, JobOpts::Default).unwrap().kill_on_drop().disarm();
+        let (process, _vmar) = Process::create(&disarmed, "spared", ProcessOpts::Default).unwrap();
+        let ten_ms = Deadline::after(Duration::from_millis(10));
+        assert_eq!(process.wait_handle(Signals::TASK_TERMINATED, ten_ms),
+            Err(Status::ErrTimedOut));
+    }
+
+    #[test]
+    fn create_nested() {
+        let parent = Job::create(&job_default(), JobOpts::Default).unwrap();
+        let child = Job::create(&parent, JobOpts::Default).unwrap();
+        let child_koid = child.basic_info().unwrap().koid;
+        assert_eq!(parent.children(), Ok(vec![child_koid]));
+        assert_eq!(child.children(), Ok(vec![]));
+
+        let handle = parent.get_child(child_koid, Rights::SAME_RIGHTS).unwrap();
+        assert_eq!(handle.basic_info().unwrap().koid, child_koid);
+    }
+
+    #[test]
+    fn default_job_has_self() {
+        let job = job_default();
+        let koid = process_self().basic_info().unwrap().koid;
+        assert!(job.processes().unwrap().contains(&koid));
+
+        let (process, _vmar) = Process::create(&job, "child", ProcessOpts::Default).unwrap();
+        assert!(job.processes().unwrap().contains(&process.basic_info().unwrap().koid));
+    }
+
+    #[test]
+    fn walk_default_job() {
+        let process_koid = process_self().basic_info().unwrap().koid;
+        let thread_koid = thread_self().basic_info().unwrap().koid;
+
+        let job = job_default();
+        assert!(job.processes().unwrap().contains(&process_koid));
+        let process = Process::from(job.get_child(process_koid, Rights::SAME_RIGHTS).unwrap());
+        assert_eq!(process.basic_info().unwrap().koid, process_koid);
+
+        assert!(process.threads().unwrap().contains(&thread_koid));
+        let thread = process.get_child(thread_koid, Rights::SAME_RIGHTS).unwrap();
+        assert_eq!(thread.basic_info().unwrap().koid, thread_koid);
+    }
+}
@@ -0,0 +1,104 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! A one-shot notification built on an event object.
+
+use {AsHandleRef, Deadline, Event, EventOpts, HandleBased, HandleRef, Rights, Signals};
+use {Status, is_asserted};
+
+/// A notification which is set once and then stays set, waking everyone waiting for it.
+///
+/// A latch is an event whose `Signals::EVENT_SIGNALED` is never cleared. Handles to it can be
+/// made with `duplicate`, and sent to other processes with `into_event`; each holder can set
+/// it, or wait for it to be set.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Latch(Event);
+
+impl Latch {
+    /// Create a latch which is not yet set.
+    pub fn new() -> Result<Latch, Status> {
+        Event::create(EventOpts::Default).map(Latch)
+    }
+
+    /// Set the latch, waking everyone waiting for it. Setting it again has no effect.
+    pub fn set(&self) -> Result<(), Status> {
+        self.0.signal()
+    }
+
+    /// Whether the latch has been set.
+    pub fn is_set(&self) -> Result<bool, Status> {
+        is_asserted(self.as_handle_ref(), Signals::EVENT_SIGNALED)
+    }
+
+    /// Wait until the latch is set, or `deadline` passes. Returns immediately if it has already
+    /// been set. Fails with `ErrTimedOut` if the deadline passes first.
+    pub fn wait(&self, deadline: Deadline) -> Result<(), Status> {
+        self.0.wait_signaled(deadline)
+    }
+
+    /// Make another handle to the same latch.
+    pub fn duplicate(&self) -> Result<Latch, Status> {
+        self.0.duplicate_handle(Rights::SAME_RIGHTS).map(Latch)
+    }
+
+    /// Unwrap the underlying event, for example to send it to another process, which can wrap
+    /// it again with `Latch::from`.
+    pub fn into_event(self) -> Event {
+        self.0
+    }
+}
+
+impl AsHandleRef for Latch {
+    fn as_handle_ref(&self) -> HandleRef {
+        self.0.as_handle_ref()
+    }
+}
+
+/// Use an event as a latch. The latch is already set if the event is signaled.
+impl From<Event> for Latch {
+    fn from(event: Event) -> Self {
+        Latch(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Duration;
+    use std::thread;
+
+    #[test]
+    fn set_once() {
+        let latch = Latch::new().unwrap();
+        assert_eq!(latch.is_set(), Ok(false));
+        assert_eq!(latch.wait(Deadline::after(Duration::from_millis(10))),
+            Err(Status::ErrTimedOut));
+        assert_eq!(latch.set(), Ok(()));
+        assert_eq!(latch.set(), Ok(()));
+        assert_eq!(latch.is_set(), Ok(true));
+        assert_eq!(latch.wait(Deadline::INFINITE), Ok(()));
+    }
+
+    #[test]
+    fn many_waiters() {
+        let latch = Latch::new().unwrap();
+        let waiters: Vec<_> = (0..3).map(|_| {
+            let latch = latch.duplicate().unwrap();
+            thread::spawn(move || latch.wait(Deadline::INFINITE))
+        }).collect();
+        assert_eq!(latch.set(), Ok(()));
+        for waiter in waiters {
+            assert_eq!(waiter.join().unwrap(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn from_signaled_event() {
+        let event = Event::create(EventOpts::Default).unwrap();
+        assert_eq!(event.signal(), Ok(()));
+        let latch = Latch::from(event);
+        assert_eq!(latch.is_set(), Ok(true));
+        assert_eq!(latch.into_event().wait_signaled(Deadline::INFINITE), Ok(()));
+    }
+}
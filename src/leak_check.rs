@@ -0,0 +1,150 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Tracking of live handles, to help find leaks. Enabled by the `leak-check` feature.
+//!
+//! This module uses `std::backtrace` and a `const` `Mutex` constructor, so the
+//! feature needs Rust 1.65 or later, unlike the rest of the crate.
+
+use sys;
+use std::backtrace::Backtrace;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread::{self, ThreadId};
+
+struct Entry {
+    sequence: u64,
+    thread: ThreadId,
+    backtrace: Arc<Backtrace>,
+}
+
+static REGISTRY: Mutex<BTreeMap<sys::zx_handle_t, Entry>> = Mutex::new(BTreeMap::new());
+static NEXT_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+// Record that the crate has taken ownership of a handle.
+pub fn track(handle: sys::zx_handle_t) {
+    let entry = Entry {
+        sequence: NEXT_SEQUENCE.fetch_add(1, Ordering::Relaxed),
+        thread: thread::current().id(),
+        backtrace: Arc::new(Backtrace::force_capture()),
+    };
+    REGISTRY.lock().unwrap_or_else(|e| e.into_inner()).insert(handle, entry);
+}
+
+// Record that a handle has been closed or transferred out of the process.
+pub fn untrack(handle: sys::zx_handle_t) {
+    REGISTRY.lock().unwrap_or_else(|e| e.into_inner()).remove(&handle);
+}
+
+fn live_handles<F: Fn(&Entry) -> bool>(filter: F) -> Vec<LeakedHandle> {
+    let registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let mut handles: Vec<_> = registry.iter()
+        .filter(|&(_, entry)| filter(entry))
+        .map(|(&raw, entry)| (entry.sequence, LeakedHandle {
+            raw,
+            thread: entry.thread,
+            backtrace: entry.backtrace.clone(),
+        }))
+        .collect();
+    handles.sort_by_key(|&(sequence, _)| sequence);
+    handles.into_iter().map(|(_, handle)| handle).collect()
+}
+
+/// A handle which is still open, together with where it was created.
+#[derive(Clone)]
+pub struct LeakedHandle {
+    /// The raw value of the handle.
+    pub raw: sys::zx_handle_t,
+    /// The thread which created the handle.
+    pub thread: ThreadId,
+    /// The stack at the point the handle was created.
+    pub backtrace: Arc<Backtrace>,
+}
+
+impl fmt::Debug for LeakedHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "handle {} created on {:?} at:\n{}", self.raw, self.thread, self.backtrace)
+    }
+}
+
+/// All handles created through this crate which are still open, in the order they were created.
+pub fn leak_report() -> Vec<LeakedHandle> {
+    live_handles(|_| true)
+}
+
+/// A point in time to check for leaks from, for example at the start of a test.
+///
+/// Only handles created on the same thread as the checkpoint are considered, so that tests
+/// running in parallel do not see each other's handles.
+#[derive(Debug)]
+pub struct LeakCheckpoint {
+    sequence: u64,
+    thread: ThreadId,
+}
+
+impl LeakCheckpoint {
+    /// Create a checkpoint on the current thread.
+    pub fn new() -> LeakCheckpoint {
+        LeakCheckpoint {
+            sequence: NEXT_SEQUENCE.load(Ordering::Relaxed),
+            thread: thread::current().id(),
+        }
+    }
+
+    /// The handles created on this thread since the checkpoint which are still open.
+    pub fn leaked_since(&self) -> Vec<LeakedHandle> {
+        live_handles(|entry| entry.sequence >= self.sequence && entry.thread == self.thread)
+    }
+
+    /// Panic, listing the offending handles and where they were created, if any handles
+    /// created on this thread since the checkpoint are still open.
+    pub fn assert_no_leaks(&self) {
+        let leaked = self.leaked_since();
+        if !leaked.is_empty() {
+            panic!("{} handle(s) leaked: {:#?}", leaked.len(), leaked);
+        }
+    }
+}
+
+impl Default for LeakCheckpoint {
+    fn default() -> Self {
+        LeakCheckpoint::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {AsHandleRef, Channel, ChannelOpts, Event, EventOpts, HandleBased, MessageBuf};
+
+    #[test]
+    fn leak_checkpoint() {
+        let checkpoint = LeakCheckpoint::new();
+        let event = Event::create(EventOpts::Default).unwrap();
+        let leaked = checkpoint.leaked_since();
+        assert_eq!(leaked.len(), 1);
+        assert_eq!(leaked[0].raw, event.raw_handle());
+        drop(event);
+        checkpoint.assert_no_leaks();
+    }
+
+    #[test]
+    fn leak_check_channel_transfer() {
+        let (p1, p2) = Channel::create(ChannelOpts::Normal).unwrap();
+        let checkpoint = LeakCheckpoint::new();
+        let event = Event::create(EventOpts::Default).unwrap();
+        let mut handles = vec![event.into_handle()];
+        p1.write(b"", &mut handles, 0).unwrap();
+        checkpoint.assert_no_leaks();
+
+        let mut buf = MessageBuf::new();
+        p2.read(0, &mut buf).unwrap();
+        let received = buf.take_handle(0).unwrap();
+        assert_eq!(checkpoint.leaked_since().len(), 1);
+        drop(received);
+        checkpoint.assert_no_leaks();
+    }
+}
@@ -8,10 +8,14 @@
 extern crate core;
 extern crate zircon_sys;
 
+use std::{cmp, fmt, io, mem};
+use std::cell::RefCell;
+use std::io::IoSlice;
 use std::marker::PhantomData;
+use std::ops::Deref;
 
 macro_rules! impl_handle_based {
-    ($type_name:path) => {
+    ($type_name:path, $object_type:path) => {
         impl AsHandleRef for $type_name {
             fn as_handle_ref(&self) -> HandleRef {
                 self.0.as_handle_ref()
@@ -31,42 +35,212 @@ macro_rules! impl_handle_based {
         }
 
         impl HandleBased for $type_name {}
+
+        impl ::TypedHandle for $type_name {
+            const OBJECT_TYPE: ::ObjectType = $object_type;
+        }
+
+        impl ::std::fmt::Debug for $type_name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                ::debug_handle(self.as_handle_ref(), stringify!($type_name), f)
+            }
+        }
     }
 }
 
+// Common methods and operators for newtypes wrapping the bitflags types of zircon_sys.
+macro_rules! impl_flags {
+    ($type_name:ident, $raw:path) => {
+        impl $type_name {
+            /// Convert from the raw type used by `zircon_sys`.
+            pub fn from_raw(raw: $raw) -> $type_name {
+                $type_name(raw)
+            }
+
+            /// Convert into the raw type used by `zircon_sys`.
+            pub fn into_raw(self) -> $raw {
+                self.0
+            }
+
+            /// Convert from raw bits, returning `None` if any unknown bits are set.
+            pub fn from_bits(bits: u32) -> Option<$type_name> {
+                <$raw>::from_bits(bits).map($type_name)
+            }
+
+            /// The raw bits of the flags.
+            pub fn bits(&self) -> u32 {
+                self.0.bits()
+            }
+
+            /// Returns true if no flags are set.
+            pub fn is_empty(&self) -> bool {
+                self.0.is_empty()
+            }
+
+            /// Returns true if all of the flags in `other` are also set in `self`.
+            pub fn contains(&self, other: $type_name) -> bool {
+                self.0.contains(other.0)
+            }
+
+            /// Returns true if any of the flags in `other` are also set in `self`.
+            pub fn intersects(&self, other: $type_name) -> bool {
+                self.0.intersects(other.0)
+            }
+        }
+
+        impl From<$raw> for $type_name {
+            fn from(raw: $raw) -> Self {
+                $type_name(raw)
+            }
+        }
+
+        impl From<$type_name> for $raw {
+            fn from(flags: $type_name) -> Self {
+                flags.0
+            }
+        }
+
+        impl ::std::ops::BitOr for $type_name {
+            type Output = $type_name;
+            fn bitor(self, other: $type_name) -> $type_name {
+                $type_name(self.0 | other.0)
+            }
+        }
+
+        impl ::std::ops::BitOrAssign for $type_name {
+            fn bitor_assign(&mut self, other: $type_name) {
+                self.0 = self.0 | other.0;
+            }
+        }
+
+        impl ::std::ops::BitAnd for $type_name {
+            type Output = $type_name;
+            fn bitand(self, other: $type_name) -> $type_name {
+                $type_name(self.0 & other.0)
+            }
+        }
+
+        impl ::std::ops::BitAndAssign for $type_name {
+            fn bitand_assign(&mut self, other: $type_name) {
+                self.0 = self.0 & other.0;
+            }
+        }
+
+        impl ::std::ops::Sub for $type_name {
+            type Output = $type_name;
+            fn sub(self, other: $type_name) -> $type_name {
+                $type_name(self.0 - other.0)
+            }
+        }
+
+        impl ::std::ops::SubAssign for $type_name {
+            fn sub_assign(&mut self, other: $type_name) {
+                self.0 = self.0 - other.0;
+            }
+        }
+    }
+}
+
+mod cancel;
 mod channel;
+mod debugger;
+mod duplicate;
 mod event;
 mod eventpair;
 mod fifo;
+mod info;
 mod job;
+mod latch;
+mod object_type;
+#[cfg(feature = "leak-check")]
+mod leak_check;
+mod mapping;
+mod peer_tracker;
 mod port;
 mod process;
+mod process_builder;
+mod rights;
+mod shared;
+mod signals;
 mod socket;
+mod startup;
+mod task_tree;
+mod time;
 mod timer;
+mod transaction;
 mod thread;
+mod typed;
+mod typed_channel;
+mod typed_fifo;
+mod unowned;
+mod vmar;
+mod vmar_flags;
 mod vmo;
-
-pub use channel::{Channel, ChannelOpts, MessageBuf};
+mod vmo_arena;
+mod vmo_cursor;
+
+pub use cancel::{CancellationReceiver, CancellationToken, cancellation};
+pub use channel::{CHANNEL_MAX_MSG_BYTES, CHANNEL_MAX_MSG_HANDLES, Channel, ChannelOpts,
+    ChannelReader, ChannelWriteError, ChannelWriter, HandleDisposition, HandleInfo, HandleOp, MessageBuf,
+    MessageBufPool, MessageTooLarge, Messages};
+pub use debugger::{Debugger, StoppedThread};
+pub use duplicate::{DuplicateOptions, duplicate_handles};
 pub use event::{Event, EventOpts};
 pub use eventpair::{EventPair, EventPairOpts};
 pub use fifo::{Fifo, FifoOpts};
-pub use job::Job;
-pub use port::{Packet, PacketContents, Port, PortOpts, SignalPacket, UserPacket, WaitAsyncOpts};
-pub use process::Process;
-pub use socket::{Socket, SocketOpts, SocketReadOpts, SocketWriteOpts};
+pub use info::{HandleBasicInfo, HandleBasicQuery, JobChildrenQuery, JobProcessesQuery, ObjectQuery,
+    ProcessInfo, ProcessQuery, ProcessThreadsQuery, SocketInfo, SocketQuery, TaskStats,
+    TaskStatsQuery, ThreadInfo, ThreadQuery, ThreadState, VmarInfo, VmarQuery, VmoInfo, VmoQuery,
+    object_get_info, object_get_info_single};
+pub use job::{Job, JobOpts, JobPolicyOpts, PolicyAction, PolicyCondition, job_default};
+pub use latch::Latch;
+pub use object_type::ObjectType;
+#[cfg(feature = "leak-check")]
+pub use leak_check::{LeakCheckpoint, LeakedHandle, leak_report};
+pub use mapping::Mapping;
+pub use peer_tracker::PeerTracker;
+pub use port::{ExceptionPacket, ExceptionPortOpts, ExceptionType, Packet, PacketContents, Port,
+    PortOpts, SignalPacket, UserPacket, WaitAsyncOpts};
+pub use process::{Process, ProcessOpts, process_self};
+pub use process_builder::ProcessBuilder;
+pub use rights::Rights;
+pub use shared::Shared;
+pub use signals::{Signals, UserSignal};
+pub use socket::{Socket, SocketOpts, SocketReadOpts, SocketWriteOpts, stdio_pipe};
+pub use startup::{HandleType, StartupHandles, startup_handles, take_startup_handle};
+pub use task_tree::{JobNode, ProcessNode, ThreadNode, task_tree};
+pub use time::{Deadline, Duration};
 pub use timer::{Timer, TimerOpts};
-pub use thread::Thread;
-pub use vmo::{Vmo, VmoCloneOpts, VmoOp, VmoOpts};
+pub use transaction::{Transactions, message_txid};
+pub use thread::{GeneralRegs, SpawnedThread, Thread, ThreadOpts, ThreadStateKind, thread_self};
+pub use typed::{CanRead, CanWrite, ReadOnly, ReadWrite, RightsMarker, TypedVmo};
+pub use typed_channel::{BytesCodec, Codec, TypedChannel};
+pub use typed_fifo::{FifoEntry, TypedFifo};
+pub use unowned::Unowned;
+pub use vmar::{AllocateOptions, Vmar};
+pub use vmar_flags::VmarFlags;
+pub use vmo::{CachePolicy, Vmo, VmoCloneOpts, VmoLockGuard, VmoOp, VmoOpts};
+pub use vmo_arena::{ArenaBuffer, VmoArena};
+pub use vmo_cursor::VmoCursor;
 
 use zircon_sys as sys;
 
-type Duration = sys::zx_duration_t;
+// Without the `leak-check` feature, the hooks for tracking handles do nothing.
+#[cfg(not(feature = "leak-check"))]
+mod leak_check {
+    pub fn track(_handle: ::sys::zx_handle_t) {}
+    pub fn untrack(_handle: ::sys::zx_handle_t) {}
+}
+
+/// A kernel object id, unique for the lifetime of the system.
+pub type Koid = sys::zx_koid_t;
+
 type Time = sys::zx_time_t;
 pub use zircon_sys::ZX_TIME_INFINITE;
 
-// A placeholder value used for handles that have been taken from the message buf.
-// We rely on the kernel never to produce any actual handles with this value.
-const INVALID_HANDLE: sys::zx_handle_t = 0;
+// A placeholder value used for invalid handles, for example those that have been taken from
+// the message buf. The kernel never produces any actual handles with this value.
+const INVALID_HANDLE: sys::zx_handle_t = sys::ZX_HANDLE_INVALID;
 
 /// A status code returned from the Zircon kernel.
 ///
@@ -161,72 +335,31 @@ impl Status {
 
     // Note: no to_raw, even though it's easy to implement, partly because
     // handling of UnknownOther would be tricky.
-}
 
-/// Rights associated with a handle.
-///
-/// See [rights.md](https://fuchsia.googlesource.com/zircon/+/master/docs/rights.md)
-/// for more information.
-pub type Rights = sys::zx_rights_t;
-pub use zircon_sys::{
-    ZX_RIGHT_NONE,
-    ZX_RIGHT_DUPLICATE,
-    ZX_RIGHT_TRANSFER,
-    ZX_RIGHT_READ,
-    ZX_RIGHT_WRITE,
-    ZX_RIGHT_EXECUTE,
-    ZX_RIGHT_MAP,
-    ZX_RIGHT_GET_PROPERTY,
-    ZX_RIGHT_SET_PROPERTY,
-    ZX_RIGHT_DEBUG,
-    ZX_RIGHT_SAME_RIGHTS,
-};
-
-/// Signals that can be waited upon.
-///
-/// See
-/// [Objects and signals](https://fuchsia.googlesource.com/zircon/+/master/docs/concepts.md#Objects-and-Signals)
-/// in the Zircon kernel documentation. Note: the names of signals are still in flux.
-pub type Signals = sys::zx_signals_t;
+    /// Convert into a `std::io::Error`, with the closest matching `ErrorKind`.
+    pub fn into_io_error(self) -> io::Error {
+        let kind = match self {
+            Status::ErrInterruptedRetry => io::ErrorKind::Interrupted,
+            Status::ErrInvalidArgs => io::ErrorKind::InvalidInput,
+            Status::ErrTimedOut => io::ErrorKind::TimedOut,
+            Status::ErrShouldWait => io::ErrorKind::WouldBlock,
+            Status::ErrPeerClosed => io::ErrorKind::BrokenPipe,
+            Status::ErrNotFound => io::ErrorKind::NotFound,
+            Status::ErrAlreadyExists => io::ErrorKind::AlreadyExists,
+            Status::ErrAccessDenied => io::ErrorKind::PermissionDenied,
+            Status::ErrIoRefused => io::ErrorKind::ConnectionRefused,
+            Status::ErrIoDataIntegrity => io::ErrorKind::InvalidData,
+            _ => io::ErrorKind::Other,
+        };
+        io::Error::new(kind, format!("{:?}", self))
+    }
+}
 
-pub use zircon_sys::{
-        ZX_SIGNAL_NONE,
-
-        ZX_SIGNAL_HANDLE_CLOSED,
-        ZX_SIGNAL_LAST_HANDLE,
-
-        ZX_USER_SIGNAL_0,
-        ZX_USER_SIGNAL_1,
-        ZX_USER_SIGNAL_2,
-        ZX_USER_SIGNAL_3,
-        ZX_USER_SIGNAL_4,
-        ZX_USER_SIGNAL_5,
-        ZX_USER_SIGNAL_6,
-        ZX_USER_SIGNAL_7,
-
-        // Event
-        ZX_EVENT_SIGNALED,
-
-        // EventPair
-        ZX_EPAIR_SIGNALED,
-        ZX_EPAIR_CLOSED,
-
-        // Task signals (process, thread, job)
-        ZX_TASK_TERMINATED,
-
-        // Channel
-        ZX_CHANNEL_READABLE,
-        ZX_CHANNEL_WRITABLE,
-        ZX_CHANNEL_PEER_CLOSED,
-
-        // Socket
-        ZX_SOCKET_READABLE,
-        ZX_SOCKET_WRITABLE,
-        ZX_SOCKET_PEER_CLOSED,
-
-        // Timer
-        ZX_TIMER_SIGNALED,
-};
+impl From<Status> for io::Error {
+    fn from(status: Status) -> io::Error {
+        status.into_io_error()
+    }
+}
 
 /// A "wait item" containing a handle reference and information about what signals
 /// to wait on, and, on return from `object_wait_many`, which are pending.
@@ -281,11 +414,11 @@ pub fn ticks_get() -> u64 {
 
 /// Compute a deadline for the time in the future that is the given `Duration` away.
 ///
-/// Wraps the
+/// Equivalent to `Deadline::after`; wraps the
 /// [zx_deadline_after](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/deadline_after.md)
 /// syscall.
-pub fn deadline_after(nanos: Duration) -> Time {
-    unsafe { sys::zx_deadline_after(nanos) }
+pub fn deadline_after(duration: Duration) -> Deadline {
+    Deadline::after(duration)
 }
 
 /// Sleep until the given deadline.
@@ -293,8 +426,8 @@ pub fn deadline_after(nanos: Duration) -> Time {
 /// Wraps the
 /// [zx_nanosleep](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/nanosleep.md)
 /// syscall.
-pub fn nanosleep(deadline: Time) {
-    unsafe { sys::zx_nanosleep(deadline); }
+pub fn nanosleep(deadline: Deadline) {
+    unsafe { sys::zx_nanosleep(deadline.into_nanos()); }
 }
 
 /// Return the number of high-precision timer ticks in a second.
@@ -306,6 +439,8 @@ pub fn ticks_per_second() -> u64 {
     unsafe { sys::zx_ticks_per_second() }
 }
 
+pub use zircon_sys::ZX_MAX_NAME_LEN;
+
 pub use zircon_sys::{
     ZX_CPRNG_DRAW_MAX_LEN,
     ZX_CPRNG_ADD_ENTROPY_MAX_LEN,
@@ -337,6 +472,36 @@ pub fn cprng_add_entropy(buffer: &[u8]) -> Result<(), Status> {
     into_result(status, || ())
 }
 
+/// The size of a page of memory, the unit in which memory is mapped and committed.
+pub fn page_size() -> usize {
+    PAGE_SIZE
+}
+
+/// Round `n` up to a multiple of the page size, returning `None` if that would overflow.
+pub fn round_up_to_page(n: usize) -> Option<usize> {
+    n.checked_add(PAGE_SIZE - 1).map(round_down_to_page)
+}
+
+/// Round `n` down to a multiple of the page size.
+pub fn round_down_to_page(n: usize) -> usize {
+    n & !(PAGE_SIZE - 1)
+}
+
+/// Returns true if `n` is a multiple of the page size.
+pub fn is_page_aligned(n: usize) -> bool {
+    n & (PAGE_SIZE - 1) == 0
+}
+
+// The stack pointer a function expects on entry, as though it had been called, given the top
+// of its stack.
+fn initial_stack_pointer(top: usize) -> usize {
+    let top = top & !15;
+    if cfg!(target_arch = "x86_64") { top - 8 } else { top }
+}
+
+// The size of a page on all architectures Zircon supports.
+const PAGE_SIZE: usize = 4096;
+
 fn into_result<T, F>(status: sys::zx_status_t, f: F) -> Result<T, Status>
     where F: FnOnce() -> T {
     // All non-negative values are assumed successful. Note: calls that don't try
@@ -352,7 +517,9 @@ fn into_result<T, F>(status: sys::zx_status_t, f: F) -> Result<T, Status>
 
 /// A borrowed reference to a `Handle`.
 ///
-/// Mostly useful as part of a `WaitItem`.
+/// Mostly useful as part of a `WaitItem`. It has the same representation as a
+/// raw `zx_handle_t`, so that a `WaitItem` can be passed directly to the kernel.
+#[repr(C)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct HandleRef<'a> {
     handle: sys::zx_handle_t,
@@ -367,8 +534,8 @@ impl<'a> HandleRef<'a> {
     pub fn duplicate(&self, rights: Rights) -> Result<Handle, Status> {
         let handle = self.handle;
         let mut out = 0;
-        let status = unsafe { sys::zx_handle_duplicate(handle, rights, &mut out) };
-        into_result(status, || Handle(out))
+        let status = unsafe { sys::zx_handle_duplicate(handle, rights.into_raw(), &mut out) };
+        into_result(status, || Handle::owned(out))
     }
 
     pub fn signal(&self, clear_mask: Signals, set_mask: Signals) -> Result<(), Status> {
@@ -377,13 +544,68 @@ impl<'a> HandleRef<'a> {
         into_result(status, || ())
     }
 
-    pub fn wait(&self, signals: Signals, deadline: Time) -> Result<Signals, Status> {
+    pub fn wait(&self, signals: Signals, deadline: Deadline) -> Result<Signals, Status> {
         let handle = self.handle;
         let mut pending = sys::zx_signals_t::empty();
         let status = unsafe {
-            sys::zx_object_wait_one(handle, signals, deadline, &mut pending)
+            sys::zx_object_wait_one(handle, signals.into_raw(), deadline.into_nanos(), &mut pending)
+        };
+        into_result(status, || Signals::from_raw(pending))
+    }
+
+    pub fn wait_for_any(&self, signals: Signals, deadline: Deadline) -> Result<Signals, Status> {
+        loop {
+            let observed = self.wait(signals, deadline)?;
+            if observed.intersects(signals) {
+                return Ok(observed & signals);
+            }
+        }
+    }
+
+    pub fn wait_for_all(&self, signals: Signals, deadline: Deadline) -> Result<Signals, Status> {
+        let mut observed = Signals::NONE;
+        loop {
+            if observed.contains(signals) {
+                return Ok(observed & signals);
+            }
+            // Wait for the signals not yet asserted; those already seen may have since been
+            // deasserted, so the result of each wait replaces what was observed before.
+            observed = self.wait(signals - observed, deadline)?;
+        }
+    }
+
+    pub fn get_name(&self) -> Result<String, Status> {
+        let handle = self.handle;
+        let mut name = [0u8; sys::ZX_MAX_NAME_LEN];
+        let status = unsafe {
+            sys::zx_object_get_property(handle, sys::ZX_PROP_NAME, name.as_mut_ptr(), name.len())
+        };
+        into_result(status, || {
+            let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+            String::from_utf8_lossy(&name[..len]).into_owned()
+        })
+    }
+
+    pub fn set_name(&self, name: &str) -> Result<(), Status> {
+        let handle = self.handle;
+        // The kernel stores at most ZX_MAX_NAME_LEN - 1 bytes plus a NUL terminator. Truncate on
+        // a character boundary so the stored name is still valid UTF-8.
+        let mut len = cmp::min(name.len(), sys::ZX_MAX_NAME_LEN - 1);
+        while !name.is_char_boundary(len) {
+            len -= 1;
+        }
+        let status = unsafe {
+            sys::zx_object_set_property(handle, sys::ZX_PROP_NAME, name.as_ptr(), len)
         };
-        into_result(status, || pending)
+        into_result(status, || ())
+    }
+
+    pub fn get_info<Q: ObjectQuery>(&self) -> Result<Vec<Q::Record>, Status> {
+        object_get_info::<Q>(*self)
+    }
+
+    pub fn basic_info(&self) -> Result<HandleBasicInfo, Status> {
+        object_get_info_single::<HandleBasicQuery>(*self).map(HandleBasicInfo::from)
     }
 
     pub fn wait_async(&self, port: &Port, key: u64, signals: Signals, options: WaitAsyncOpts)
@@ -391,7 +613,8 @@ impl<'a> HandleRef<'a> {
     {
         let handle = self.handle;
         let status = unsafe {
-            sys::zx_object_wait_async(handle, port.raw_handle(), key, signals, options as u32)
+            sys::zx_object_wait_async(handle, port.raw_handle(), key, signals.into_raw(),
+                options as u32)
         };
         into_result(status, || ())
     }
@@ -410,21 +633,105 @@ pub trait AsHandleRef {
         self.as_handle_ref().raw_handle()
     }
 
+    /// Returns true if this is the invalid handle, for example one created by
+    /// `Handle::invalid`.
+    fn is_invalid(&self) -> bool {
+        self.raw_handle() == INVALID_HANDLE
+    }
+
     /// Set and clear userspace-accessible signal bits on an object. Wraps the
     /// [zx_object_signal](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_signal.md)
     /// syscall.
+    ///
+    /// Only the user signals (`ZX_USER_SIGNAL_0` through `ZX_USER_SIGNAL_7`) may be
+    /// set or cleared; any other bit in either mask results in `ErrInvalidArgs`
+    /// without calling into the kernel.
     fn signal_handle(&self, clear_mask: Signals, set_mask: Signals) -> Result<(), Status> {
+        if !Signals::USER_ALL.contains(clear_mask | set_mask) {
+            return Err(Status::ErrInvalidArgs);
+        }
         self.as_handle_ref().signal(clear_mask, set_mask)
     }
 
+    /// Assert the user signal `signal` on the object, leaving the others unchanged.
+    fn assert_signal(&self, signal: UserSignal) -> Result<(), Status> {
+        self.as_handle_ref().signal(Signals::NONE, signal.signals())
+    }
+
+    /// Deassert the user signal `signal` on the object, leaving the others unchanged.
+    fn clear_signal(&self, signal: UserSignal) -> Result<(), Status> {
+        self.as_handle_ref().signal(signal.signals(), Signals::NONE)
+    }
+
+    /// Wait until the user signal `signal` is asserted on the object, or `deadline` passes.
+    /// Fails with `ErrTimedOut` if the deadline passes first.
+    fn wait_user_signal(&self, signal: UserSignal, deadline: Deadline) -> Result<(), Status> {
+        self.as_handle_ref().wait(signal.signals(), deadline).map(|_| ())
+    }
+
     /// Waits on a handle. Wraps the
     /// [zx_object_wait_one](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_wait_one.md)
     /// syscall.
-    fn wait_handle(&self, signals: Signals, deadline: Time) -> Result<Signals, Status> {
+    fn wait_handle(&self, signals: Signals, deadline: Deadline) -> Result<Signals, Status> {
         self.as_handle_ref().wait(signals, deadline)
     }
 
+    /// Waits until any of the given signals is asserted on the object, returning those of them
+    /// which are asserted. Wraps the
+    /// [zx_object_wait_one](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_wait_one.md)
+    /// syscall.
+    fn wait_for_any(&self, signals: Signals, deadline: Deadline) -> Result<Signals, Status> {
+        self.as_handle_ref().wait_for_any(signals, deadline)
+    }
+
+    /// Waits until all of the given signals are asserted on the object at the same time,
+    /// returning them. Wraps the
+    /// [zx_object_wait_one](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_wait_one.md)
+    /// syscall, calling it repeatedly as needed.
+    fn wait_for_all(&self, signals: Signals, deadline: Deadline) -> Result<Signals, Status> {
+        self.as_handle_ref().wait_for_all(signals, deadline)
+    }
+
+    /// Get the name of the object, as set by `set_name`. Wraps the
+    /// [zx_object_get_property](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_get_property.md)
+    /// syscall with `ZX_PROP_NAME`.
+    fn get_name(&self) -> Result<String, Status> {
+        self.as_handle_ref().get_name()
+    }
+
+    /// Set the name of the object, which is visible to kernel debugging tools. Names longer
+    /// than `ZX_MAX_NAME_LEN - 1` bytes are truncated. Wraps the
+    /// [zx_object_set_property](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_set_property.md)
+    /// syscall with `ZX_PROP_NAME`.
+    fn set_name(&self, name: &str) -> Result<(), Status> {
+        self.as_handle_ref().set_name(name)
+    }
+
+    /// Query information about the object. The records returned are determined by the
+    /// `ObjectQuery` type. Wraps the
+    /// [zx_object_get_info](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_get_info.md)
+    /// syscall.
+    fn get_info<Q: ObjectQuery>(&self) -> Result<Vec<Q::Record>, Status> where Self: Sized {
+        self.as_handle_ref().get_info::<Q>()
+    }
+
+    /// Get basic information about the handle, such as the koid and type of the object and the
+    /// rights of the handle. Wraps the
+    /// [zx_object_get_info](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_get_info.md)
+    /// syscall with `ZX_INFO_HANDLE_BASIC`.
+    fn basic_info(&self) -> Result<HandleBasicInfo, Status> {
+        self.as_handle_ref().basic_info()
+    }
+
+    /// Get the type of the object the handle refers to. Wraps the
+    /// [zx_object_get_info](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_get_info.md)
+    /// syscall with `ZX_INFO_HANDLE_BASIC`.
+    fn object_type(&self) -> Result<ObjectType, Status> {
+        self.as_handle_ref().basic_info().map(|info| info.object_type)
+    }
+
     /// Causes packet delivery on the given port when the object changes state and matches signals.
+    /// The packet will carry the given `key`. Wraps the
     /// [zx_object_wait_async](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_wait_async.md)
     /// syscall.
     fn wait_async_handle(&self, port: &Port, key: u64, signals: Signals, options: WaitAsyncOpts)
@@ -453,6 +760,12 @@ pub trait HandleBased: AsHandleRef + From<Handle> + Into<Handle> {
         self.as_handle_ref().duplicate(rights).map(|handle| Self::from(handle))
     }
 
+    /// Start building a duplicate of the handle whose rights are computed from those of the
+    /// original, for example with some rights removed. See `DuplicateOptions`.
+    fn duplicate_with(&self) -> DuplicateOptions<Self> {
+        DuplicateOptions::new(self)
+    }
+
     /// Create a replacement for a handle, possibly reducing the rights available. This invalidates
     /// the original handle. Wraps the
     /// [zx_handle_replace](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/handle_replace.md)
@@ -462,6 +775,14 @@ pub trait HandleBased: AsHandleRef + From<Handle> + Into<Handle> {
             .replace(rights).map(|handle| Self::from(handle))
     }
 
+    /// Close the handle, returning any error from the kernel. Dropping a handle also closes
+    /// it, but ignores errors. Wraps the
+    /// [zx_handle_close](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/handle_close.md)
+    /// syscall.
+    fn close(self) -> Result<(), Status> {
+        self.into_handle().close()
+    }
+
     /// Converts the value into its inner handle.
     ///
     /// This is a convenience function which simply forwards to the `Into` trait.
@@ -488,12 +809,40 @@ pub trait HandleBased: AsHandleRef + From<Handle> + Into<Handle> {
     }
 }
 
+/// A trait implemented by handle types which refer to a specific type of kernel object, allowing
+/// checked conversion from an untyped `Handle`.
+///
+/// `std::convert::TryFrom<Handle>` cannot be implemented for these types, as it is already
+/// provided (infallibly, without any check) by their `From<Handle>` implementations.
+pub trait TypedHandle: HandleBased {
+    /// The type of the objects that handles of this type refer to.
+    const OBJECT_TYPE: ObjectType;
+
+    /// Convert from an untyped `Handle`, checking that it refers to an object of the right type.
+    /// On failure the original handle is returned, together with `ErrWrongType` or any error
+    /// from looking up the type. Wraps the
+    /// [zx_object_get_info](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_get_info.md)
+    /// syscall with `ZX_INFO_HANDLE_BASIC`.
+    fn try_from_handle(handle: Handle) -> Result<Self, (Handle, Status)> {
+        match handle.object_type() {
+            Ok(object_type) if object_type == Self::OBJECT_TYPE => Ok(Self::from(handle)),
+            Ok(_) => Err((handle, Status::ErrWrongType)),
+            Err(status) => Err((handle, status)),
+        }
+    }
+}
+
 /// A trait implemented by all handles for objects which have a peer.
 pub trait Peered: HandleBased {
     /// Set and clear userspace-accessible signal bits on the object's peer. Wraps the
     /// [zx_object_signal_peer](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_signal.md)
     /// syscall.
+    ///
+    /// As with `signal_handle`, only the user signals may be set or cleared.
     fn signal_peer(&self, clear_mask: Signals, set_mask: Signals) -> Result<(), Status> {
+        if !Signals::USER_ALL.contains(clear_mask | set_mask) {
+            return Err(Status::ErrInvalidArgs);
+        }
         let handle = self.as_handle_ref().handle;
         let status = unsafe {
             sys::zx_object_signal_peer(handle, clear_mask.bits(), set_mask.bits())
@@ -525,10 +874,210 @@ pub trait Cookied: HandleBased {
     }
 }
 
+/// A trait implemented by the handles of tasks: jobs, processes and threads.
+pub trait Task: HandleBased {
+    /// Kill the task. Killing a job kills the jobs and processes within it, and killing a
+    /// process kills its threads. `Signals::TASK_TERMINATED` is asserted once it has died.
+    ///
+    /// Wraps the
+    /// [zx_task_kill](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/task_kill.md)
+    /// syscall.
+    fn kill(&self) -> Result<(), Status> {
+        let status = unsafe { sys::zx_task_kill(self.raw_handle()) };
+        into_result(status, || ())
+    }
+
+    /// Wrap the task in a guard which kills it when dropped, unless `KillOnDrop::disarm` is
+    /// called first.
+    fn kill_on_drop(self) -> KillOnDrop<Self> {
+        KillOnDrop { task: Some(self) }
+    }
+
+    /// Bind `port` as the exception port of the task, so that exceptions raised by its threads
+    /// are sent to it as `PacketContents::Exception` packets with the given key. The thread
+    /// which raised an exception waits until it is resumed or killed.
+    ///
+    /// Fails with `ErrAlreadyBound` if the task already has an exception port of that kind.
+    ///
+    /// Wraps the
+    /// [zx_task_bind_exception_port](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/task_bind_exception_port.md)
+    /// syscall.
+    fn bind_exception_port(&self, port: &Port, key: u64, opts: ExceptionPortOpts)
+        -> Result<(), Status>
+    {
+        let status = unsafe {
+            sys::zx_task_bind_exception_port(self.raw_handle(), port.raw_handle(), key,
+                opts as u32)
+        };
+        into_result(status, || ())
+    }
+
+    /// Unbind the exception port of the task of the given kind. Threads waiting on exceptions
+    /// sent to it are handled as though the port had not been bound.
+    ///
+    /// Wraps the
+    /// [zx_task_bind_exception_port](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/task_bind_exception_port.md)
+    /// syscall.
+    fn unbind_exception_port(&self, opts: ExceptionPortOpts) -> Result<(), Status> {
+        let status = unsafe {
+            sys::zx_task_bind_exception_port(self.raw_handle(), INVALID_HANDLE, 0,
+                opts as u32)
+        };
+        into_result(status, || ())
+    }
+
+    /// Suspend the task, returning a token which resumes it when dropped. Suspension is
+    /// asynchronous: `Signals::THREAD_SUSPENDED` is asserted once a thread has stopped. Only
+    /// threads can currently be suspended; for other tasks this fails with `ErrNotSupported`.
+    ///
+    /// Wraps the
+    /// [zx_task_suspend](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/task_suspend.md)
+    /// syscall.
+    fn suspend(&self) -> Result<SuspendToken<'_, Self>, Status> {
+        let status = unsafe { sys::zx_task_suspend(self.raw_handle()) };
+        into_result(status, || SuspendToken { task: self })
+    }
+}
+
+/// A task which is killed when dropped, unless disarmed first, created by `Task::kill_on_drop`.
+///
+/// This keeps a supervisor from leaving tasks running if it panics or returns early.
+#[derive(Debug)]
+pub struct KillOnDrop<T: Task> {
+    task: Option<T>,
+}
+
+impl<T: Task> KillOnDrop<T> {
+    /// Stop the task being killed when the guard is dropped, returning it.
+    pub fn disarm(mut self) -> T {
+        self.task.take().unwrap()
+    }
+}
+
+impl<T: Task> Deref for KillOnDrop<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.task.as_ref().unwrap()
+    }
+}
+
+impl<T: Task> AsHandleRef for KillOnDrop<T> {
+    fn as_handle_ref(&self) -> HandleRef {
+        self.deref().as_handle_ref()
+    }
+}
+
+impl<T: Task> Drop for KillOnDrop<T> {
+    fn drop(&mut self) {
+        if let Some(ref task) = self.task {
+            let _ = task.kill();
+        }
+    }
+}
+
+/// A token for a task suspended with `Task::suspend`, which resumes the task when dropped.
+#[must_use = "the task is resumed as soon as the token is dropped"]
+#[derive(Debug)]
+pub struct SuspendToken<'a, T: Task + 'a> {
+    task: &'a T,
+}
+
+impl<'a, T: Task> SuspendToken<'a, T> {
+    /// The suspended task.
+    pub fn task(&self) -> &'a T {
+        self.task
+    }
+
+    /// Resume the task, returning any error from the kernel. Dropping the token also resumes
+    /// it, but ignores errors.
+    ///
+    /// Wraps the
+    /// [zx_task_resume](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/task_resume.md)
+    /// syscall.
+    pub fn resume(self) -> Result<(), Status> {
+        let status = unsafe { sys::zx_task_resume(self.task.raw_handle(), 0) };
+        mem::forget(self);
+        into_result(status, || ())
+    }
+}
+
+impl<'a, T: Task> Drop for SuspendToken<'a, T> {
+    fn drop(&mut self) {
+        let _ = unsafe { sys::zx_task_resume(self.task.raw_handle(), 0) };
+    }
+}
+
 fn handle_drop(handle: sys::zx_handle_t) {
+    leak_check::untrack(handle);
     let _ = unsafe { sys::zx_handle_close(handle) };
 }
 
+// Perform an operation, and while it fails with `ErrShouldWait`, wait for `ready` to be
+// asserted on `handle` and try again. Fails with `ErrPeerClosed` if `peer_closed` is asserted
+// instead, or `ErrTimedOut` if the deadline passes.
+fn retry_when_ready<T, F>(handle: HandleRef, ready: Signals, peer_closed: Signals,
+    deadline: Deadline, mut op: F) -> Result<T, Status>
+    where F: FnMut() -> Result<T, Status>
+{
+    loop {
+        match op() {
+            Err(Status::ErrShouldWait) => {
+                let observed = handle.wait(ready | peer_closed, deadline)?;
+                if !observed.contains(ready) {
+                    return Err(Status::ErrPeerClosed);
+                }
+            }
+            result => return result,
+        }
+    }
+}
+
+// Whether any of `signals` is currently asserted on `handle`, checked without waiting.
+fn is_asserted(handle: HandleRef, signals: Signals) -> Result<bool, Status> {
+    match handle.wait(signals, Deadline::from_nanos(0)) {
+        Ok(_) => Ok(true),
+        Err(Status::ErrTimedOut) => Ok(false),
+        Err(status) => Err(status),
+    }
+}
+
+// The largest buffer kept by `with_gathered` between calls, so that one large write does not
+// hold on to memory for the rest of the thread's life.
+const GATHER_MAX_RETAINED: usize = 64 * 1024;
+
+thread_local! {
+    // The buffer used by `with_gathered`, reused between calls on the same thread. A `const`
+    // initializer would need Rust 1.59.
+    #[allow(clippy::missing_const_for_thread_local)]
+    static GATHER_SCRATCH: RefCell<Vec<u8>> = RefCell::new(Vec::new());
+}
+
+// Call `f` with the contents of `bufs` concatenated. They are gathered into a per-thread
+// buffer, which is kept for reuse unless it has grown beyond `GATHER_MAX_RETAINED` bytes.
+fn with_gathered<T, F>(bufs: &[IoSlice], f: F) -> T
+    where F: FnOnce(&[u8]) -> T
+{
+    let mut scratch = GATHER_SCRATCH.with(|scratch| mem::take(&mut *scratch.borrow_mut()));
+    scratch.clear();
+    for buf in bufs {
+        scratch.extend_from_slice(buf);
+    }
+    let result = f(&scratch);
+    if scratch.capacity() <= GATHER_MAX_RETAINED {
+        GATHER_SCRATCH.with(|cell| *cell.borrow_mut() = scratch);
+    }
+    result
+}
+
+/// Get a handle to the child of the given object with the given koid. Wraps the
+/// [zx_object_get_child](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_get_child.md)
+/// syscall.
+fn object_get_child(handle: HandleRef, koid: Koid, rights: Rights) -> Result<Handle, Status> {
+    let mut out = 0;
+    let status = unsafe { sys::zx_object_get_child(handle.handle, koid, rights.into_raw(), &mut out) };
+    into_result(status, || Handle::owned(out))
+}
+
 /// Wait on multiple handles.
 /// The success return value is a bool indicating whether one or more of the
 /// provided handle references was closed during the wait.
@@ -536,13 +1085,13 @@ fn handle_drop(handle: sys::zx_handle_t) {
 /// Wraps the
 /// [zx_object_wait_many](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_wait_many.md)
 /// syscall.
-pub fn object_wait_many(items: &mut [WaitItem], deadline: Time) -> Result<bool, Status>
+pub fn object_wait_many(items: &mut [WaitItem], deadline: Deadline) -> Result<bool, Status>
 {
     let len = try!(usize_into_u32(items.len()).map_err(|_| Status::ErrOutOfRange));
     let items_ptr = items.as_mut_ptr() as *mut sys::zx_wait_item_t;
-    let status = unsafe { sys::zx_object_wait_many( items_ptr, len, deadline) };
+    let status = unsafe { sys::zx_object_wait_many(items_ptr, len, deadline.into_nanos()) };
     if status == sys::ZX_ERR_CANCELED {
-        return Ok((true))
+        return Ok(true)
     }
     into_result(status, || false)
 }
@@ -560,7 +1109,8 @@ pub fn object_wait_many(items: &mut [WaitItem], deadline: Time) -> Result<bool,
 /// enforced in the type system; attempting to use them will result in errors
 /// returned by the kernel. These conversions don't change the underlying
 /// representation, but do change the type and thus what operations are available.
-#[derive(Debug, Eq, PartialEq, Hash)]
+#[derive(Eq, PartialEq, Hash)]
+#[repr(transparent)]
 pub struct Handle(sys::zx_handle_t);
 
 impl AsHandleRef for Handle {
@@ -571,24 +1121,71 @@ impl AsHandleRef for Handle {
 
 impl HandleBased for Handle {}
 
+impl fmt::Debug for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        debug_handle(self.as_handle_ref(), "Handle", f)
+    }
+}
+
+// Shared implementation of `Debug` for handle types: shows the raw handle value and, if it can
+// be looked up, the koid and type of the object it refers to.
+fn debug_handle(handle: HandleRef, type_name: &str, f: &mut fmt::Formatter) -> fmt::Result {
+    let mut debug = f.debug_struct(type_name);
+    debug.field("handle", &handle.raw_handle());
+    if let Ok(info) = handle.basic_info() {
+        debug.field("koid", &info.koid);
+        debug.field("object_type", &info.object_type);
+    }
+    debug.finish()
+}
+
 impl Drop for Handle {
     fn drop(&mut self) {
-        handle_drop(self.0)
+        if self.0 != INVALID_HANDLE {
+            handle_drop(self.0)
+        }
     }
 }
 
 impl Handle {
+    /// The invalid handle, which does not refer to any object. It can be used as a placeholder,
+    /// for example in a struct field that does not always hold a handle; dropping it does
+    /// nothing, and any operation on it fails with `ErrBadHandle`.
+    pub fn invalid() -> Handle {
+        Handle(INVALID_HANDLE)
+    }
+
     /// If a raw handle is obtained from some other source, this method converts
     /// it into a type-safe owned handle.
     pub unsafe fn from_raw(raw: sys::zx_handle_t) -> Handle {
+        Handle::owned(raw)
+    }
+
+    // Take ownership of a handle returned by the kernel.
+    fn owned(raw: sys::zx_handle_t) -> Handle {
+        leak_check::track(raw);
         Handle(raw)
     }
 
+    // Give up ownership of the handle without closing it, for a syscall which consumes it.
+    fn into_raw_untracked(self) -> sys::zx_handle_t {
+        let raw = self.0;
+        mem::forget(self);
+        leak_check::untrack(raw);
+        raw
+    }
+
     pub fn replace(self, rights: Rights) -> Result<Handle, Status> {
         let handle = self.0;
         let mut out = 0;
-        let status = unsafe { sys::zx_handle_replace(handle, rights, &mut out) };
-        into_result(status, || Handle(out))
+        let status = unsafe { sys::zx_handle_replace(handle, rights.into_raw(), &mut out) };
+        into_result(status, || Handle::owned(out))
+    }
+
+    pub fn close(self) -> Result<(), Status> {
+        let handle = self.into_raw_untracked();
+        let status = unsafe { sys::zx_handle_close(handle) };
+        into_result(status, || ())
     }
 }
 
@@ -599,7 +1196,7 @@ mod tests {
     #[test]
     fn monotonic_time_increases() {
         let time1 = time_get(ClockId::Monotonic);
-        nanosleep(deadline_after(1_000));
+        nanosleep(deadline_after(Duration::from_micros(1)));
         let time2 = time_get(ClockId::Monotonic);
         assert!(time2 > time1);
     }
@@ -607,7 +1204,7 @@ mod tests {
     #[test]
     fn utc_time_increases() {
         let time1 = time_get(ClockId::UTC);
-        nanosleep(deadline_after(1_000));
+        nanosleep(deadline_after(Duration::from_micros(1)));
         let time2 = time_get(ClockId::UTC);
         assert!(time2 > time1);
     }
@@ -615,7 +1212,7 @@ mod tests {
     #[test]
     fn thread_time_increases() {
         let time1 = time_get(ClockId::Thread);
-        nanosleep(deadline_after(1_000));
+        nanosleep(deadline_after(Duration::from_micros(1)));
         let time2 = time_get(ClockId::Thread);
         assert!(time2 > time1);
     }
@@ -623,7 +1220,7 @@ mod tests {
     #[test]
     fn ticks_increases() {
         let ticks1 = ticks_get();
-        nanosleep(deadline_after(1_000));
+        nanosleep(deadline_after(Duration::from_micros(1)));
         let ticks2 = ticks_get();
         assert!(ticks2 > ticks1);
     }
@@ -633,7 +1230,7 @@ mod tests {
         let sleep_ns = 1_000_000;  // 1ms
         let one_second_ns = 1_000_000_000; // 1 second in ns
         let ticks1 = ticks_get();
-        nanosleep(deadline_after(sleep_ns));
+        nanosleep(deadline_after(Duration::from_nanos(sleep_ns)));
         let ticks2 = ticks_get();
         // The number of ticks should have increased by at least 1 ms worth
         assert!(ticks2 > ticks1 + sleep_ns * ticks_per_second() / one_second_ns);
@@ -645,7 +1242,7 @@ mod tests {
     fn sleep() {
         let sleep_ns = 1_000_000;  // 1ms
         let time1 = time_get(ClockId::Monotonic);
-        nanosleep(deadline_after(sleep_ns));
+        nanosleep(deadline_after(Duration::from_nanos(sleep_ns)));
         let time2 = time_get(ClockId::Monotonic);
         assert!(time2 > time1 + sleep_ns);
     }
@@ -660,7 +1257,7 @@ mod tests {
         assert!(vmo.write(b"hello", 0).is_ok());
 
         // Replace, reducing rights to read.
-        let readonly_vmo = vmo.duplicate_handle(ZX_RIGHT_READ).unwrap();
+        let readonly_vmo = vmo.duplicate_handle(Rights::READ).unwrap();
         // Make sure we can read but not write.
         let mut read_vec = vec![0; hello_length];
         assert_eq!(readonly_vmo.read(&mut read_vec, 0).unwrap(), hello_length);
@@ -683,7 +1280,7 @@ mod tests {
         assert!(vmo.write(b"hello", 0).is_ok());
 
         // Replace, reducing rights to read.
-        let readonly_vmo = vmo.replace_handle(ZX_RIGHT_READ).unwrap();
+        let readonly_vmo = vmo.replace_handle(Rights::READ).unwrap();
         // Make sure we can read but not write.
         let mut read_vec = vec![0; hello_length];
         assert_eq!(readonly_vmo.read(&mut read_vec, 0).unwrap(), hello_length);
@@ -691,63 +1288,178 @@ mod tests {
         assert_eq!(readonly_vmo.write(b"", 0), Err(Status::ErrAccessDenied));
     }
 
+    #[test]
+    fn invalid_handle() {
+        let handle = Handle::invalid();
+        assert!(handle.is_invalid());
+        assert_eq!(handle.duplicate_handle(Rights::SAME_RIGHTS), Err(Status::ErrBadHandle));
+
+        let vmo = Vmo::from(handle);
+        assert!(vmo.is_invalid());
+        assert_eq!(vmo.get_size(), Err(Status::ErrBadHandle));
+
+        let vmo = Vmo::create(0, VmoOpts::Default).unwrap();
+        assert!(!vmo.is_invalid());
+    }
+
+    #[test]
+    fn page_rounding() {
+        let page = page_size();
+        assert_eq!(round_up_to_page(0), Some(0));
+        assert_eq!(round_up_to_page(1), Some(page));
+        assert_eq!(round_up_to_page(page), Some(page));
+        assert_eq!(round_up_to_page(usize::MAX), None);
+        assert_eq!(round_down_to_page(page * 2 - 1), page);
+        assert!(is_page_aligned(page * 3));
+        assert!(!is_page_aligned(page + 1));
+    }
+
+    #[test]
+    fn object_type() {
+        let vmo = Vmo::create(0, VmoOpts::Default).unwrap();
+        assert_eq!(vmo.object_type(), Ok(ObjectType::Vmo));
+        assert_eq!(vmo.into_handle().object_type(), Ok(ObjectType::Vmo));
+        assert_eq!(Handle::invalid().object_type(), Err(Status::ErrBadHandle));
+    }
+
+    #[test]
+    fn close() {
+        let vmo = Vmo::create(0, VmoOpts::Default).unwrap();
+        let duplicate = vmo.duplicate_handle(Rights::SAME_RIGHTS).unwrap();
+        assert_eq!(vmo.close(), Ok(()));
+        assert_eq!(duplicate.get_size(), Ok(0));
+        assert_eq!(duplicate.close(), Ok(()));
+
+        // Closing the invalid handle is allowed, and does nothing.
+        assert_eq!(Handle::invalid().close(), Ok(()));
+    }
+
+    #[test]
+    fn try_from_handle() {
+        let handle = Vmo::create(0, VmoOpts::Default).unwrap().into_handle();
+        let (handle, status) = Event::try_from_handle(handle).unwrap_err();
+        assert_eq!(status, Status::ErrWrongType);
+        let vmo = Vmo::try_from_handle(handle).unwrap();
+        assert_eq!(vmo.get_size(), Ok(0));
+
+        let (_, status) = Vmo::try_from_handle(Handle::invalid()).unwrap_err();
+        assert_eq!(status, Status::ErrBadHandle);
+    }
+
+    #[test]
+    fn get_info_single_record() {
+        let vmo = Vmo::create(0, VmoOpts::Default).unwrap();
+        let records = vmo.get_info::<HandleBasicQuery>().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(HandleBasicInfo::from(records[0]), vmo.basic_info().unwrap());
+    }
+
+    #[test]
+    fn debug_format() {
+        assert_eq!(format!("{:?}", Handle::invalid()), "Handle { handle: 0 }");
+
+        let vmo = Vmo::create(0, VmoOpts::Default).unwrap();
+        let info = vmo.basic_info().unwrap();
+        assert_eq!(format!("{:?}", vmo), format!("Vmo {{ handle: {}, koid: {}, object_type: Vmo }}",
+            vmo.raw_handle(), info.koid));
+    }
+
     #[test]
     fn wait_and_signal() {
         let event = Event::create(EventOpts::Default).unwrap();
-        let ten_ms: Duration = 10_000_000;
+        let ten_ms = Duration::from_millis(10);
 
         // Waiting on it without setting any signal should time out.
         assert_eq!(event.wait_handle(
-            ZX_USER_SIGNAL_0, deadline_after(ten_ms)), Err(Status::ErrTimedOut));
+            Signals::USER_0, deadline_after(ten_ms)), Err(Status::ErrTimedOut));
 
         // If we set a signal, we should be able to wait for it.
-        assert!(event.signal_handle(ZX_SIGNAL_NONE, ZX_USER_SIGNAL_0).is_ok());
-        assert_eq!(event.wait_handle(ZX_USER_SIGNAL_0, deadline_after(ten_ms)).unwrap(),
-            ZX_USER_SIGNAL_0 | ZX_SIGNAL_LAST_HANDLE);
+        assert!(event.signal_handle(Signals::NONE, Signals::USER_0).is_ok());
+        assert_eq!(event.wait_handle(Signals::USER_0, deadline_after(ten_ms)).unwrap(),
+            Signals::USER_0 | Signals::LAST_HANDLE);
 
         // Should still work, signals aren't automatically cleared.
-        assert_eq!(event.wait_handle(ZX_USER_SIGNAL_0, deadline_after(ten_ms)).unwrap(),
-            ZX_USER_SIGNAL_0 | ZX_SIGNAL_LAST_HANDLE);
+        assert_eq!(event.wait_handle(Signals::USER_0, deadline_after(ten_ms)).unwrap(),
+            Signals::USER_0 | Signals::LAST_HANDLE);
 
         // Now clear it, and waiting should time out again.
-        assert!(event.signal_handle(ZX_USER_SIGNAL_0, ZX_SIGNAL_NONE).is_ok());
+        assert!(event.signal_handle(Signals::USER_0, Signals::NONE).is_ok());
         assert_eq!(event.wait_handle(
-            ZX_USER_SIGNAL_0, deadline_after(ten_ms)), Err(Status::ErrTimedOut));
+            Signals::USER_0, deadline_after(ten_ms)), Err(Status::ErrTimedOut));
+    }
+
+    #[test]
+    fn wait_for_any_and_all() {
+        let event = Event::create(EventOpts::Default).unwrap();
+        let ten_ms = Duration::from_millis(10);
+        let both = Signals::USER_0 | Signals::USER_1;
+
+        assert!(event.signal_handle(Signals::NONE, Signals::USER_1).is_ok());
+        assert_eq!(event.wait_for_any(both, deadline_after(ten_ms)), Ok(Signals::USER_1));
+        assert_eq!(event.wait_for_all(both, deadline_after(ten_ms)), Err(Status::ErrTimedOut));
+
+        assert!(event.signal_handle(Signals::NONE, Signals::USER_0).is_ok());
+        assert_eq!(event.wait_for_any(both, deadline_after(ten_ms)), Ok(both));
+        assert_eq!(event.wait_for_all(both, deadline_after(ten_ms)), Ok(both));
+    }
+
+    #[test]
+    fn signal_non_user_bits() {
+        let event = Event::create(EventOpts::Default).unwrap();
+        assert_eq!(event.signal_handle(Signals::NONE, Signals::LAST_HANDLE),
+            Err(Status::ErrInvalidArgs));
+        assert_eq!(event.signal_handle(Signals::CHANNEL_READABLE, Signals::USER_0),
+            Err(Status::ErrInvalidArgs));
+        assert!(event.signal_handle(Signals::USER_1, Signals::USER_0).is_ok());
+    }
+
+    #[test]
+    fn user_signal_helpers() {
+        let event = Event::create(EventOpts::Default).unwrap();
+        let signal = UserSignal::new(3).unwrap();
+        let now = Deadline::from_nanos(0);
+        assert_eq!(event.wait_user_signal(signal, now), Err(Status::ErrTimedOut));
+        assert_eq!(event.assert_signal(signal), Ok(()));
+        assert_eq!(event.wait_user_signal(signal, now), Ok(()));
+        assert_eq!(event.wait_handle(Signals::USER_ALL, now),
+            Ok(Signals::USER_3 | Signals::LAST_HANDLE));
+        assert_eq!(event.clear_signal(signal), Ok(()));
+        assert_eq!(event.wait_user_signal(signal, now), Err(Status::ErrTimedOut));
     }
 
     #[test]
     fn wait_many_and_signal() {
-        let ten_ms: Duration = 10_000_000;
+        let ten_ms = Duration::from_millis(10);
         let e1 = Event::create(EventOpts::Default).unwrap();
         let e2 = Event::create(EventOpts::Default).unwrap();
 
         // Waiting on them now should time out.
         let mut items = vec![
-          WaitItem { handle: e1.as_handle_ref(), waitfor: ZX_USER_SIGNAL_0, pending: ZX_SIGNAL_NONE },
-          WaitItem { handle: e2.as_handle_ref(), waitfor: ZX_USER_SIGNAL_1, pending: ZX_SIGNAL_NONE },
+          WaitItem { handle: e1.as_handle_ref(), waitfor: Signals::USER_0, pending: Signals::NONE },
+          WaitItem { handle: e2.as_handle_ref(), waitfor: Signals::USER_1, pending: Signals::NONE },
         ];
         assert_eq!(object_wait_many(&mut items, deadline_after(ten_ms)), Err(Status::ErrTimedOut));
-        assert_eq!(items[0].pending, ZX_SIGNAL_LAST_HANDLE);
-        assert_eq!(items[1].pending, ZX_SIGNAL_LAST_HANDLE);
+        assert_eq!(items[0].pending, Signals::LAST_HANDLE);
+        assert_eq!(items[1].pending, Signals::LAST_HANDLE);
 
         // Signal one object and it should return success.
-        assert!(e1.signal_handle(ZX_SIGNAL_NONE, ZX_USER_SIGNAL_0).is_ok());
+        assert!(e1.signal_handle(Signals::NONE, Signals::USER_0).is_ok());
         assert!(object_wait_many(&mut items, deadline_after(ten_ms)).is_ok());
-        assert_eq!(items[0].pending, ZX_USER_SIGNAL_0 | ZX_SIGNAL_LAST_HANDLE);
-        assert_eq!(items[1].pending, ZX_SIGNAL_LAST_HANDLE);
+        assert_eq!(items[0].pending, Signals::USER_0 | Signals::LAST_HANDLE);
+        assert_eq!(items[1].pending, Signals::LAST_HANDLE);
 
         // Signal the other and it should return both.
-        assert!(e2.signal_handle(ZX_SIGNAL_NONE, ZX_USER_SIGNAL_1).is_ok());
+        assert!(e2.signal_handle(Signals::NONE, Signals::USER_1).is_ok());
         assert!(object_wait_many(&mut items, deadline_after(ten_ms)).is_ok());
-        assert_eq!(items[0].pending, ZX_USER_SIGNAL_0 | ZX_SIGNAL_LAST_HANDLE);
-        assert_eq!(items[1].pending, ZX_USER_SIGNAL_1 | ZX_SIGNAL_LAST_HANDLE);
+        assert_eq!(items[0].pending, Signals::USER_0 | Signals::LAST_HANDLE);
+        assert_eq!(items[1].pending, Signals::USER_1 | Signals::LAST_HANDLE);
 
         // Clear signals on both; now it should time out again.
-        assert!(e1.signal_handle(ZX_USER_SIGNAL_0, ZX_SIGNAL_NONE).is_ok());
-        assert!(e2.signal_handle(ZX_USER_SIGNAL_1, ZX_SIGNAL_NONE).is_ok());
+        assert!(e1.signal_handle(Signals::USER_0, Signals::NONE).is_ok());
+        assert!(e2.signal_handle(Signals::USER_1, Signals::NONE).is_ok());
         assert_eq!(object_wait_many(&mut items, deadline_after(ten_ms)), Err(Status::ErrTimedOut));
-        assert_eq!(items[0].pending, ZX_SIGNAL_LAST_HANDLE);
-        assert_eq!(items[1].pending, ZX_SIGNAL_LAST_HANDLE);
+        assert_eq!(items[0].pending, Signals::LAST_HANDLE);
+        assert_eq!(items[1].pending, Signals::LAST_HANDLE);
     }
 
     #[test]
@@ -774,6 +1486,18 @@ mod tests {
         assert_eq!(event.set_cookie(&event.as_handle_ref(), 123), Err(Status::ErrAccessDenied));
     }
 
+    #[test]
+    fn name() {
+        let vmo = Vmo::create(0, VmoOpts::Default).unwrap();
+        assert_eq!(vmo.set_name("test-vmo"), Ok(()));
+        assert_eq!(vmo.get_name(), Ok("test-vmo".to_string()));
+
+        // Overly long names are truncated.
+        let long_name = "a".repeat(ZX_MAX_NAME_LEN * 2);
+        assert_eq!(vmo.set_name(&long_name), Ok(()));
+        assert_eq!(vmo.get_name(), Ok("a".repeat(ZX_MAX_NAME_LEN - 1)));
+    }
+
     #[test]
     fn cprng() {
         let mut buffer = [0; 20];
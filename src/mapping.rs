@@ -0,0 +1,282 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Mappings of virtual memory objects into the address space of the current process.
+
+use {CanWrite, HandleBased, Rights, RightsMarker, Status, Vmar, VmarFlags, Vmo, VmoOp};
+use std::{fmt, slice};
+use std::ops::Range;
+use round_down_to_page;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+/// A range of a virtual memory object mapped into an address region with `Vmar::map_guarded`
+/// or `Vmo::map`, which records where it is mapped and is unmapped when dropped.
+///
+/// The mapping can be read as a byte slice, and written as one too if `R` is `ReadWrite`. It
+/// stays valid after the `Vmo` it was created from is closed.
+///
+/// The kernel does not prevent other holders of a handle to the object from changing its
+/// contents while it is mapped, which is why creating a mapping is unsafe, or from shrinking
+/// it, which makes accessing the unbacked part of the mapping fault. Only map objects whose
+/// other users can be trusted not to do so.
+pub struct Mapping<'a, R: RightsMarker> {
+    vmar: &'a Vmar,
+    addr: usize,
+    len: usize,
+    // A duplicate of the handle to the mapped object, and the offset of the mapping in it, for
+    // `decommit`. There is no duplicate if the handle lacked `Rights::DUPLICATE`.
+    vmo: Option<Vmo>,
+    vmo_offset: u64,
+    perms: PhantomData<R>,
+}
+
+impl<'a, R: RightsMarker> Mapping<'a, R> {
+    /// The address at which the range is mapped.
+    pub fn addr(&self) -> usize {
+        self.addr
+    }
+
+    /// The region the range is mapped in.
+    pub fn vmar(&self) -> &'a Vmar {
+        self.vmar
+    }
+
+    /// Change the permissions of the pages covering `range`, an offset range within the
+    /// mapping, to the `PERM_*` flags in `perms`. The start of the range is rounded down to a
+    /// page boundary, and the end up.
+    ///
+    /// Fails with `ErrOutOfRange` if the range extends past the end of the mapping.
+    ///
+    /// # Safety
+    ///
+    /// The mapping must not be accessed in ways the new permissions do not allow, including
+    /// through `as_slice` if the pages are no longer readable, or `as_mut_slice` if they are no
+    /// longer writable.
+    pub unsafe fn protect(&mut self, range: Range<usize>, perms: VmarFlags)
+        -> Result<(), Status>
+    {
+        self.check_range(&range)?;
+        let start = round_down_to_page(range.start);
+        self.vmar.protect(self.addr + start, range.end - start, perms)
+    }
+
+    /// Release the memory behind the pages covering `range`, an offset range within the
+    /// mapping, back to the kernel, without unmapping them. They read as zero afterwards.
+    ///
+    /// This decommits the pages of the mapped object, which requires the handle it was mapped
+    /// with to have had `Rights::DUPLICATE`, so that the mapping could keep a handle to it;
+    /// otherwise this fails with `ErrAccessDenied`. Fails with `ErrOutOfRange` if the range
+    /// extends past the end of the mapping.
+    pub fn decommit(&mut self, range: Range<usize>) -> Result<(), Status> {
+        self.check_range(&range)?;
+        let vmo = self.vmo.as_ref().ok_or(Status::ErrAccessDenied)?;
+        vmo.op_range(VmoOp::Decommit, self.vmo_offset + range.start as u64,
+            (range.end - range.start) as u64)
+    }
+
+    // Check that an offset range lies within the mapping.
+    fn check_range(&self, range: &Range<usize>) -> Result<(), Status> {
+        if range.start > range.end || range.end > self.len {
+            return Err(Status::ErrOutOfRange);
+        }
+        Ok(())
+    }
+
+    /// The mapped memory.
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.addr as *const u8, self.len) }
+    }
+}
+
+impl<'a, R: CanWrite> Mapping<'a, R> {
+    /// The mapped memory, for writing.
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.addr as *mut u8, self.len) }
+    }
+}
+
+impl<'a, R: RightsMarker> Deref for Mapping<'a, R> {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl<'a, R: CanWrite> DerefMut for Mapping<'a, R> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        self.as_mut_slice()
+    }
+}
+
+impl<'a, R: RightsMarker> Drop for Mapping<'a, R> {
+    fn drop(&mut self) {
+        // No references into the mapping can outlive it. The length is rounded up to whole
+        // pages, as the kernel mapped them.
+        let _ = unsafe { self.vmar.unmap(self.addr, self.len) };
+    }
+}
+
+impl<'a, R: RightsMarker> fmt::Debug for Mapping<'a, R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Mapping {{ addr: {:#x}, len: {}, rights: {:?} }}", self.addr, self.len,
+            R::rights())
+    }
+}
+
+// The permissions for mapping with the rights `R`.
+fn perms<R: RightsMarker>() -> VmarFlags {
+    let mut flags = VmarFlags::NONE;
+    if R::rights().contains(Rights::READ) {
+        flags |= VmarFlags::PERM_READ;
+    }
+    if R::rights().contains(Rights::WRITE) {
+        flags |= VmarFlags::PERM_WRITE;
+    }
+    flags
+}
+
+impl Vmar {
+    /// Map `len` bytes of a virtual memory object, starting at `vmo_offset`, into the region,
+    /// returning a `Mapping` which unmaps it when dropped. The permissions of the mapping are
+    /// given by `R`, which is `ReadOnly` or `ReadWrite`; `flags` gives any other flags, such as
+    /// `VmarFlags::SPECIFIC` to place the mapping at `vmar_offset`.
+    ///
+    /// Wraps the
+    /// [zx_vmar_map](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/vmar_map.md)
+    /// syscall.
+    ///
+    /// # Safety
+    ///
+    /// The mapping hands out ordinary Rust slices of the mapped memory, so while one of them is
+    /// in use, nothing else may write to that memory. In particular, another writable mapping
+    /// of the same part of the object (such as a second `Mapping`) must not be written at the
+    /// same time, and nothing, in this process or any other, may write to that part of the
+    /// object through a handle to it.
+    pub unsafe fn map_guarded<R: RightsMarker>(&self, vmar_offset: usize, vmo: &Vmo, vmo_offset: u64,
+        len: usize, flags: VmarFlags) -> Result<Mapping<'_, R>, Status>
+    {
+        let addr = self.map(vmar_offset, vmo, vmo_offset, len, flags | perms::<R>())?;
+        let vmo = vmo.duplicate_handle(Rights::SAME_RIGHTS).ok();
+        Ok(Mapping { vmar: self, addr, len, vmo, vmo_offset, perms: PhantomData })
+    }
+}
+
+impl Vmo {
+    /// Map `len` bytes of the virtual memory object, starting at `offset`, into the root address
+    /// space of the current process. The access allowed to the mapping is given by `R`, which
+    /// is `ReadOnly` or `ReadWrite`; the handle must have the corresponding rights, as well as
+    /// `Rights::MAP`.
+    ///
+    /// Equivalent to `Vmar::root_self().map_guarded(0, vmo, offset, len, VmarFlags::NONE)`.
+    ///
+    /// # Safety
+    ///
+    /// As with `Vmar::map_guarded`, nothing else may write to the mapped part of the object
+    /// while a slice of the mapping is in use.
+    pub unsafe fn map<R: RightsMarker>(&self, offset: u64, len: usize)
+        -> Result<Mapping<'static, R>, Status>
+    {
+        Vmar::root_self().map_guarded(0, self, offset, len, VmarFlags::NONE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {ReadOnly, ReadWrite, VmoOpts};
+
+    #[test]
+    fn vmo_map() {
+        let vmo = Vmo::create(4096, VmoOpts::Default).unwrap();
+        let mut mapping = unsafe { vmo.map::<ReadWrite>(0, 4096) }.unwrap();
+        mapping[..5].copy_from_slice(b"hello");
+        assert_eq!(mapping.len(), 4096);
+        assert_eq!(vmo.read_to_vec(0, 5), Ok(b"hello".to_vec()));
+
+        // Writes through the object are seen by the mapping, which outlives the handle. No
+        // slices of the mappings are in use while the object is written.
+        assert_eq!(vmo.write_all(b"J", 0), Ok(()));
+        let read_only = unsafe { vmo.map::<ReadOnly>(0, 4096) }.unwrap();
+        drop(vmo);
+        assert_eq!(&read_only[..5], b"Jello");
+        assert_eq!(&mapping[..5], b"Jello");
+    }
+
+    #[test]
+    fn vmar_map_guarded() {
+        let (vmar, base) = Vmar::root_self().allocate(0, 8192, VmarFlags::CAN_MAP_READ
+            | VmarFlags::CAN_MAP_WRITE | VmarFlags::CAN_MAP_SPECIFIC).unwrap();
+        let vmo = Vmo::create(4096, VmoOpts::Default).unwrap();
+        let mut mapping = unsafe {
+            vmar.map_guarded::<ReadWrite>(4096, &vmo, 0, 4096, VmarFlags::SPECIFIC)
+        }.unwrap();
+        assert_eq!(mapping.addr(), base + 4096);
+        mapping.as_mut_slice()[0] = 42;
+        assert_eq!(mapping.as_slice()[0], 42);
+
+        // Once the mapping is dropped, its range is free to be mapped again.
+        drop(mapping);
+        assert!(unsafe {
+            vmar.map_guarded::<ReadOnly>(4096, &vmo, 0, 4096, VmarFlags::SPECIFIC)
+        }.is_ok());
+    }
+
+    #[test]
+    fn mapping_decommit() {
+        let vmo = Vmo::create(8192, VmoOpts::Default).unwrap();
+        let mut mapping = unsafe { vmo.map::<ReadWrite>(0, 8192) }.unwrap();
+        mapping[0] = 1;
+        mapping[4096] = 2;
+        assert_eq!(mapping.decommit(0..4096), Ok(()));
+        assert_eq!((mapping[0], mapping[4096]), (0, 2));
+        assert_eq!(mapping.decommit(4096..12288), Err(Status::ErrOutOfRange));
+
+        let vmo = vmo.duplicate_with().remove(Rights::DUPLICATE).build().unwrap();
+        let mut mapping = unsafe { vmo.map::<ReadOnly>(0, 8192) }.unwrap();
+        assert_eq!(mapping.decommit(0..4096), Err(Status::ErrAccessDenied));
+    }
+
+    #[test]
+    fn mapping_protect() {
+        let vmo = Vmo::create(8192, VmoOpts::Default).unwrap();
+        let mut mapping = unsafe { vmo.map::<ReadWrite>(0, 8192) }.unwrap();
+        unsafe {
+            assert_eq!(mapping.protect(4096..8192, VmarFlags::PERM_READ), Ok(()));
+            assert_eq!(mapping.protect(0..8193, VmarFlags::PERM_READ),
+                Err(Status::ErrOutOfRange));
+        }
+        // The first page is still writable.
+        mapping[0] = 1;
+        assert_eq!(mapping[4096], 0);
+    }
+
+    #[test]
+    fn mapping_partial_page() {
+        let vmo = Vmo::create(4096, VmoOpts::Default).unwrap();
+        let mapping = unsafe { vmo.map::<ReadOnly>(0, 5) }.unwrap();
+        assert_eq!(mapping.len(), 5);
+        let addr = mapping.addr();
+        drop(mapping);
+
+        // The whole page was unmapped, so it can be mapped again in the same place.
+        let root = Vmar::root_self();
+        let offset = addr - root.info().unwrap().base;
+        let remapped = unsafe {
+            root.map_guarded::<ReadOnly>(offset, &vmo, 0, 4096, VmarFlags::SPECIFIC)
+        }.unwrap();
+        assert_eq!(remapped.addr(), addr);
+    }
+
+    #[test]
+    fn vmo_map_without_rights() {
+        let vmo = Vmo::create(4096, VmoOpts::Default).unwrap();
+        let read_only = vmo.duplicate_with().remove(Rights::WRITE).build().unwrap();
+        unsafe {
+            assert!(read_only.map::<ReadOnly>(0, 4096).is_ok());
+            assert_eq!(read_only.map::<ReadWrite>(0, 4096).unwrap_err(),
+                Status::ErrAccessDenied);
+        }
+    }
+}
@@ -0,0 +1,78 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! The types of kernel objects.
+
+use sys;
+
+/// The type of a kernel object, as reported by `AsHandleRef::object_type`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[repr(u32)]
+pub enum ObjectType {
+    None = sys::ZX_OBJ_TYPE_NONE,
+    Process = sys::ZX_OBJ_TYPE_PROCESS,
+    Thread = sys::ZX_OBJ_TYPE_THREAD,
+    Vmo = sys::ZX_OBJ_TYPE_VMO,
+    Channel = sys::ZX_OBJ_TYPE_CHANNEL,
+    Event = sys::ZX_OBJ_TYPE_EVENT,
+    Port = sys::ZX_OBJ_TYPE_PORT,
+    Interrupt = sys::ZX_OBJ_TYPE_INTERRUPT,
+    PciDevice = sys::ZX_OBJ_TYPE_PCI_DEVICE,
+    Log = sys::ZX_OBJ_TYPE_LOG,
+    Socket = sys::ZX_OBJ_TYPE_SOCKET,
+    Resource = sys::ZX_OBJ_TYPE_RESOURCE,
+    EventPair = sys::ZX_OBJ_TYPE_EVENT_PAIR,
+    Job = sys::ZX_OBJ_TYPE_JOB,
+    Vmar = sys::ZX_OBJ_TYPE_VMAR,
+    Fifo = sys::ZX_OBJ_TYPE_FIFO,
+    Guest = sys::ZX_OBJ_TYPE_GUEST,
+    Vcpu = sys::ZX_OBJ_TYPE_VCPU,
+    Timer = sys::ZX_OBJ_TYPE_TIMER,
+
+    /// Any zx_obj_type_t not in the set above will map to the following:
+    Unknown = u32::MAX,
+}
+
+impl ObjectType {
+    pub fn from_raw(raw: sys::zx_obj_type_t) -> Self {
+        match raw {
+            sys::ZX_OBJ_TYPE_NONE => ObjectType::None,
+            sys::ZX_OBJ_TYPE_PROCESS => ObjectType::Process,
+            sys::ZX_OBJ_TYPE_THREAD => ObjectType::Thread,
+            sys::ZX_OBJ_TYPE_VMO => ObjectType::Vmo,
+            sys::ZX_OBJ_TYPE_CHANNEL => ObjectType::Channel,
+            sys::ZX_OBJ_TYPE_EVENT => ObjectType::Event,
+            sys::ZX_OBJ_TYPE_PORT => ObjectType::Port,
+            sys::ZX_OBJ_TYPE_INTERRUPT => ObjectType::Interrupt,
+            sys::ZX_OBJ_TYPE_PCI_DEVICE => ObjectType::PciDevice,
+            sys::ZX_OBJ_TYPE_LOG => ObjectType::Log,
+            sys::ZX_OBJ_TYPE_SOCKET => ObjectType::Socket,
+            sys::ZX_OBJ_TYPE_RESOURCE => ObjectType::Resource,
+            sys::ZX_OBJ_TYPE_EVENT_PAIR => ObjectType::EventPair,
+            sys::ZX_OBJ_TYPE_JOB => ObjectType::Job,
+            sys::ZX_OBJ_TYPE_VMAR => ObjectType::Vmar,
+            sys::ZX_OBJ_TYPE_FIFO => ObjectType::Fifo,
+            sys::ZX_OBJ_TYPE_GUEST => ObjectType::Guest,
+            sys::ZX_OBJ_TYPE_VCPU => ObjectType::Vcpu,
+            sys::ZX_OBJ_TYPE_TIMER => ObjectType::Timer,
+            _ => ObjectType::Unknown,
+        }
+    }
+
+    pub fn into_raw(self) -> sys::zx_obj_type_t {
+        self as sys::zx_obj_type_t
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn object_type_raw() {
+        assert_eq!(ObjectType::from_raw(sys::ZX_OBJ_TYPE_CHANNEL), ObjectType::Channel);
+        assert_eq!(ObjectType::Timer.into_raw(), sys::ZX_OBJ_TYPE_TIMER);
+        assert_eq!(ObjectType::from_raw(1000), ObjectType::Unknown);
+    }
+}
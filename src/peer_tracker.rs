@@ -0,0 +1,114 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Tracking the lifetime of a resource across processes with an event pair.
+
+use {AsHandleRef, Deadline, EventPair, EventPairOpts, HandleRef, Status};
+use std::ops::{Deref, DerefMut};
+
+/// A resource tied to one end of an event pair, whose other end is held by a remote party.
+///
+/// When the tracker is dropped, the remote party sees `Signals::EPAIR_CLOSED` on its end, and
+/// can wait for it with `EventPair::wait_peer_closed`. In the other direction, the tracker sees
+/// the same signal once the remote party closes its end, or exits; a service which keeps one
+/// tracker per client can use this to release the client's resources, waiting for the signal
+/// on a port with the handle from `as_handle_ref`.
+#[derive(Debug)]
+pub struct PeerTracker<T> {
+    resource: T,
+    pair: EventPair,
+}
+
+impl<T> PeerTracker<T> {
+    /// Start tracking `resource`, returning the tracker and the end of the pair to give to the
+    /// remote party.
+    pub fn new(resource: T) -> Result<(PeerTracker<T>, EventPair), Status> {
+        let (pair, remote) = EventPair::create(EventPairOpts::Default)?;
+        Ok((PeerTracker { resource, pair }, remote))
+    }
+
+    /// Track `resource` with an existing end of an event pair, for example one received from
+    /// the remote party.
+    pub fn with_event_pair(resource: T, pair: EventPair) -> PeerTracker<T> {
+        PeerTracker { resource, pair }
+    }
+
+    /// Whether the remote party has closed its end of the pair.
+    pub fn is_peer_closed(&self) -> Result<bool, Status> {
+        self.pair.is_peer_closed()
+    }
+
+    /// Wait until the remote party has closed its end of the pair, or `deadline` passes. Fails
+    /// with `ErrTimedOut` if the deadline passes first.
+    pub fn wait_peer_closed(&self, deadline: Deadline) -> Result<(), Status> {
+        self.pair.wait_peer_closed(deadline)
+    }
+
+    /// Stop tracking the resource and return it. The end of the pair is closed, so the remote
+    /// party sees the resource as gone.
+    pub fn into_inner(self) -> T {
+        self.resource
+    }
+
+    /// Stop tracking the resource, returning it and the end of the pair, which stays open.
+    pub fn into_parts(self) -> (T, EventPair) {
+        (self.resource, self.pair)
+    }
+}
+
+impl<T> Deref for PeerTracker<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.resource
+    }
+}
+
+impl<T> DerefMut for PeerTracker<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.resource
+    }
+}
+
+impl<T> AsHandleRef for PeerTracker<T> {
+    fn as_handle_ref(&self) -> HandleRef {
+        self.pair.as_handle_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Duration;
+
+    #[test]
+    fn remote_sees_drop() {
+        let (tracker, remote) = PeerTracker::new(vec![1, 2, 3]).unwrap();
+        assert_eq!(tracker.len(), 3);
+        assert_eq!(remote.is_peer_closed(), Ok(false));
+        assert_eq!(tracker.into_inner(), vec![1, 2, 3]);
+        assert_eq!(remote.wait_peer_closed(Deadline::INFINITE), Ok(()));
+    }
+
+    #[test]
+    fn tracker_sees_remote_close() {
+        let (mut tracker, remote) = PeerTracker::new(0).unwrap();
+        *tracker += 1;
+        assert_eq!(tracker.wait_peer_closed(Deadline::after(Duration::from_millis(10))),
+            Err(Status::ErrTimedOut));
+        drop(remote);
+        assert_eq!(tracker.is_peer_closed(), Ok(true));
+        assert_eq!(*tracker, 1);
+    }
+
+    #[test]
+    fn into_parts_keeps_pair_open() {
+        let (tracker, remote) = PeerTracker::new("client").unwrap();
+        let (resource, pair) = tracker.into_parts();
+        assert_eq!(resource, "client");
+        assert_eq!(remote.is_peer_closed(), Ok(false));
+        let tracker = PeerTracker::with_event_pair(resource, pair);
+        drop(tracker);
+        assert_eq!(remote.is_peer_closed(), Ok(true));
+    }
+}
@@ -6,16 +6,16 @@
 
 use std::mem;
 
-use {AsHandleRef, HandleBased, Handle, HandleRef, Signals, Status, Time};
+use {AsHandleRef, Deadline, HandleBased, Handle, HandleRef, Koid, ObjectType, Signals, Status};
 use {sys, into_result};
 
 /// An object representing a Zircon
 /// [port](https://fuchsia.googlesource.com/zircon/+/master/docs/objects/port.md).
 ///
 /// As essentially a subtype of `Handle`, it can be freely interconverted.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Eq, PartialEq)]
 pub struct Port(Handle);
-impl_handle_based!(Port);
+impl_handle_based!(Port, ObjectType::Port);
 
 /// A packet sent through a port. This is a type-safe wrapper for
 /// [zx_port_packet_t](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/port_wait2.md).
@@ -31,6 +31,9 @@ pub enum PacketContents {
     SignalOne(SignalPacket),
     /// A repeating signal packet generated via `object_wait_async`.
     SignalRep(SignalPacket),
+    /// An exception raised by a task whose exception port is the port, bound with
+    /// `Task::bind_exception_port`.
+    Exception(ExceptionPacket),
 }
 
 /// Contents of a user packet (one sent by `port_queue`). This is a type-safe wrapper for
@@ -43,13 +46,65 @@ pub struct UserPacket(sys::zx_packet_user_t);
 #[derive(Debug, Copy, Clone)]
 pub struct SignalPacket(sys::zx_packet_signal_t);
 
+/// Contents of an exception packet (one sent to an exception port by the kernel). This is a
+/// type-safe wrapper for
+/// [zx_packet_exception_t](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/port_wait2.md).
+#[derive(Debug, Copy, Clone)]
+pub struct ExceptionPacket {
+    raw_type: sys::zx_packet_type_t,
+    exception: sys::zx_packet_exception_t,
+}
+
+/// The type of an exception reported in an `ExceptionPacket`.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ExceptionType {
+    /// An architectural exception which is not one of the more specific types below.
+    General = sys::ZX_EXCP_GENERAL,
+    /// A page fault which could not be resolved, such as an access to unmapped memory.
+    FatalPageFault = sys::ZX_EXCP_FATAL_PAGE_FAULT,
+    /// An undefined or illegal instruction.
+    UndefinedInstruction = sys::ZX_EXCP_UNDEFINED_INSTRUCTION,
+    /// A software breakpoint instruction.
+    SwBreakpoint = sys::ZX_EXCP_SW_BREAKPOINT,
+    /// A hardware breakpoint or watchpoint.
+    HwBreakpoint = sys::ZX_EXCP_HW_BREAKPOINT,
+    /// A misaligned memory access.
+    UnalignedAccess = sys::ZX_EXCP_UNALIGNED_ACCESS,
+    /// A thread is starting. Only sent to debugger exception ports.
+    ThreadStarting = sys::ZX_EXCP_THREAD_STARTING,
+    /// A thread is exiting. Only sent to debugger exception ports.
+    ThreadExiting = sys::ZX_EXCP_THREAD_EXITING,
+    /// A job policy set with `Job::set_policy` was violated.
+    PolicyError = sys::ZX_EXCP_POLICY_ERROR,
+}
+
+impl ExceptionType {
+    /// Convert from the type of an exception packet, returning `None` if it is not a type this
+    /// crate knows about.
+    pub fn from_raw(raw: sys::zx_packet_type_t) -> Option<ExceptionType> {
+        match raw {
+            sys::ZX_EXCP_GENERAL => Some(ExceptionType::General),
+            sys::ZX_EXCP_FATAL_PAGE_FAULT => Some(ExceptionType::FatalPageFault),
+            sys::ZX_EXCP_UNDEFINED_INSTRUCTION => Some(ExceptionType::UndefinedInstruction),
+            sys::ZX_EXCP_SW_BREAKPOINT => Some(ExceptionType::SwBreakpoint),
+            sys::ZX_EXCP_HW_BREAKPOINT => Some(ExceptionType::HwBreakpoint),
+            sys::ZX_EXCP_UNALIGNED_ACCESS => Some(ExceptionType::UnalignedAccess),
+            sys::ZX_EXCP_THREAD_STARTING => Some(ExceptionType::ThreadStarting),
+            sys::ZX_EXCP_THREAD_EXITING => Some(ExceptionType::ThreadExiting),
+            sys::ZX_EXCP_POLICY_ERROR => Some(ExceptionType::PolicyError),
+            _ => None,
+        }
+    }
+}
+
 impl Packet {
     /// Creates a new packet with `UserPacket` data.
     pub fn from_user_packet(key: u64, status: i32, user: UserPacket) -> Packet {
         Packet(
             sys::zx_port_packet_t {
                 key: key,
-                packet_type: sys::zx_packet_type_t::ZX_PKT_TYPE_USER,
+                packet_type: sys::ZX_PKT_TYPE_USER,
                 status: status,
                 union: user.0,
             }
@@ -69,12 +124,17 @@ impl Packet {
 
     /// The contents of the packet.
     pub fn contents(&self) -> PacketContents {
-        if self.0.packet_type == sys::zx_packet_type_t::ZX_PKT_TYPE_USER {
+        if self.0.packet_type == sys::ZX_PKT_TYPE_USER {
             PacketContents::User(UserPacket(self.0.union))
-        } else if self.0.packet_type == sys::zx_packet_type_t::ZX_PKT_TYPE_SIGNAL_ONE {
+        } else if self.0.packet_type == sys::ZX_PKT_TYPE_SIGNAL_ONE {
             PacketContents::SignalOne(SignalPacket(unsafe { mem::transmute_copy(&self.0.union) }))
-        } else if self.0.packet_type == sys::zx_packet_type_t::ZX_PKT_TYPE_SIGNAL_REP {
+        } else if self.0.packet_type == sys::ZX_PKT_TYPE_SIGNAL_REP {
             PacketContents::SignalRep(SignalPacket(unsafe { mem::transmute_copy(&self.0.union) }))
+        } else if self.0.packet_type & sys::ZX_PKT_TYPE_MASK == sys::ZX_PKT_TYPE_EXCEPTION {
+            PacketContents::Exception(ExceptionPacket {
+                raw_type: self.0.packet_type,
+                exception: unsafe { mem::transmute_copy(&self.0.union) },
+            })
         } else {
             panic!("unexpected packet type");
         }
@@ -98,12 +158,12 @@ impl UserPacket {
 impl SignalPacket {
     /// The signals used in the call to `object_wait_async`.
     pub fn trigger(&self) -> Signals {
-        self.0.trigger
+        Signals::from_raw(self.0.trigger)
     }
 
     /// The observed signals.
     pub fn observed(&self) -> Signals {
-        self.0.observed
+        Signals::from_raw(self.0.observed)
     }
 
     /// A per object count of pending operations.
@@ -112,6 +172,28 @@ impl SignalPacket {
     }
 }
 
+impl ExceptionPacket {
+    /// The type of the exception, or `None` if it is not a type this crate knows about.
+    pub fn exception_type(&self) -> Option<ExceptionType> {
+        ExceptionType::from_raw(self.raw_type)
+    }
+
+    /// The type of the exception, as the raw type of the packet.
+    pub fn raw_type(&self) -> sys::zx_packet_type_t {
+        self.raw_type
+    }
+
+    /// The koid of the process in which the exception was raised.
+    pub fn pid(&self) -> Koid {
+        self.exception.pid
+    }
+
+    /// The koid of the thread which raised the exception.
+    pub fn tid(&self) -> Koid {
+        self.exception.tid
+    }
+}
+
 impl Port {
     /// Create an IO port, allowing IO packets to be read and enqueued.
     ///
@@ -122,7 +204,7 @@ impl Port {
         unsafe {
             let mut handle = 0;
             let status = sys::zx_port_create(opts as u32, &mut handle);
-            into_result(status, || Self::from(Handle(handle)))
+            into_result(status, || Self::from(Handle::owned(handle)))
         }
     }
 
@@ -144,10 +226,10 @@ impl Port {
     /// Wraps the
     /// [zx_port_wait](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/port_wait2.md)
     /// syscall.
-    pub fn wait(&self, deadline: Time) -> Result<Packet, Status> {
+    pub fn wait(&self, deadline: Deadline) -> Result<Packet, Status> {
         let mut packet = Default::default();
         let status = unsafe {
-            sys::zx_port_wait(self.raw_handle(), deadline,
+            sys::zx_port_wait(self.raw_handle(), deadline.into_nanos(),
                 &mut packet as *mut sys::zx_port_packet_t as *mut u8, 0)
         };
         into_result(status, || Packet(packet))
@@ -158,7 +240,7 @@ impl Port {
     /// Wraps the
     /// [zx_port_cancel](https://fuchsia.googlesource.com/zircon/+/HEAD/docs/syscalls/port_cancel.md)
     /// syscall.
-    pub fn cancel<H>(&self, source: &H, key: u64) -> Result<(), Status> where H: HandleBased {
+    pub fn cancel<H>(&self, source: &H, key: u64) -> Result<(), Status> where H: AsHandleRef {
         let status = unsafe {
             sys::zx_port_cancel(self.raw_handle(), source.raw_handle(), key)
         };
@@ -180,11 +262,32 @@ impl Default for PortOpts {
     }
 }
 
+/// Options for binding an exception port with `Task::bind_exception_port`.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ExceptionPortOpts {
+    /// Bind the task's ordinary exception port, which receives exceptions that were not handled
+    /// by the port of a more specific task.
+    Default = 0,
+    /// Bind the debugger exception port of a process, which receives every exception in the
+    /// process first, as well as thread start and exit reports.
+    Debugger = sys::ZX_EXCEPTION_PORT_DEBUGGER,
+}
+
+impl Default for ExceptionPortOpts {
+    fn default() -> Self {
+        ExceptionPortOpts::Default
+    }
+}
+
 /// Options for wait_async.
 #[repr(u32)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum WaitAsyncOpts {
+    /// Deliver a single packet the next time the signals are asserted, then disarm.
     Once = sys::ZX_WAIT_ASYNC_ONCE,
+    /// Deliver a packet every time the signals transition to being asserted, until cancelled
+    /// with `Port::cancel` or the handle is closed.
     Repeating = sys::ZX_WAIT_ASYNC_REPEATING,
 }
 
@@ -192,12 +295,11 @@ pub enum WaitAsyncOpts {
 mod tests {
     use super::*;
     use {Duration, Event, EventOpts};
-    use {ZX_SIGNAL_LAST_HANDLE, ZX_SIGNAL_NONE, ZX_USER_SIGNAL_0, ZX_USER_SIGNAL_1};
     use deadline_after;
 
     #[test]
     fn port_basic() {
-        let ten_ms: Duration = 10_000_000;
+        let ten_ms = Duration::from_millis(10);
 
         let port = Port::create(PortOpts::Default).unwrap();
 
@@ -217,29 +319,48 @@ mod tests {
         assert_eq!(read_packet, packet);
     }
 
+    #[test]
+    fn exception_packet() {
+        let exception = sys::zx_packet_exception_t { pid: 1, tid: 2, reserved0: 0, reserved1: 0 };
+        let packet = Packet(sys::zx_port_packet_t {
+            key: 7,
+            packet_type: sys::ZX_EXCP_SW_BREAKPOINT,
+            status: 0,
+            union: unsafe { mem::transmute::<sys::zx_packet_exception_t, [u8; 32]>(exception) },
+        });
+        match packet.contents() {
+            PacketContents::Exception(exception) => {
+                assert_eq!(exception.exception_type(), Some(ExceptionType::SwBreakpoint));
+                assert_eq!((exception.pid(), exception.tid()), (1, 2));
+            }
+            _ => panic!("wrong packet type"),
+        }
+        assert_eq!(ExceptionType::from_raw(0x7f08), None);
+    }
+
     #[test]
     fn wait_async_once() {
-        let ten_ms: Duration = 10_000_000;
+        let ten_ms = Duration::from_millis(10);
         let key = 42;
 
         let port = Port::create(PortOpts::Default).unwrap();
         let event = Event::create(EventOpts::Default).unwrap();
 
-        assert!(event.wait_async_handle(&port, key, ZX_USER_SIGNAL_0 | ZX_USER_SIGNAL_1,
+        assert!(event.wait_async_handle(&port, key, Signals::USER_0 | Signals::USER_1,
             WaitAsyncOpts::Once).is_ok());
 
         // Waiting without setting any signal should time out.
         assert_eq!(port.wait(deadline_after(ten_ms)), Err(Status::ErrTimedOut));
 
         // If we set a signal, we should be able to wait for it.
-        assert!(event.signal_handle(ZX_SIGNAL_NONE, ZX_USER_SIGNAL_0).is_ok());
+        assert!(event.signal_handle(Signals::NONE, Signals::USER_0).is_ok());
         let read_packet = port.wait(deadline_after(ten_ms)).unwrap();
         assert_eq!(read_packet.key(), key);
         assert_eq!(read_packet.status(), 0);
         match read_packet.contents() {
             PacketContents::SignalOne(sig) => {
-                assert_eq!(sig.trigger(), ZX_USER_SIGNAL_0 | ZX_USER_SIGNAL_1);
-                assert_eq!(sig.observed(), ZX_USER_SIGNAL_0 | ZX_SIGNAL_LAST_HANDLE);
+                assert_eq!(sig.trigger(), Signals::USER_0 | Signals::USER_1);
+                assert_eq!(sig.observed(), Signals::USER_0 | Signals::LAST_HANDLE);
                 assert_eq!(sig.count(), 1);
             }
             _ => panic!("wrong packet type"),
@@ -249,14 +370,14 @@ mod tests {
         assert_eq!(port.wait(deadline_after(ten_ms)), Err(Status::ErrTimedOut));
 
         // Calling wait_async again should result in another packet.
-        assert!(event.wait_async_handle(&port, key, ZX_USER_SIGNAL_0, WaitAsyncOpts::Once).is_ok());
+        assert!(event.wait_async_handle(&port, key, Signals::USER_0, WaitAsyncOpts::Once).is_ok());
         let read_packet = port.wait(deadline_after(ten_ms)).unwrap();
         assert_eq!(read_packet.key(), key);
         assert_eq!(read_packet.status(), 0);
         match read_packet.contents() {
             PacketContents::SignalOne(sig) => {
-                assert_eq!(sig.trigger(), ZX_USER_SIGNAL_0);
-                assert_eq!(sig.observed(), ZX_USER_SIGNAL_0 | ZX_SIGNAL_LAST_HANDLE);
+                assert_eq!(sig.trigger(), Signals::USER_0);
+                assert_eq!(sig.observed(), Signals::USER_0 | Signals::LAST_HANDLE);
                 assert_eq!(sig.count(), 1);
             }
             _ => panic!("wrong packet type"),
@@ -264,41 +385,41 @@ mod tests {
 
         // Calling wait_async_handle then cancel, we should not get a packet as cancel will
         // remove it from  the queue.
-        assert!(event.wait_async_handle(&port, key, ZX_USER_SIGNAL_0, WaitAsyncOpts::Once).is_ok());
+        assert!(event.wait_async_handle(&port, key, Signals::USER_0, WaitAsyncOpts::Once).is_ok());
         assert!(port.cancel(&event, key).is_ok());
         assert_eq!(port.wait(deadline_after(ten_ms)), Err(Status::ErrTimedOut));
 
         // If the event is signalled after the cancel, we also shouldn't get a packet.
-        assert!(event.signal_handle(ZX_USER_SIGNAL_0, ZX_SIGNAL_NONE).is_ok());  // clear signal
-        assert!(event.wait_async_handle(&port, key, ZX_USER_SIGNAL_0, WaitAsyncOpts::Once).is_ok());
+        assert!(event.signal_handle(Signals::USER_0, Signals::NONE).is_ok());  // clear signal
+        assert!(event.wait_async_handle(&port, key, Signals::USER_0, WaitAsyncOpts::Once).is_ok());
         assert!(port.cancel(&event, key).is_ok());
-        assert!(event.signal_handle(ZX_SIGNAL_NONE, ZX_USER_SIGNAL_0).is_ok());
+        assert!(event.signal_handle(Signals::NONE, Signals::USER_0).is_ok());
         assert_eq!(port.wait(deadline_after(ten_ms)), Err(Status::ErrTimedOut));
     }
 
     #[test]
     fn wait_async_repeating() {
-        let ten_ms: Duration = 10_000_000;
+        let ten_ms = Duration::from_millis(10);
         let key = 42;
 
         let port = Port::create(PortOpts::Default).unwrap();
         let event = Event::create(EventOpts::Default).unwrap();
 
-        assert!(event.wait_async_handle(&port, key, ZX_USER_SIGNAL_0 | ZX_USER_SIGNAL_1,
+        assert!(event.wait_async_handle(&port, key, Signals::USER_0 | Signals::USER_1,
             WaitAsyncOpts::Repeating).is_ok());
 
         // Waiting without setting any signal should time out.
         assert_eq!(port.wait(deadline_after(ten_ms)), Err(Status::ErrTimedOut));
 
         // If we set a signal, we should be able to wait for it.
-        assert!(event.signal_handle(ZX_SIGNAL_NONE, ZX_USER_SIGNAL_0).is_ok());
+        assert!(event.signal_handle(Signals::NONE, Signals::USER_0).is_ok());
         let read_packet = port.wait(deadline_after(ten_ms)).unwrap();
         assert_eq!(read_packet.key(), key);
         assert_eq!(read_packet.status(), 0);
         match read_packet.contents() {
             PacketContents::SignalRep(sig) => {
-                assert_eq!(sig.trigger(), ZX_USER_SIGNAL_0 | ZX_USER_SIGNAL_1);
-                assert_eq!(sig.observed(), ZX_USER_SIGNAL_0 | ZX_SIGNAL_LAST_HANDLE);
+                assert_eq!(sig.trigger(), Signals::USER_0 | Signals::USER_1);
+                assert_eq!(sig.observed(), Signals::USER_0 | Signals::LAST_HANDLE);
                 assert_eq!(sig.count(), 1);
             }
             _ => panic!("wrong packet type"),
@@ -310,15 +431,15 @@ mod tests {
 
         // If we clear and resignal, we should get the same packet again,
         // even though we didn't call event.wait_async again.
-        assert!(event.signal_handle(ZX_USER_SIGNAL_0, ZX_SIGNAL_NONE).is_ok());  // clear signal
-        assert!(event.signal_handle(ZX_SIGNAL_NONE, ZX_USER_SIGNAL_0).is_ok());
+        assert!(event.signal_handle(Signals::USER_0, Signals::NONE).is_ok());  // clear signal
+        assert!(event.signal_handle(Signals::NONE, Signals::USER_0).is_ok());
         let read_packet = port.wait(deadline_after(ten_ms)).unwrap();
         assert_eq!(read_packet.key(), key);
         assert_eq!(read_packet.status(), 0);
         match read_packet.contents() {
             PacketContents::SignalRep(sig) => {
-                assert_eq!(sig.trigger(), ZX_USER_SIGNAL_0 | ZX_USER_SIGNAL_1);
-                assert_eq!(sig.observed(), ZX_USER_SIGNAL_0 | ZX_SIGNAL_LAST_HANDLE);
+                assert_eq!(sig.trigger(), Signals::USER_0 | Signals::USER_1);
+                assert_eq!(sig.observed(), Signals::USER_0 | Signals::LAST_HANDLE);
                 assert_eq!(sig.count(), 1);
             }
             _ => panic!("wrong packet type"),
@@ -328,20 +449,20 @@ mod tests {
         assert!(port.cancel(&event, key).is_ok());
         assert_eq!(port.wait(deadline_after(ten_ms)), Err(Status::ErrTimedOut));
         // ... even if we clear and resignal
-        assert!(event.signal_handle(ZX_USER_SIGNAL_0, ZX_SIGNAL_NONE).is_ok());  // clear signal
-        assert!(event.signal_handle(ZX_SIGNAL_NONE, ZX_USER_SIGNAL_0).is_ok());
+        assert!(event.signal_handle(Signals::USER_0, Signals::NONE).is_ok());  // clear signal
+        assert!(event.signal_handle(Signals::NONE, Signals::USER_0).is_ok());
         assert_eq!(port.wait(deadline_after(ten_ms)), Err(Status::ErrTimedOut));
 
         // Calling wait_async again should result in another packet.
         assert!(event.wait_async_handle(
-            &port, key, ZX_USER_SIGNAL_0, WaitAsyncOpts::Repeating).is_ok());
+            &port, key, Signals::USER_0, WaitAsyncOpts::Repeating).is_ok());
         let read_packet = port.wait(deadline_after(ten_ms)).unwrap();
         assert_eq!(read_packet.key(), key);
         assert_eq!(read_packet.status(), 0);
         match read_packet.contents() {
             PacketContents::SignalRep(sig) => {
-                assert_eq!(sig.trigger(), ZX_USER_SIGNAL_0);
-                assert_eq!(sig.observed(), ZX_USER_SIGNAL_0 | ZX_SIGNAL_LAST_HANDLE);
+                assert_eq!(sig.trigger(), Signals::USER_0);
+                assert_eq!(sig.observed(), Signals::USER_0 | Signals::LAST_HANDLE);
                 assert_eq!(sig.count(), 1);
             }
             _ => panic!("wrong packet type"),
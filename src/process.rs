@@ -4,11 +4,206 @@
 
 //! Type-safe bindings for Zircon process.
 
-use {AsHandleRef, HandleBased, Handle, HandleRef};
+use {AsHandleRef, Deadline, HandleBased, Handle, HandleRef, Job, Koid, ObjectType, ProcessInfo};
+use {ProcessQuery, ProcessThreadsQuery, Rights, Signals, Status, Task, TaskStats, TaskStatsQuery};
+use {Thread, Unowned, Vmar};
+use {object_get_child, object_get_info_single, sys, into_result, usize_into_u32};
 
 /// An object representing a Zircon process.
 ///
 /// As essentially a subtype of `Handle`, it can be freely interconverted.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Eq, PartialEq)]
 pub struct Process(Handle);
-impl_handle_based!(Process);
\ No newline at end of file
+impl_handle_based!(Process, ObjectType::Process);
+impl Task for Process {}
+
+impl Process {
+    /// Create a new process within `job`, along with a handle to the root region of its address
+    /// space. The process has no threads and does not run until `start` is called.
+    ///
+    /// Wraps the
+    /// [zx_process_create](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/process_create.md)
+    /// syscall.
+    pub fn create(job: &Job, name: &str, opts: ProcessOpts) -> Result<(Process, Vmar), Status> {
+        let name_len = usize_into_u32(name.len()).map_err(|_| Status::ErrOutOfRange)?;
+        let mut process = 0;
+        let mut vmar = 0;
+        let status = unsafe {
+            sys::zx_process_create(job.raw_handle(), name.as_ptr(), name_len, opts as u32,
+                &mut process, &mut vmar)
+        };
+        into_result(status, ||
+            (Process::from(Handle::owned(process)), Vmar::from(Handle::owned(vmar))))
+    }
+
+    /// Start the process running, with `thread`, one of its threads which has not yet been
+    /// started, beginning execution at `entry` with its stack pointer set to `stack`. The thread
+    /// receives `arg1`, transferred into the process, and `arg2` as its first two arguments.
+    ///
+    /// `arg1` is consumed even if this fails.
+    ///
+    /// Wraps the
+    /// [zx_process_start](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/process_start.md)
+    /// syscall.
+    pub fn start(&self, thread: &Thread, entry: usize, stack: usize, arg1: Handle, arg2: usize)
+        -> Result<(), Status>
+    {
+        let raw = arg1.into_raw_untracked();
+        let status = unsafe {
+            sys::zx_process_start(self.raw_handle(), thread.raw_handle(), entry, stack, raw, arg2)
+        };
+        into_result(status, || ())
+    }
+
+    /// Whether the process has started or exited, and the code it exited with.
+    ///
+    /// Wraps the `ZX_INFO_PROCESS` topic of
+    /// [zx_object_get_info](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_get_info.md).
+    pub fn info(&self) -> Result<ProcessInfo, Status> {
+        object_get_info_single::<ProcessQuery>(self.as_handle_ref()).map(ProcessInfo::from)
+    }
+
+    /// The code the process exited with. Fails with `ErrBadState` if it has not exited yet;
+    /// wait for `Signals::PROCESS_TERMINATED` first.
+    pub fn exit_code(&self) -> Result<i64, Status> {
+        let info = self.info()?;
+        if !info.exited {
+            return Err(Status::ErrBadState);
+        }
+        Ok(info.return_code)
+    }
+
+    /// How much memory the process has mapped, and how much of it is private to the process or
+    /// shared with others.
+    ///
+    /// Wraps the `ZX_INFO_TASK_STATS` topic of
+    /// [zx_object_get_info](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_get_info.md).
+    pub fn task_stats(&self) -> Result<TaskStats, Status> {
+        object_get_info_single::<TaskStatsQuery>(self.as_handle_ref()).map(TaskStats::from)
+    }
+
+    /// Wait until the process has terminated, or `deadline` passes, and return the code it
+    /// exited with. Fails with `ErrTimedOut` if the deadline passes first.
+    pub fn wait_for_exit(&self, deadline: Deadline) -> Result<i64, Status> {
+        self.wait_handle(Signals::TASK_TERMINATED, deadline)?;
+        self.exit_code()
+    }
+
+    /// Read memory of the process at `vaddr` into `buffer`, returning the number of bytes read,
+    /// which may be fewer than requested if part of the range is not mapped. This is meant for
+    /// debuggers; the handle needs `Rights::READ` and `Rights::WRITE`.
+    ///
+    /// Wraps the
+    /// [zx_process_read_memory](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/process_read_memory.md)
+    /// syscall.
+    pub fn read_memory(&self, vaddr: usize, buffer: &mut [u8]) -> Result<usize, Status> {
+        let mut actual = 0;
+        let status = unsafe {
+            sys::zx_process_read_memory(self.raw_handle(), vaddr, buffer.as_mut_ptr(),
+                buffer.len(), &mut actual)
+        };
+        into_result(status, || actual)
+    }
+
+    /// Write `data` to memory of the process at `vaddr`, returning the number of bytes written,
+    /// which may be fewer than requested if part of the range is not mapped. This is meant for
+    /// debuggers; the handle needs `Rights::READ` and `Rights::WRITE`.
+    ///
+    /// Wraps the
+    /// [zx_process_write_memory](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/process_write_memory.md)
+    /// syscall.
+    pub fn write_memory(&self, vaddr: usize, data: &[u8]) -> Result<usize, Status> {
+        let mut actual = 0;
+        let status = unsafe {
+            sys::zx_process_write_memory(self.raw_handle(), vaddr, data.as_ptr(), data.len(),
+                &mut actual)
+        };
+        into_result(status, || actual)
+    }
+
+    /// Get the koids of the threads in this process.
+    ///
+    /// Wraps the
+    /// [zx_object_get_info](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_get_info.md)
+    /// syscall with `ZX_INFO_PROCESS_THREADS`.
+    pub fn threads(&self) -> Result<Vec<Koid>, Status> {
+        self.get_info::<ProcessThreadsQuery>()
+    }
+
+    /// Get a handle to a thread of this process, given its koid.
+    ///
+    /// Wraps the
+    /// [zx_object_get_child](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_get_child.md)
+    /// syscall.
+    pub fn get_child(&self, koid: Koid, rights: Rights) -> Result<Handle, Status> {
+        object_get_child(self.as_handle_ref(), koid, rights)
+    }
+}
+
+/// The current process, as provided by the runtime.
+///
+/// The handle is borrowed from the runtime and is not closed when dropped.
+pub fn process_self() -> Unowned<'static, Process> {
+    unsafe { Unowned::from_raw_handle(sys::zx_process_self()) }
+}
+
+/// Options for creating a process.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ProcessOpts {
+    /// Default options.
+    Default = 0,
+}
+
+impl Default for ProcessOpts {
+    fn default() -> Self {
+        ProcessOpts::Default
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Event, EventOpts};
+
+    #[test]
+    fn create_without_job() {
+        let job = Job::from(Handle::invalid());
+        assert_eq!(Process::create(&job, "test", ProcessOpts::Default).unwrap_err(),
+            Status::ErrBadHandle);
+    }
+
+    #[test]
+    fn start_consumes_arg() {
+        let process = Process::from(Handle::invalid());
+        let thread = Thread::from(Handle::invalid());
+        let event = Event::create(EventOpts::Default).unwrap();
+        assert_eq!(process.start(&thread, 0, 0, event.into_handle(), 0),
+            Err(Status::ErrBadHandle));
+    }
+
+    #[test]
+    fn self_is_running() {
+        let process = process_self();
+        let info = process.info().unwrap();
+        assert!(info.started && !info.exited);
+        assert_eq!(process.exit_code(), Err(Status::ErrBadState));
+        assert_eq!(process.wait_for_exit(Deadline::from_nanos(0)), Err(Status::ErrTimedOut));
+    }
+
+    #[test]
+    fn exit_code_of_invalid() {
+        let process = Process::from(Handle::invalid());
+        assert_eq!(process.info(), Err(Status::ErrBadHandle));
+        assert_eq!(process.exit_code(), Err(Status::ErrBadHandle));
+        assert_eq!(process.wait_for_exit(Deadline::INFINITE), Err(Status::ErrBadHandle));
+    }
+
+    #[test]
+    fn self_task_stats() {
+        let stats = process_self().task_stats().unwrap();
+        assert!(stats.mem_mapped_bytes > 0);
+        assert!(stats.mem_private_bytes <= stats.mem_mapped_bytes);
+        assert!(stats.mem_scaled_shared_bytes <= stats.mem_shared_bytes);
+    }
+}
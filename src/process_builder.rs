@@ -0,0 +1,396 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Spawning processes from executables, with the processargs bootstrap protocol.
+
+use {Channel, ChannelOpts, Handle, HandleBased, HandleType, Job, Process, ProcessOpts, Rights};
+use {Status, Thread, ThreadOpts, Vmar, VmarFlags, Vmo, VmoCloneOpts, VmoOpts};
+use {initial_stack_pointer, page_size, round_down_to_page, round_up_to_page, sys};
+use std::mem;
+
+/// The size of the stack of the initial thread, unless set with `ProcessBuilder::stack_size`.
+const DEFAULT_STACK_SIZE: usize = 256 * 1024;
+
+/// A builder for spawning a process from an executable, in the way launchpad does.
+///
+/// `spawn` loads the executable into a new process, gives its initial thread a stack, and sends
+/// it a bootstrap message with its arguments, environment and handles using the
+/// [processargs](https://fuchsia.googlesource.com/zircon/+/master/system/public/zircon/processargs.h)
+/// protocol, which the C runtime reads at startup.
+///
+/// Only statically linked executables can be loaded; those which need a dynamic linker fail
+/// with `ErrNotSupported`. A process can only make syscalls through the vDSO, which should be
+/// given with `vdso`.
+#[derive(Debug)]
+pub struct ProcessBuilder<'a> {
+    job: &'a Job,
+    name: String,
+    executable: Vmo,
+    vdso: Option<Vmo>,
+    args: Vec<String>,
+    env: Vec<String>,
+    handles: Vec<(u32, Handle)>,
+    stack_size: usize,
+}
+
+impl<'a> ProcessBuilder<'a> {
+    /// Start building a process named `name` in `job`, which runs the ELF executable in
+    /// `executable`. The handle needs `Rights::READ`, `Rights::EXECUTE` and `Rights::MAP`.
+    pub fn new(job: &'a Job, name: &str, executable: Vmo) -> Self {
+        ProcessBuilder {
+            job,
+            name: name.to_owned(),
+            executable,
+            vdso: None,
+            args: Vec::new(),
+            env: Vec::new(),
+            handles: Vec::new(),
+            stack_size: DEFAULT_STACK_SIZE,
+        }
+    }
+
+    /// Add an argument. By convention the first argument is the name of the program.
+    pub fn arg(mut self, arg: &str) -> Self {
+        self.args.push(arg.to_owned());
+        self
+    }
+
+    /// Add several arguments.
+    pub fn args<I, S>(mut self, args: I) -> Self where I: IntoIterator<Item = S>, S: AsRef<str> {
+        self.args.extend(args.into_iter().map(|arg| arg.as_ref().to_owned()));
+        self
+    }
+
+    /// Add an environment variable, given as `NAME=value`.
+    pub fn env(mut self, var: &str) -> Self {
+        self.env.push(var.to_owned());
+        self
+    }
+
+    /// Pass a handle to the process, tagged with its type and an argument which distinguishes
+    /// handles of the same type, for the process to take with `take_startup_handle`.
+    pub fn add_handle(mut self, handle_type: HandleType, arg: u16, handle: Handle) -> Self {
+        self.handles.push((handle_type.info(arg), handle));
+        self
+    }
+
+    /// Map the vDSO, given as the virtual memory object of its image, into the process, and
+    /// pass it the object too so it can start processes of its own.
+    pub fn vdso(mut self, vdso: Vmo) -> Self {
+        self.vdso = Some(vdso);
+        self
+    }
+
+    /// Set the size of the stack of the initial thread, which is rounded up to whole pages.
+    pub fn stack_size(mut self, size: usize) -> Self {
+        self.stack_size = size;
+        self
+    }
+
+    /// Create the process and start it running.
+    ///
+    /// Fails with `ErrInvalidArgs` if the executable or vDSO is not a valid ELF image, or an
+    /// argument or environment variable contains a NUL byte, and with `ErrNotSupported` if the
+    /// executable is for another architecture or needs a dynamic linker.
+    pub fn spawn(self) -> Result<Process, Status> {
+        let mut infos = vec![HandleType::ProcessSelf.info(0), HandleType::ThreadSelf.info(0),
+            HandleType::RootVmar.info(0)];
+        if self.vdso.is_some() {
+            infos.push(HandleType::VdsoVmo.info(0));
+        }
+        infos.extend(self.handles.iter().map(|&(info, _)| info));
+        let message = encode_message(&self.args, &self.env, &infos)?;
+
+        let (process, root_vmar) = Process::create(self.job, &self.name, ProcessOpts::Default)?;
+        let entry = load_elf(&root_vmar, &self.executable)?.entry;
+        let vdso_base = match self.vdso {
+            Some(ref vdso) => load_elf(&root_vmar, vdso)?.base,
+            None => 0,
+        };
+        let thread = Thread::create(&process, &self.name, ThreadOpts::Default)?;
+        let stack_size = round_up_to_page(self.stack_size).ok_or(Status::ErrOutOfRange)?;
+        let stack = Vmo::create(stack_size as u64, VmoOpts::Default)?;
+        let stack_base = root_vmar.map(0, &stack, 0, stack_size,
+            VmarFlags::PERM_READ | VmarFlags::PERM_WRITE)?;
+
+        // The handles, in the same order as their infos.
+        let mut handles = vec![process.duplicate_handle(Rights::SAME_RIGHTS)?.into_handle(),
+            thread.duplicate_handle(Rights::SAME_RIGHTS)?.into_handle(), root_vmar.into_handle()];
+        handles.extend(self.vdso.map(Vmo::into_handle));
+        handles.extend(self.handles.into_iter().map(|(_, handle)| handle));
+        let (bootstrap, child_bootstrap) = Channel::create(ChannelOpts::Normal)?;
+        bootstrap.write(&message, &mut handles, 0)?;
+
+        process.start(&thread, entry, initial_stack_pointer(stack_base + stack_size),
+            child_bootstrap.into_handle(), vdso_base)?;
+        Ok(process)
+    }
+}
+
+// Encode the bootstrap message: the header, then the handle table, then the arguments and
+// environment as NUL-terminated strings.
+fn encode_message(args: &[String], env: &[String], infos: &[u32]) -> Result<Vec<u8>, Status> {
+    let header_len = mem::size_of::<sys::zx_proc_args_t>();
+    let args_off = header_len + infos.len() * 4;
+    let environ_off = args_off + args.iter().map(|arg| arg.len() + 1).sum::<usize>();
+    let header = [sys::ZX_PROCARGS_PROTOCOL, sys::ZX_PROCARGS_VERSION, header_len as u32,
+        args_off as u32, args.len() as u32, environ_off as u32, env.len() as u32];
+
+    let mut message = Vec::new();
+    for word in header.iter().chain(infos) {
+        message.extend_from_slice(&word.to_le_bytes());
+    }
+    for string in args.iter().chain(env) {
+        if string.as_bytes().contains(&0) {
+            return Err(Status::ErrInvalidArgs);
+        }
+        message.extend_from_slice(string.as_bytes());
+        message.push(0);
+    }
+    Ok(message)
+}
+
+// Where an ELF image was loaded.
+struct Loaded {
+    // The address the lowest segment was loaded at.
+    base: usize,
+    // The address of the entry point.
+    entry: usize,
+}
+
+// ELF constants, from the System V ABI.
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const ET_EXEC: u16 = 2;
+const ET_DYN: u16 = 3;
+#[cfg(target_arch = "x86_64")]
+const EM_NATIVE: u16 = 62;
+#[cfg(target_arch = "aarch64")]
+const EM_NATIVE: u16 = 183;
+const PT_LOAD: u32 = 1;
+const PT_INTERP: u32 = 3;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+const EHDR_SIZE: usize = 64;
+const PHDR_SIZE: usize = 56;
+
+// A loadable segment of an ELF image.
+struct Segment {
+    offset: usize,
+    vaddr: usize,
+    filesz: usize,
+    memsz: usize,
+    flags: u32,
+}
+
+// Load the segments of an ELF image into a region, returning where it was loaded.
+fn load_elf(vmar: &Vmar, image: &Vmo) -> Result<Loaded, Status> {
+    let mut ehdr = [0; EHDR_SIZE];
+    image.read_exact(&mut ehdr, 0).map_err(|_| Status::ErrInvalidArgs)?;
+    if &ehdr[..4] != ELF_MAGIC || ehdr[4] != ELFCLASS64 || ehdr[5] != ELFDATA2LSB {
+        return Err(Status::ErrInvalidArgs);
+    }
+    let elf_type = u16_at(&ehdr, 0x10);
+    if elf_type != ET_EXEC && elf_type != ET_DYN {
+        return Err(Status::ErrInvalidArgs);
+    }
+    if u16_at(&ehdr, 0x12) != EM_NATIVE {
+        return Err(Status::ErrNotSupported);
+    }
+    let entry = u64_at(&ehdr, 0x18) as usize;
+    let phoff = u64_at(&ehdr, 0x20);
+    let phentsize = u16_at(&ehdr, 0x36) as usize;
+    let phnum = u16_at(&ehdr, 0x38) as usize;
+    if phentsize < PHDR_SIZE {
+        return Err(Status::ErrInvalidArgs);
+    }
+    let phdrs = image.read_to_vec(phoff, phentsize * phnum).map_err(|_| Status::ErrInvalidArgs)?;
+
+    let mut segments = Vec::new();
+    for phdr in phdrs.chunks(phentsize) {
+        match u32_at(phdr, 0) {
+            PT_LOAD => segments.push(Segment {
+                flags: u32_at(phdr, 4),
+                offset: u64_at(phdr, 8) as usize,
+                vaddr: u64_at(phdr, 16) as usize,
+                filesz: u64_at(phdr, 32) as usize,
+                memsz: u64_at(phdr, 40) as usize,
+            }),
+            PT_INTERP => return Err(Status::ErrNotSupported),
+            _ => {}
+        }
+    }
+    let misaligned = |s: &Segment| s.offset % page_size() != s.vaddr % page_size();
+    if segments.iter().any(|s| s.filesz > s.memsz || misaligned(s)) {
+        return Err(Status::ErrInvalidArgs);
+    }
+    let low = round_down_to_page(segments.iter().map(|s| s.vaddr).min()
+        .ok_or(Status::ErrInvalidArgs)?);
+    let high = segments.iter().map(|s| s.vaddr.checked_add(s.memsz)).max()
+        .and_then(|high| high).and_then(round_up_to_page).ok_or(Status::ErrInvalidArgs)?;
+
+    // Reserve the whole range of the image, where its segments are then mapped. Executables
+    // are loaded at the addresses they were linked at; position-independent images anywhere.
+    let can_map = VmarFlags::CAN_MAP_READ | VmarFlags::CAN_MAP_WRITE
+        | VmarFlags::CAN_MAP_EXECUTE | VmarFlags::CAN_MAP_SPECIFIC;
+    let (region, base) = if elf_type == ET_EXEC {
+        let offset = low.checked_sub(vmar.info()?.base).ok_or(Status::ErrInvalidArgs)?;
+        vmar.allocate(offset, high - low, can_map | VmarFlags::SPECIFIC)?
+    } else {
+        vmar.allocate(0, high - low, can_map)?
+    };
+    for segment in &segments {
+        map_segment(&region, low, image, segment)?;
+    }
+    let entry = entry.checked_sub(low).and_then(|offset| offset.checked_add(base))
+        .ok_or(Status::ErrInvalidArgs)?;
+    Ok(Loaded { base, entry })
+}
+
+// Map a segment into the region reserved for an image whose lowest page is at `low`.
+fn map_segment(region: &Vmar, low: usize, image: &Vmo, segment: &Segment)
+    -> Result<(), Status>
+{
+    let mut perms = VmarFlags::SPECIFIC;
+    if segment.flags & PF_R != 0 {
+        perms |= VmarFlags::PERM_READ;
+    }
+    if segment.flags & PF_W != 0 {
+        perms |= VmarFlags::PERM_WRITE;
+    }
+    if segment.flags & PF_X != 0 {
+        perms |= VmarFlags::PERM_EXECUTE;
+    }
+    let start = round_down_to_page(segment.vaddr);
+    let file_end = segment.vaddr.checked_add(segment.filesz).ok_or(Status::ErrInvalidArgs)?;
+    let mut zero_start = start;
+    if segment.filesz > 0 {
+        let offset = round_down_to_page(segment.offset) as u64;
+        let len = round_up_to_page(file_end).ok_or(Status::ErrInvalidArgs)? - start;
+        if segment.flags & PF_W != 0 {
+            // Writable data is mapped from a copy-on-write clone, so the image is not changed,
+            // with the rest of its last page cleared for the start of the zeroed data.
+            let data = image.clone(VmoCloneOpts::CopyOnWrite, offset, len as u64)?;
+            let tail = file_end - start;
+            data.zero_range(tail as u64, (len - tail) as u64)?;
+            region.map(start - low, &data, 0, len, perms)?;
+        } else {
+            region.map(start - low, image, offset, len, perms)?;
+        }
+        zero_start = start + len;
+    }
+    let zero_end = segment.vaddr.checked_add(segment.memsz).and_then(round_up_to_page)
+        .ok_or(Status::ErrInvalidArgs)?;
+    if zero_end > zero_start {
+        let zeros = Vmo::create((zero_end - zero_start) as u64, VmoOpts::Default)?;
+        region.map(zero_start - low, &zeros, 0, zero_end - zero_start, perms)?;
+    }
+    Ok(())
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    let mut word = [0; 4];
+    word.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(word)
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    let mut word = [0; 8];
+    word.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {AsHandleRef, Deadline, Duration, JobOpts, Signals, Task, job_default};
+
+    // An instruction which jumps to itself.
+    #[cfg(target_arch = "x86_64")]
+    const SPIN: &[u8] = &[0xeb, 0xfe];
+    #[cfg(target_arch = "aarch64")]
+    const SPIN: &[u8] = &[0x00, 0x00, 0x00, 0x14];
+
+    // A position-independent ELF image with a single segment, loaded at `vaddr`, which holds
+    // the headers followed by `SPIN`.
+    fn spin_image(vaddr: u64, entry: u64) -> Vmo {
+        let mut image = vec![0; EHDR_SIZE + PHDR_SIZE];
+        image[..4].copy_from_slice(ELF_MAGIC);
+        image[4] = ELFCLASS64;
+        image[5] = ELFDATA2LSB;
+        image[6] = 1;
+        image[0x10..0x12].copy_from_slice(&ET_DYN.to_le_bytes());
+        image[0x12..0x14].copy_from_slice(&EM_NATIVE.to_le_bytes());
+        image[0x14..0x18].copy_from_slice(&1u32.to_le_bytes());
+        image[0x18..0x20].copy_from_slice(&entry.to_le_bytes());
+        image[0x20..0x28].copy_from_slice(&(EHDR_SIZE as u64).to_le_bytes());
+        image[0x34..0x36].copy_from_slice(&(EHDR_SIZE as u16).to_le_bytes());
+        image[0x36..0x38].copy_from_slice(&(PHDR_SIZE as u16).to_le_bytes());
+        image[0x38..0x3a].copy_from_slice(&1u16.to_le_bytes());
+        image.extend_from_slice(SPIN);
+
+        let size = image.len() as u64;
+        let phdr = &mut image[EHDR_SIZE..];
+        phdr[0..4].copy_from_slice(&PT_LOAD.to_le_bytes());
+        phdr[4..8].copy_from_slice(&(PF_R | PF_X).to_le_bytes());
+        phdr[16..24].copy_from_slice(&vaddr.to_le_bytes());
+        phdr[32..40].copy_from_slice(&size.to_le_bytes());
+        phdr[40..48].copy_from_slice(&size.to_le_bytes());
+        phdr[48..56].copy_from_slice(&(page_size() as u64).to_le_bytes());
+        Vmo::create_from(&image).unwrap().0
+    }
+
+    #[test]
+    fn bootstrap_message() {
+        let args = ["prog".to_owned(), "-v".to_owned()];
+        let infos = [HandleType::ProcessSelf.info(0), HandleType::User0.info(3)];
+        let message = encode_message(&args, &["HOME=/".to_owned()], &infos).unwrap();
+        let header: Vec<u32> = (0..7).map(|i| u32_at(&message, i * 4)).collect();
+        assert_eq!(header, [sys::ZX_PROCARGS_PROTOCOL, sys::ZX_PROCARGS_VERSION, 28, 36, 2, 44, 1]);
+        assert_eq!((u32_at(&message, 28), u32_at(&message, 32)), (0x01, 0x0003_00f0));
+        assert_eq!(&message[36..44], b"prog\0-v\0");
+        assert_eq!(&message[44..], b"HOME=/\0");
+
+        assert_eq!(encode_message(&["a\0b".to_owned()], &[], &[]).unwrap_err(),
+            Status::ErrInvalidArgs);
+    }
+
+    #[test]
+    fn spawn_not_elf() {
+        let (vmo, _) = Vmo::create_from(b"#!/bin/sh\n").unwrap();
+        let job = job_default();
+        assert_eq!(ProcessBuilder::new(&job, "script", vmo).arg("script").spawn().unwrap_err(),
+            Status::ErrInvalidArgs);
+    }
+
+    #[test]
+    fn spawn_entry_below_segments() {
+        let image = spin_image(0x1000, 0);
+        let job = job_default();
+        assert_eq!(ProcessBuilder::new(&job, "bad-entry", image).spawn().unwrap_err(),
+            Status::ErrInvalidArgs);
+    }
+
+    #[test]
+    fn spawn_spinning() {
+        let image = spin_image(0x1000, 0x1000 + (EHDR_SIZE + PHDR_SIZE) as u64);
+        let job = Job::create(&job_default(), JobOpts::Default).unwrap();
+        let process = ProcessBuilder::new(&job, "spinner", image).arg("spinner").env("A=b")
+            .spawn().unwrap();
+        assert!(process.info().unwrap().started);
+
+        // The process keeps running until it is killed.
+        let ten_ms = Deadline::after(Duration::from_millis(10));
+        assert_eq!(process.wait_handle(Signals::TASK_TERMINATED, ten_ms),
+            Err(Status::ErrTimedOut));
+        assert_eq!(process.kill(), Ok(()));
+        assert!(process.wait_handle(Signals::TASK_TERMINATED, Deadline::INFINITE).is_ok());
+    }
+}
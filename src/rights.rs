@@ -0,0 +1,122 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Type-safe handle rights.
+
+use sys;
+use std::fmt;
+
+/// Rights associated with a handle.
+///
+/// Rights can be combined with the `|` operator, for example `Rights::READ | Rights::WRITE`.
+///
+/// See [rights.md](https://fuchsia.googlesource.com/zircon/+/master/docs/rights.md)
+/// for more information.
+#[repr(C)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Rights(sys::zx_rights_t);
+
+const NAMES: &[(Rights, &str)] = &[
+    (Rights::DUPLICATE, "DUPLICATE"),
+    (Rights::TRANSFER, "TRANSFER"),
+    (Rights::READ, "READ"),
+    (Rights::WRITE, "WRITE"),
+    (Rights::EXECUTE, "EXECUTE"),
+    (Rights::MAP, "MAP"),
+    (Rights::GET_PROPERTY, "GET_PROPERTY"),
+    (Rights::SET_PROPERTY, "SET_PROPERTY"),
+    (Rights::DEBUG, "DEBUG"),
+    (Rights::SAME_RIGHTS, "SAME_RIGHTS"),
+];
+
+impl Rights {
+    /// No rights.
+    pub const NONE: Rights = Rights(sys::ZX_RIGHT_NONE);
+    /// The handle may be duplicated.
+    pub const DUPLICATE: Rights = Rights(sys::ZX_RIGHT_DUPLICATE);
+    /// The handle may be transferred to another process, for example over a channel.
+    pub const TRANSFER: Rights = Rights(sys::ZX_RIGHT_TRANSFER);
+    /// The object may be read from.
+    pub const READ: Rights = Rights(sys::ZX_RIGHT_READ);
+    /// The object may be written to.
+    pub const WRITE: Rights = Rights(sys::ZX_RIGHT_WRITE);
+    /// The object may be mapped as executable.
+    pub const EXECUTE: Rights = Rights(sys::ZX_RIGHT_EXECUTE);
+    /// The object may be mapped.
+    pub const MAP: Rights = Rights(sys::ZX_RIGHT_MAP);
+    /// The object's properties may be read.
+    pub const GET_PROPERTY: Rights = Rights(sys::ZX_RIGHT_GET_PROPERTY);
+    /// The object's properties may be changed.
+    pub const SET_PROPERTY: Rights = Rights(sys::ZX_RIGHT_SET_PROPERTY);
+    /// The object may be debugged.
+    pub const DEBUG: Rights = Rights(sys::ZX_RIGHT_DEBUG);
+    /// When duplicating or replacing a handle, keep the same rights as the original.
+    pub const SAME_RIGHTS: Rights = Rights(sys::ZX_RIGHT_SAME_RIGHTS);
+}
+
+impl Default for Rights {
+    fn default() -> Self {
+        Rights::NONE
+    }
+}
+
+impl_flags!(Rights, sys::zx_rights_t);
+
+/// Lists the names of the rights, separated by `|`, for example `READ | WRITE`.
+impl fmt::Display for Rights {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("NONE");
+        }
+        let mut first = true;
+        for &(right, name) in NAMES {
+            if self.contains(right) {
+                if !first {
+                    f.write_str(" | ")?;
+                }
+                f.write_str(name)?;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Rights {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Rights({})", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rights_ops() {
+        let rw = Rights::READ | Rights::WRITE;
+        assert!(rw.contains(Rights::READ));
+        assert!(rw.contains(Rights::READ | Rights::WRITE));
+        assert!(!rw.contains(Rights::MAP));
+        assert_eq!(rw - Rights::WRITE, Rights::READ);
+        assert_eq!(rw & Rights::WRITE, Rights::WRITE);
+        assert!(Rights::NONE.is_empty());
+    }
+
+    #[test]
+    fn rights_raw() {
+        let rw = Rights::READ | Rights::WRITE;
+        assert_eq!(rw.bits(), (1 << 2) | (1 << 3));
+        assert_eq!(Rights::from_bits(rw.bits()), Some(rw));
+        assert_eq!(Rights::from_raw(rw.into_raw()), rw);
+        assert_eq!(Rights::from_bits(1 << 30), None);
+    }
+
+    #[test]
+    fn rights_display() {
+        assert_eq!(format!("{}", Rights::NONE), "NONE");
+        assert_eq!(format!("{}", Rights::READ | Rights::WRITE), "READ | WRITE");
+        assert_eq!(format!("{:?}", Rights::MAP), "Rights(MAP)");
+    }
+}
@@ -0,0 +1,85 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! A handle wrapper which can be cloned.
+
+use {AsHandleRef, HandleBased, HandleRef, Rights};
+use std::ops::Deref;
+
+/// A handle which implements `Clone` by duplicating the underlying handle with the same rights,
+/// so that it can be stored in data structures which require `Clone`.
+///
+/// Cloning panics if the handle cannot be duplicated, for example because it lacks
+/// `Rights::DUPLICATE`; use `HandleBased::duplicate_handle` directly to handle that case.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Shared<T: HandleBased>(T);
+
+impl<T: HandleBased> Shared<T> {
+    /// Wrap a handle.
+    pub fn new(handle: T) -> Shared<T> {
+        Shared(handle)
+    }
+
+    /// Unwrap the handle.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: HandleBased> Clone for Shared<T> {
+    /// Duplicate the handle. Wraps the
+    /// [zx_handle_duplicate](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/handle_duplicate.md)
+    /// syscall, panicking if it fails.
+    fn clone(&self) -> Self {
+        match self.0.duplicate_handle(Rights::SAME_RIGHTS) {
+            Ok(handle) => Shared(handle),
+            Err(status) => panic!("failed to duplicate shared handle: {:?}", status),
+        }
+    }
+}
+
+impl<T: HandleBased> Deref for Shared<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: HandleBased> AsHandleRef for Shared<T> {
+    fn as_handle_ref(&self) -> HandleRef {
+        self.0.as_handle_ref()
+    }
+}
+
+impl<T: HandleBased> From<T> for Shared<T> {
+    fn from(handle: T) -> Self {
+        Shared(handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Vmo, VmoOpts};
+
+    #[test]
+    fn clone_duplicates() {
+        let vmo = Shared::new(Vmo::create(5, VmoOpts::Default).unwrap());
+        let clone = vmo.clone();
+        assert!(clone.raw_handle() != vmo.raw_handle());
+        assert_eq!(clone.basic_info().unwrap().koid, vmo.basic_info().unwrap().koid);
+        assert_eq!(clone.write(b"hello", 0), Ok(5));
+        let mut buf = [0; 5];
+        assert_eq!(vmo.into_inner().read(&mut buf, 0), Ok(5));
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[test]
+    #[should_panic]
+    fn clone_without_duplicate_right() {
+        let vmo = Vmo::create(0, VmoOpts::Default).unwrap();
+        let vmo = vmo.duplicate_with().remove(Rights::DUPLICATE).build().unwrap();
+        let _ = Shared::new(vmo).clone();
+    }
+}
@@ -0,0 +1,213 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Type-safe object signals.
+
+use sys;
+use std::fmt;
+
+/// Signals that can be waited upon.
+///
+/// Signals can be combined with the `|` operator, for example
+/// `Signals::CHANNEL_READABLE | Signals::CHANNEL_PEER_CLOSED`. Many of the per-object
+/// constants share the same underlying bit; which meaning applies depends on the type of
+/// object being waited upon.
+///
+/// See
+/// [Objects and signals](https://fuchsia.googlesource.com/zircon/+/master/docs/concepts.md#Objects-and-Signals)
+/// in the Zircon kernel documentation. Note: the names of signals are still in flux.
+#[repr(C)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Signals(sys::zx_signals_t);
+
+impl Signals {
+    /// No signals.
+    pub const NONE: Signals = Signals(sys::ZX_SIGNAL_NONE);
+    /// All of the object-specific signals.
+    pub const OBJECT_ALL: Signals = Signals(sys::ZX_OBJECT_SIGNAL_ALL);
+    /// All of the user signals.
+    pub const USER_ALL: Signals = Signals(sys::ZX_USER_SIGNAL_ALL);
+
+    /// The handle being waited upon was closed during the wait.
+    pub const HANDLE_CLOSED: Signals = Signals(sys::ZX_SIGNAL_HANDLE_CLOSED);
+    /// Only one handle to the object exists.
+    pub const LAST_HANDLE: Signals = Signals(sys::ZX_SIGNAL_LAST_HANDLE);
+
+    pub const USER_0: Signals = Signals(sys::ZX_USER_SIGNAL_0);
+    pub const USER_1: Signals = Signals(sys::ZX_USER_SIGNAL_1);
+    pub const USER_2: Signals = Signals(sys::ZX_USER_SIGNAL_2);
+    pub const USER_3: Signals = Signals(sys::ZX_USER_SIGNAL_3);
+    pub const USER_4: Signals = Signals(sys::ZX_USER_SIGNAL_4);
+    pub const USER_5: Signals = Signals(sys::ZX_USER_SIGNAL_5);
+    pub const USER_6: Signals = Signals(sys::ZX_USER_SIGNAL_6);
+    pub const USER_7: Signals = Signals(sys::ZX_USER_SIGNAL_7);
+
+    // Event
+    pub const EVENT_SIGNALED: Signals = Signals(sys::ZX_EVENT_SIGNALED);
+
+    // EventPair
+    pub const EPAIR_SIGNALED: Signals = Signals(sys::ZX_EPAIR_SIGNALED);
+    pub const EPAIR_CLOSED: Signals = Signals(sys::ZX_EPAIR_CLOSED);
+
+    // Task signals (process, thread, job)
+    pub const TASK_TERMINATED: Signals = Signals(sys::ZX_TASK_TERMINATED);
+
+    // Channel
+    pub const CHANNEL_READABLE: Signals = Signals(sys::ZX_CHANNEL_READABLE);
+    pub const CHANNEL_WRITABLE: Signals = Signals(sys::ZX_CHANNEL_WRITABLE);
+    pub const CHANNEL_PEER_CLOSED: Signals = Signals(sys::ZX_CHANNEL_PEER_CLOSED);
+
+    // Socket
+    pub const SOCKET_READABLE: Signals = Signals(sys::ZX_SOCKET_READABLE);
+    pub const SOCKET_WRITABLE: Signals = Signals(sys::ZX_SOCKET_WRITABLE);
+    pub const SOCKET_PEER_CLOSED: Signals = Signals(sys::ZX_SOCKET_PEER_CLOSED);
+    /// The peer has closed its half of the socket with `Socket::half_close`, so no more data
+    /// will arrive once that already queued has been read.
+    pub const SOCKET_READ_DISABLED: Signals = Signals(sys::ZX_SOCKET_READ_DISABLED);
+    /// This end has closed its half of the socket with `Socket::half_close`, so it can no longer
+    /// be written to.
+    pub const SOCKET_WRITE_DISABLED: Signals = Signals(sys::ZX_SOCKET_WRITE_DISABLED);
+    /// A control message is waiting to be read with `Socket::read_control`.
+    pub const SOCKET_CONTROL_READABLE: Signals = Signals(sys::ZX_SOCKET_CONTROL_READABLE);
+    /// A control message can be written with `Socket::write_control`.
+    pub const SOCKET_CONTROL_WRITABLE: Signals = Signals(sys::ZX_SOCKET_CONTROL_WRITABLE);
+    /// A socket shared by the peer with `Socket::share` is waiting to be accepted.
+    pub const SOCKET_ACCEPT: Signals = Signals(sys::ZX_SOCKET_ACCEPT);
+    /// A socket can be shared with the peer with `Socket::share`.
+    pub const SOCKET_SHARE: Signals = Signals(sys::ZX_SOCKET_SHARE);
+
+    // Port
+    pub const PORT_READABLE: Signals = Signals(sys::ZX_PORT_READABLE);
+
+    // Fifo
+    pub const FIFO_READABLE: Signals = Signals(sys::ZX_FIFO_READABLE);
+    pub const FIFO_WRITABLE: Signals = Signals(sys::ZX_FIFO_WRITABLE);
+    pub const FIFO_PEER_CLOSED: Signals = Signals(sys::ZX_FIFO_PEER_CLOSED);
+
+    // Job
+    pub const JOB_NO_PROCESSES: Signals = Signals(sys::ZX_JOB_NO_PROCESSES);
+    pub const JOB_NO_JOBS: Signals = Signals(sys::ZX_JOB_NO_JOBS);
+
+    // Process
+    pub const PROCESS_TERMINATED: Signals = Signals(sys::ZX_PROCESS_TERMINATED);
+
+    // Thread
+    pub const THREAD_TERMINATED: Signals = Signals(sys::ZX_THREAD_TERMINATED);
+    pub const THREAD_RUNNING: Signals = Signals(sys::ZX_THREAD_RUNNING);
+    pub const THREAD_SUSPENDED: Signals = Signals(sys::ZX_THREAD_SUSPENDED);
+
+    // Timer
+    pub const TIMER_SIGNALED: Signals = Signals(sys::ZX_TIMER_SIGNALED);
+}
+
+impl Default for Signals {
+    fn default() -> Self {
+        Signals::NONE
+    }
+}
+
+impl_flags!(Signals, sys::zx_signals_t);
+
+/// One of the eight user signals, `Signals::USER_0` through `Signals::USER_7`, by number.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct UserSignal(u8);
+
+impl UserSignal {
+    /// The user signal numbered `n`, or `None` unless `n` is less than 8.
+    pub fn new(n: u8) -> Option<UserSignal> {
+        if n < 8 { Some(UserSignal(n)) } else { None }
+    }
+
+    /// The number of the signal.
+    pub fn number(&self) -> u8 {
+        self.0
+    }
+
+    /// The signal as a set of signals.
+    pub fn signals(&self) -> Signals {
+        const USER: [Signals; 8] = [Signals::USER_0, Signals::USER_1, Signals::USER_2,
+            Signals::USER_3, Signals::USER_4, Signals::USER_5, Signals::USER_6, Signals::USER_7];
+        USER[self.0 as usize]
+    }
+}
+
+impl From<UserSignal> for Signals {
+    fn from(signal: UserSignal) -> Self {
+        signal.signals()
+    }
+}
+
+// The name used for each bit when displaying a set of signals. As the meaning of the low bits
+// depends on the type of object, these use the most common interpretation.
+fn bit_name(bit: u32) -> String {
+    match bit {
+        0 => "READABLE".to_string(),
+        1 => "WRITABLE".to_string(),
+        2 => "PEER_CLOSED".to_string(),
+        3 => "SIGNALED".to_string(),
+        22 => "LAST_HANDLE".to_string(),
+        23 => "HANDLE_CLOSED".to_string(),
+        24..=31 => format!("USER_{}", bit - 24),
+        _ => format!("OBJECT_{}", bit),
+    }
+}
+
+/// Lists the names of the signals, separated by `|`, for example `READABLE | USER_0`.
+impl fmt::Display for Signals {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("NONE");
+        }
+        let mut first = true;
+        for bit in 0..32 {
+            if self.bits() & (1 << bit) != 0 {
+                if !first {
+                    f.write_str(" | ")?;
+                }
+                f.write_str(&bit_name(bit))?;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Signals {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Signals({})", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signals_ops() {
+        let signals = Signals::CHANNEL_READABLE | Signals::USER_0;
+        assert!(signals.contains(Signals::USER_0));
+        assert!(signals.intersects(Signals::USER_0 | Signals::USER_1));
+        assert!(!signals.contains(Signals::USER_0 | Signals::USER_1));
+        assert!(Signals::USER_ALL.contains(Signals::USER_7));
+        assert_eq!(signals - Signals::USER_0, Signals::CHANNEL_READABLE);
+        assert_eq!(Signals::SOCKET_READABLE, Signals::CHANNEL_READABLE);
+    }
+
+    #[test]
+    fn signals_display() {
+        assert_eq!(format!("{}", Signals::NONE), "NONE");
+        assert_eq!(format!("{}", Signals::CHANNEL_READABLE | Signals::CHANNEL_PEER_CLOSED),
+            "READABLE | PEER_CLOSED");
+        assert_eq!(format!("{}", Signals::USER_3 | Signals::LAST_HANDLE), "LAST_HANDLE | USER_3");
+        assert_eq!(format!("{:?}", Signals::JOB_NO_JOBS), "Signals(OBJECT_4)");
+    }
+
+    #[test]
+    fn user_signal() {
+        assert_eq!(UserSignal::new(0).unwrap().signals(), Signals::USER_0);
+        assert_eq!(Signals::from(UserSignal::new(7).unwrap()), Signals::USER_7);
+        assert_eq!(UserSignal::new(5).unwrap().number(), 5);
+        assert_eq!(UserSignal::new(8), None);
+    }
+}
@@ -4,26 +4,112 @@
 
 //! Type-safe bindings for Zircon sockets.
 
-use {AsHandleRef, HandleBased, Handle, HandleRef, Peered};
-use {sys, Status, into_result};
+use {AsHandleRef, Deadline, HandleBased, Handle, HandleRef, ObjectType, Peered, Signals};
+use {SocketInfo, SocketQuery, object_get_info_single};
+use {sys, Status, into_result, is_asserted, retry_when_ready};
 
-use std::ptr;
+use std::{io, ptr};
 
 /// An object representing a Zircon
 /// [socket](https://fuchsia.googlesource.com/zircon/+/master/docs/concepts.md#Message-Passing_Sockets-and-Channels).
 ///
+/// Sockets carry a stream of bytes, with no message boundaries. Reads and writes may be
+/// short: `read` returns whatever is available up to the size of the buffer, and `write` as
+/// much as fits in the socket's buffer, so callers must check the returned count. When no data
+/// is available, or there is no room to write, they fail with `ErrShouldWait`.
+///
 /// As essentially a subtype of `Handle`, it can be freely interconverted.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Eq, PartialEq)]
 pub struct Socket(Handle);
-impl_handle_based!(Socket);
+impl_handle_based!(Socket, ObjectType::Socket);
 impl Peered for Socket {}
 
+/// Reads block until data is available. Once the peer has been closed, or has closed its
+/// half of the socket with `half_close`, and all data has been read, reads return 0 bytes.
+impl io::Read for &Socket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let result = retry_when_ready(self.as_handle_ref(), Signals::SOCKET_READABLE,
+            Signals::SOCKET_PEER_CLOSED | Signals::SOCKET_READ_DISABLED, Deadline::INFINITE,
+            || Socket::read(self, SocketReadOpts::Default, buf));
+        match result {
+            Err(Status::ErrPeerClosed) => Ok(0),
+            // Some kernels fail reads with `ErrBadState`, rather than `ErrPeerClosed`, once the
+            // peer has closed its half of the socket.
+            Err(Status::ErrBadState) if is_read_disabled(self) => Ok(0),
+            result => result.map_err(Status::into_io_error),
+        }
+    }
+}
+
+// Whether the peer has closed its half of the socket with `half_close`.
+fn is_read_disabled(socket: &Socket) -> bool {
+    is_asserted(socket.as_handle_ref(), Signals::SOCKET_READ_DISABLED).unwrap_or(false)
+}
+
+impl io::Read for Socket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        io::Read::read(&mut &*self, buf)
+    }
+}
+
+/// Writes block until there is room in the socket. Writing once the peer has been closed fails
+/// with `BrokenPipe`.
+impl io::Write for &Socket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.write_deadline(SocketWriteOpts::Default, buf, Deadline::INFINITE)
+            .map_err(Status::into_io_error)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl io::Write for Socket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::Write::write(&mut &*self, buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Create a socket pair for plumbing the standard input, output or error of a spawned process,
+/// like a pipe in `std::process`.
+///
+/// The returned handle is the end to give to the new process, for example as a file descriptor
+/// handle when launching it. The returned socket is the end kept by the caller, which
+/// implements `std::io::Read` and `std::io::Write`, so it can be written to the process's input
+/// or read from its output with the standard library's I/O helpers. Reads from it return 0
+/// bytes once the process has exited (or otherwise closed its end), and all output has been
+/// read.
+pub fn stdio_pipe() -> Result<(Socket, Handle), Status> {
+    let (local, remote) = Socket::create(SocketOpts::Default)?;
+    Ok((local, remote.into_handle()))
+}
+
 /// Options for creating a socket pair.
 #[repr(u32)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum SocketOpts {
-    /// Default options.
-    Default = 0,
+    /// Default options, creating a stream socket.
+    Default = sys::ZX_SOCKET_STREAM,
+    /// Create a datagram socket, which preserves the boundaries between writes. Use
+    /// `read_datagram` and `write_datagram` to access it.
+    Datagram = sys::ZX_SOCKET_DATAGRAM,
+    /// Create a stream socket through which other sockets can be passed, with `share` and
+    /// `accept`.
+    HasAccept = sys::ZX_SOCKET_HAS_ACCEPT,
+    /// Create a stream socket with a control plane, which carries small messages separately
+    /// from the data, with `write_control` and `read_control`.
+    HasControl = sys::ZX_SOCKET_HAS_CONTROL,
 }
 
 impl Default for SocketOpts {
@@ -38,6 +124,8 @@ impl Default for SocketOpts {
 pub enum SocketWriteOpts {
     /// Default options.
     Default = 0,
+    /// Write a message to the control plane, rather than the data stream.
+    Control = sys::ZX_SOCKET_CONTROL,
 }
 
 impl Default for SocketWriteOpts {
@@ -52,6 +140,8 @@ impl Default for SocketWriteOpts {
 pub enum SocketReadOpts {
     /// Default options.
     Default = 0,
+    /// Read a message from the control plane, rather than the data stream.
+    Control = sys::ZX_SOCKET_CONTROL,
 }
 
 impl Default for SocketReadOpts {
@@ -73,8 +163,8 @@ impl Socket {
             let mut out1 = 0;
             let status = sys::zx_socket_create(opts as u32, &mut out0, &mut out1);
             into_result(status, ||
-                (Self::from(Handle(out0)),
-                    Self::from(Handle(out1))))
+                (Self::from(Handle::owned(out0)),
+                    Self::from(Handle::owned(out1))))
         }
     }
 
@@ -92,6 +182,17 @@ impl Socket {
         into_result(status, || actual)
     }
 
+    /// Write the given bytes into the socket, waiting for it to become writable if necessary.
+    /// Return value (on success) is number of bytes actually written. Fails with `ErrTimedOut`
+    /// if it does not become writable before the deadline, or `ErrPeerClosed` if the peer is
+    /// closed.
+    pub fn write_deadline(&self, opts: SocketWriteOpts, bytes: &[u8], deadline: Deadline)
+        -> Result<usize, Status>
+    {
+        retry_when_ready(self.as_handle_ref(), Signals::SOCKET_WRITABLE,
+            Signals::SOCKET_PEER_CLOSED, deadline, || self.write(opts, bytes))
+    }
+
     /// Read the given bytes from the socket.
     /// Return value (on success) is number of bytes actually read.
     ///
@@ -111,7 +212,76 @@ impl Socket {
         into_result(status, || actual)
     }
 
-    /// Close half of the socket, so attempts by the other side to write will fail.
+    /// Write all of the given bytes into the socket, waiting for room as necessary. Fails with
+    /// `ErrTimedOut` if they have not all been written before the deadline, or `ErrPeerClosed`
+    /// if the peer is closed; some of the bytes may have been written in either case.
+    pub fn write_all_deadline(&self, opts: SocketWriteOpts, mut bytes: &[u8], deadline: Deadline)
+        -> Result<(), Status>
+    {
+        while !bytes.is_empty() {
+            let actual = self.write_deadline(opts, bytes, deadline)?;
+            bytes = &bytes[actual..];
+        }
+        Ok(())
+    }
+
+    /// Read exactly enough bytes to fill `bytes`, waiting for data as necessary. Fails with
+    /// `ErrTimedOut` if it has not been filled before the deadline, or `ErrPeerClosed` if the
+    /// peer is closed first; in either case the bytes which were read are lost.
+    pub fn read_exact_deadline(&self, opts: SocketReadOpts, mut bytes: &mut [u8],
+        deadline: Deadline) -> Result<(), Status>
+    {
+        while !bytes.is_empty() {
+            let actual = retry_when_ready(self.as_handle_ref(), Signals::SOCKET_READABLE,
+                Signals::SOCKET_PEER_CLOSED, deadline, || self.read(opts, bytes))?;
+            bytes = &mut bytes[actual..];
+        }
+        Ok(())
+    }
+
+    /// Write the given bytes into a datagram socket as a single datagram. Unlike `write`, the
+    /// datagram is either written in full or not at all; fails with `ErrShouldWait` if there is
+    /// not room for all of it.
+    ///
+    /// Wraps
+    /// [zx_socket_write](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/socket_write.md).
+    pub fn write_datagram(&self, bytes: &[u8]) -> Result<(), Status> {
+        let actual = self.write(SocketWriteOpts::Default, bytes)?;
+        debug_assert_eq!(actual, bytes.len());
+        Ok(())
+    }
+
+    /// The size of the next datagram queued on a datagram socket, or 0 if there is none.
+    pub fn next_datagram_size(&self) -> Result<usize, Status> {
+        let mut size = 0;
+        let status = unsafe {
+            sys::zx_socket_read(self.raw_handle(), 0, ptr::null_mut(), 0, &mut size)
+        };
+        into_result(status, || size)
+    }
+
+    /// Read the next datagram from a datagram socket into `bytes`, returning its size.
+    ///
+    /// Rather than silently truncating a datagram which does not fit, fails with
+    /// `ErrBufferTooSmall` and leaves it queued on the socket; `next_datagram_size` gives the
+    /// size of buffer needed.
+    ///
+    /// Wraps
+    /// [zx_socket_read](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/socket_read.md).
+    pub fn read_datagram(&self, bytes: &mut [u8]) -> Result<usize, Status> {
+        if self.next_datagram_size()? > bytes.len() {
+            return Err(Status::ErrBufferTooSmall);
+        }
+        self.read(SocketReadOpts::Default, bytes)
+    }
+
+    /// Close half of the socket, so attempts by this side to write will fail with `ErrBadState`,
+    /// like `shutdown` for writing on a TCP socket. The other side can still write, and can
+    /// still read any data already written; after that, its reads with `read` fail with
+    /// `ErrPeerClosed`, and reads through `std::io::Read` return 0 bytes.
+    ///
+    /// Afterwards `Signals::SOCKET_WRITE_DISABLED` is asserted on this end of the socket, and
+    /// `Signals::SOCKET_READ_DISABLED` on the other.
     ///
     /// Implements the `ZX_SOCKET_HALF_CLOSE` option of
     /// [zx_socket_write](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/socket_write.md).
@@ -121,18 +291,79 @@ impl Socket {
         into_result(status, || ())
     }
 
+    /// Write a message to the control plane of the socket, which must have been created with
+    /// `SocketOpts::HasControl`. Control messages are kept separate from the data stream, and
+    /// are written in full or not at all; only one can be queued at a time, and
+    /// `Signals::SOCKET_CONTROL_WRITABLE` is asserted when another can be written.
+    ///
+    /// Implements the `ZX_SOCKET_CONTROL` option of
+    /// [zx_socket_write](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/socket_write.md).
+    pub fn write_control(&self, bytes: &[u8]) -> Result<(), Status> {
+        self.write(SocketWriteOpts::Control, bytes).map(|_| ())
+    }
+
+    /// Read a message from the control plane of the socket, returning its size. Fails with
+    /// `ErrShouldWait` if there is none; `Signals::SOCKET_CONTROL_READABLE` is asserted when
+    /// there is one.
+    ///
+    /// Implements the `ZX_SOCKET_CONTROL` option of
+    /// [zx_socket_read](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/socket_read.md).
+    pub fn read_control(&self, bytes: &mut [u8]) -> Result<usize, Status> {
+        self.read(SocketReadOpts::Control, bytes)
+    }
+
+    /// Pass a socket to the peer, which can take it with `accept`. The socket must have been
+    /// created with `SocketOpts::HasAccept`, and only one shared socket can be waiting to be
+    /// accepted at a time: `Signals::SOCKET_SHARE` is asserted when another can be shared.
+    ///
+    /// The shared socket is consumed even if this fails.
+    ///
+    /// Wraps the
+    /// [zx_socket_share](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/socket_share.md)
+    /// syscall.
+    pub fn share(&self, socket: Socket) -> Result<(), Status> {
+        let raw = socket.into_handle().into_raw_untracked();
+        let status = unsafe { sys::zx_socket_share(self.raw_handle(), raw) };
+        into_result(status, || ())
+    }
+
+    /// Take a socket passed by the peer with `share`. Fails with `ErrShouldWait` if there is
+    /// none; `Signals::SOCKET_ACCEPT` is asserted when there is one waiting.
+    ///
+    /// Wraps the
+    /// [zx_socket_accept](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/socket_accept.md)
+    /// syscall.
+    pub fn accept(&self) -> Result<Socket, Status> {
+        let mut out = 0;
+        let status = unsafe { sys::zx_socket_accept(self.raw_handle(), &mut out) };
+        into_result(status, || Socket::from(Handle::owned(out)))
+    }
+
+    /// Information about the socket's buffers, such as how much data is queued in each
+    /// direction.
+    ///
+    /// Wraps the `ZX_INFO_SOCKET` topic of
+    /// [zx_object_get_info](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_get_info.md).
+    pub fn info(&self) -> Result<SocketInfo, Status> {
+        object_get_info_single::<SocketQuery>(self.as_handle_ref()).map(SocketInfo::from)
+    }
+
+    /// The number of bytes queued for this end to read, without consuming them.
     pub fn outstanding_read_bytes(&self) -> Result<usize, Status> {
-        let mut outstanding = 0;
-        let status = unsafe {
-            sys::zx_socket_read(self.raw_handle(), 0, ptr::null_mut(), 0, &mut outstanding)
-        };
-        into_result(status, || outstanding)
+        self.info().map(|info| info.rx_buf_size)
+    }
+
+    /// The number of bytes written by this end which the peer has not yet read.
+    pub fn outstanding_write_bytes(&self) -> Result<usize, Status> {
+        self.info().map(|info| info.tx_buf_size)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use {Duration, deadline_after};
+    use std::thread;
 
     #[test]
     fn socket_basic() {
@@ -159,4 +390,197 @@ mod tests {
         assert_eq!(s1.read(SocketReadOpts::Default, &mut read_vec).unwrap(), 4);
         assert_eq!(&read_vec[0..4], b"back");
     }
+
+    #[test]
+    fn socket_short_read() {
+        let (s1, s2) = Socket::create(SocketOpts::Default).unwrap();
+        assert_eq!(s1.write(SocketWriteOpts::Default, b"hello").unwrap(), 5);
+        assert_eq!(s1.write(SocketWriteOpts::Default, b" world").unwrap(), 6);
+
+        // The two writes are read back as a single stream, in pieces of any size.
+        let mut read_vec = vec![0; 3];
+        assert_eq!(s2.read(SocketReadOpts::Default, &mut read_vec).unwrap(), 3);
+        assert_eq!(read_vec, b"hel");
+        let mut read_vec = vec![0; 16];
+        assert_eq!(s2.read(SocketReadOpts::Default, &mut read_vec).unwrap(), 8);
+        assert_eq!(&read_vec[0..8], b"lo world");
+        assert_eq!(s2.outstanding_read_bytes(), Ok(0));
+    }
+
+    #[test]
+    fn socket_exact_deadline() {
+        let (s1, s2) = Socket::create(SocketOpts::Default).unwrap();
+        let writer = thread::spawn(move || {
+            for chunk in b"hello world".chunks(3) {
+                s1.write_all_deadline(SocketWriteOpts::Default, chunk, Deadline::INFINITE)
+                    .unwrap();
+            }
+            s1
+        });
+        let mut read_vec = vec![0; 11];
+        assert!(s2.read_exact_deadline(SocketReadOpts::Default, &mut read_vec,
+            Deadline::INFINITE).is_ok());
+        assert_eq!(read_vec, b"hello world");
+
+        // Nothing more is coming, so the next read times out.
+        let deadline = deadline_after(Duration::from_millis(10));
+        assert_eq!(s2.read_exact_deadline(SocketReadOpts::Default, &mut read_vec, deadline),
+            Err(Status::ErrTimedOut));
+        drop(writer.join().unwrap());
+        assert_eq!(s2.read_exact_deadline(SocketReadOpts::Default, &mut read_vec,
+            Deadline::INFINITE), Err(Status::ErrPeerClosed));
+    }
+
+    #[test]
+    fn socket_info() {
+        let (s1, s2) = Socket::create(SocketOpts::Default).unwrap();
+        assert_eq!(s1.write(SocketWriteOpts::Default, b"hello").unwrap(), 5);
+        assert_eq!(s1.outstanding_write_bytes(), Ok(5));
+        assert_eq!(s2.outstanding_read_bytes(), Ok(5));
+
+        let info = s2.info().unwrap();
+        assert_eq!(info.options, SocketOpts::Default as u32);
+        assert!(info.rx_buf_max >= 5);
+        assert_eq!(info.tx_buf_size, 0);
+
+        let mut read_vec = vec![0; 3];
+        assert_eq!(s2.read(SocketReadOpts::Default, &mut read_vec).unwrap(), 3);
+        assert_eq!(s2.outstanding_read_bytes(), Ok(2));
+    }
+
+    #[test]
+    fn socket_datagram() {
+        let (s1, s2) = Socket::create(SocketOpts::Datagram).unwrap();
+        assert!(s1.write_datagram(b"hello").is_ok());
+        assert!(s1.write_datagram(b" world").is_ok());
+
+        // Each datagram is read back separately, and is not truncated to fit the buffer.
+        let mut read_vec = vec![0; 3];
+        assert_eq!(s2.next_datagram_size(), Ok(5));
+        assert_eq!(s2.read_datagram(&mut read_vec), Err(Status::ErrBufferTooSmall));
+        let mut read_vec = vec![0; 16];
+        assert_eq!(s2.read_datagram(&mut read_vec), Ok(5));
+        assert_eq!(&read_vec[0..5], b"hello");
+        assert_eq!(s2.read_datagram(&mut read_vec), Ok(6));
+        assert_eq!(&read_vec[0..6], b" world");
+        assert_eq!(s2.read_datagram(&mut read_vec), Err(Status::ErrShouldWait));
+    }
+
+    #[test]
+    fn socket_control() {
+        let (s1, s2) = Socket::create(SocketOpts::HasControl).unwrap();
+        assert!(s1.write_control(b"stop").is_ok());
+        assert_eq!(s1.write(SocketWriteOpts::Default, b"data").unwrap(), 4);
+
+        // The control message does not appear in the data stream.
+        let mut read_vec = vec![0; 8];
+        assert_eq!(s2.read(SocketReadOpts::Default, &mut read_vec).unwrap(), 4);
+        assert_eq!(&read_vec[0..4], b"data");
+        let deadline = deadline_after(Duration::from_millis(10));
+        assert!(s2.wait_handle(Signals::SOCKET_CONTROL_READABLE, deadline).is_ok());
+        assert_eq!(s2.read_control(&mut read_vec).unwrap(), 4);
+        assert_eq!(&read_vec[0..4], b"stop");
+        assert_eq!(s2.read_control(&mut read_vec), Err(Status::ErrShouldWait));
+    }
+
+    #[test]
+    fn socket_share() {
+        let (s1, s2) = Socket::create(SocketOpts::HasAccept).unwrap();
+        let (shared, kept) = Socket::create(SocketOpts::Default).unwrap();
+        let koid = shared.basic_info().unwrap().koid;
+        assert_eq!(s2.accept().unwrap_err(), Status::ErrShouldWait);
+        assert!(s1.share(shared).is_ok());
+
+        let deadline = deadline_after(Duration::from_millis(10));
+        assert!(s2.wait_handle(Signals::SOCKET_ACCEPT, deadline).is_ok());
+        let accepted = s2.accept().unwrap();
+        assert_eq!(accepted.basic_info().unwrap().koid, koid);
+        assert_eq!(kept.write(SocketWriteOpts::Default, b"hello").unwrap(), 5);
+        assert_eq!(accepted.outstanding_read_bytes(), Ok(5));
+    }
+
+    #[test]
+    fn socket_io() {
+        use std::io::{Read, Write};
+
+        let (mut s1, mut s2) = Socket::create(SocketOpts::Default).unwrap();
+        let writer = thread::spawn(move || {
+            s1.write_all(b"hello world").unwrap();
+            // Dropping the socket ends the stream.
+        });
+        let mut received = String::new();
+        assert_eq!(s2.read_to_string(&mut received).unwrap(), 11);
+        assert_eq!(received, "hello world");
+        assert!(writer.join().is_ok());
+
+        let err = Write::write(&mut s2, b"more").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn socket_io_half_close() {
+        use std::io::Read;
+
+        let (s1, mut s2) = Socket::create(SocketOpts::Default).unwrap();
+        assert_eq!(s1.write(SocketWriteOpts::Default, b"bye").unwrap(), 3);
+        assert!(s1.half_close().is_ok());
+
+        // The peer is still open, but has closed its half, so the stream ends.
+        let mut received = Vec::new();
+        assert_eq!(s2.read_to_end(&mut received).unwrap(), 3);
+        assert_eq!(received, b"bye");
+        assert_eq!(Read::read(&mut s2, &mut [0; 4]).unwrap(), 0);
+    }
+
+    #[test]
+    fn socket_stdio_pipe() {
+        use std::io::{Read, Write};
+
+        let (mut output, remote) = stdio_pipe().unwrap();
+        let child = thread::spawn(move || {
+            let mut stdout = Socket::from(remote);
+            writeln!(stdout, "hello").unwrap();
+        });
+        let mut received = String::new();
+        assert!(output.read_to_string(&mut received).is_ok());
+        assert_eq!(received, "hello\n");
+        assert!(child.join().is_ok());
+    }
+
+    #[test]
+    fn socket_half_close_signals() {
+        let (s1, s2) = Socket::create(SocketOpts::Default).unwrap();
+        let deadline = deadline_after(Duration::from_millis(10));
+        assert_eq!(s2.wait_handle(Signals::SOCKET_READ_DISABLED, deadline),
+            Err(Status::ErrTimedOut));
+        assert!(s1.half_close().is_ok());
+        assert!(s1.wait_handle(Signals::SOCKET_WRITE_DISABLED, deadline).unwrap()
+            .contains(Signals::SOCKET_WRITE_DISABLED));
+        assert!(s2.wait_handle(Signals::SOCKET_READ_DISABLED, deadline).unwrap()
+            .contains(Signals::SOCKET_READ_DISABLED));
+    }
+
+    #[test]
+    fn socket_read_after_half_close() {
+        let (s1, s2) = Socket::create(SocketOpts::Default).unwrap();
+        assert_eq!(s1.write(SocketWriteOpts::Default, b"last").unwrap(), 4);
+        assert!(s1.half_close().is_ok());
+
+        // Data written before the half-close can still be read, then reads fail.
+        let mut read_vec = vec![0; 8];
+        assert_eq!(s2.read(SocketReadOpts::Default, &mut read_vec).unwrap(), 4);
+        assert_eq!(&read_vec[0..4], b"last");
+        assert_eq!(s2.read(SocketReadOpts::Default, &mut read_vec), Err(Status::ErrPeerClosed));
+        assert_eq!(s1.write(SocketWriteOpts::Default, b"more"), Err(Status::ErrBadState));
+    }
+
+    #[test]
+    fn socket_write_deadline() {
+        let (s1, s2) = Socket::create(SocketOpts::Default).unwrap();
+        let deadline = deadline_after(Duration::from_millis(10));
+        assert_eq!(s1.write_deadline(SocketWriteOpts::Default, b"hello", deadline), Ok(5));
+        drop(s2);
+        assert_eq!(s1.write_deadline(SocketWriteOpts::Default, b"hello", deadline),
+            Err(Status::ErrPeerClosed));
+    }
 }
@@ -0,0 +1,130 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Access to the handles the current process was started with.
+
+use Handle;
+use sys;
+
+/// The type of a handle in the processargs handle table, which tells a process what a handle
+/// it was started with is for. Together with an argument, which distinguishes handles of the
+/// same type, it makes up the handle's info.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum HandleType {
+    /// The process itself.
+    ProcessSelf = sys::PA_PROC_SELF,
+    /// The initial thread of the process.
+    ThreadSelf = sys::PA_THREAD_SELF,
+    /// The job in which the process should create new processes.
+    DefaultJob = sys::PA_JOB_DEFAULT,
+    /// The root region of the process's address space.
+    RootVmar = sys::PA_VMAR_ROOT,
+    /// The region the executable was loaded into.
+    LoadedVmar = sys::PA_VMAR_LOADED,
+    /// A channel to the loader service, for loading shared libraries.
+    LoaderService = sys::PA_SVC_LOADER,
+    /// The virtual memory object of the vDSO image.
+    VdsoVmo = sys::PA_VMO_VDSO,
+    /// The virtual memory object of the initial thread's stack.
+    StackVmo = sys::PA_VMO_STACK,
+    /// The virtual memory object of the executable.
+    ExecutableVmo = sys::PA_VMO_EXECUTABLE,
+    /// A virtual memory object of boot data.
+    BootdataVmo = sys::PA_VMO_BOOTDATA,
+    /// The virtual memory object of the boot filesystem.
+    BootfsVmo = sys::PA_VMO_BOOTFS,
+    /// A virtual memory object of a file from the kernel.
+    KernelFileVmo = sys::PA_VMO_KERNEL_FILE,
+    /// A directory in the process's namespace.
+    NamespaceDirectory = sys::PA_NS_DIR,
+    /// A remote file descriptor, whose number is the argument.
+    FdioRemote = sys::PA_FDIO_REMOTE,
+    /// A pipe file descriptor, whose number is the argument.
+    FdioPipe = sys::PA_FDIO_PIPE,
+    /// An event file descriptor, whose number is the argument.
+    FdioEvent = sys::PA_FDIO_EVENT,
+    /// A logger file descriptor, whose number is the argument.
+    FdioLogger = sys::PA_FDIO_LOGGER,
+    /// A socket file descriptor, whose number is the argument.
+    FdioSocket = sys::PA_FDIO_SOCKET,
+    /// A handle whose meaning is agreed between the process and whoever started it.
+    User0 = sys::PA_USER0,
+    /// A handle whose meaning is agreed between the process and whoever started it.
+    User1 = sys::PA_USER1,
+    /// A handle whose meaning is agreed between the process and whoever started it.
+    User2 = sys::PA_USER2,
+}
+
+impl HandleType {
+    /// The processargs handle info of a handle of this type with the given argument.
+    pub fn info(self, arg: u16) -> u32 {
+        self as u32 | ((arg as u32) << 16)
+    }
+}
+
+/// Take the handle of the given type and argument which the current process was started with.
+/// Returns `None` if it was not given one, or it has already been taken; each handle can only
+/// be taken once.
+pub fn take_startup_handle(handle_type: HandleType, arg: u16) -> Option<Handle> {
+    let raw = unsafe { sys::zx_get_startup_handle(handle_type.info(arg)) };
+    if raw == sys::ZX_HANDLE_INVALID {
+        None
+    } else {
+        Some(Handle::owned(raw))
+    }
+}
+
+/// Take the handles of the given type which the current process was started with, in order of
+/// their arguments, starting from zero and stopping at the first argument with no handle.
+///
+/// The runtime does not list the handles it holds, so a handle whose argument follows a gap is
+/// not found; take it with `take_startup_handle` instead.
+pub fn startup_handles(handle_type: HandleType) -> StartupHandles {
+    StartupHandles { handle_type, next_arg: Some(0) }
+}
+
+/// An iterator over the handles of one type the current process was started with, taking
+/// each along with its argument. Created by `startup_handles`.
+#[derive(Debug)]
+pub struct StartupHandles {
+    handle_type: HandleType,
+    next_arg: Option<u16>,
+}
+
+impl Iterator for StartupHandles {
+    type Item = (u16, Handle);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let arg = self.next_arg?;
+        match take_startup_handle(self.handle_type, arg) {
+            Some(handle) => {
+                self.next_arg = arg.checked_add(1);
+                Some((arg, handle))
+            }
+            None => {
+                self.next_arg = None;
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handle_info() {
+        assert_eq!(HandleType::ProcessSelf.info(0), 0x01);
+        assert_eq!(HandleType::User0.info(3), 0x0003_00f0);
+    }
+
+    #[test]
+    fn take_once() {
+        // The test runner is not given any user handles.
+        assert!(take_startup_handle(HandleType::User2, 0xffff).is_none());
+        assert_eq!(startup_handles(HandleType::User2).count(), 0);
+    }
+}
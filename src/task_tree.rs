@@ -0,0 +1,127 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! A snapshot of the tree of jobs, processes and threads under a job.
+
+use {AsHandleRef, Job, Koid, Process, ProcessInfo, Rights, Status, Thread, ThreadInfo};
+
+/// A job in a snapshot taken with `task_tree`, with the jobs and processes within it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct JobNode {
+    /// The koid of the job.
+    pub koid: Koid,
+    /// The name of the job.
+    pub name: String,
+    /// The child jobs of the job.
+    pub jobs: Vec<JobNode>,
+    /// The processes directly within the job.
+    pub processes: Vec<ProcessNode>,
+}
+
+/// A process in a snapshot taken with `task_tree`, with its threads.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ProcessNode {
+    /// The koid of the process.
+    pub koid: Koid,
+    /// The name of the process.
+    pub name: String,
+    /// Whether the process has started or exited.
+    pub info: ProcessInfo,
+    /// The threads of the process.
+    pub threads: Vec<ThreadNode>,
+}
+
+/// A thread in a snapshot taken with `task_tree`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ThreadNode {
+    /// The koid of the thread.
+    pub koid: Koid,
+    /// The name of the thread.
+    pub name: String,
+    /// What the thread was doing.
+    pub info: ThreadInfo,
+}
+
+/// Take a snapshot of the jobs, processes and threads under `root`, for tools such as `ps`.
+///
+/// Tasks are listed and then opened one at a time, so those which die in between are left out,
+/// and those created meanwhile may be missed. Each task is opened with the rights of the handle
+/// to its parent, which must allow listing children and reading names and state.
+pub fn task_tree(root: &Job) -> Result<JobNode, Status> {
+    let mut jobs = Vec::new();
+    for koid in root.children()? {
+        if let Some(job) = skip_dead(root.get_child(koid, Rights::SAME_RIGHTS))? {
+            if let Some(node) = skip_dead(task_tree(&Job::from(job)))? {
+                jobs.push(node);
+            }
+        }
+    }
+    let mut processes = Vec::new();
+    for koid in root.processes()? {
+        if let Some(process) = skip_dead(root.get_child(koid, Rights::SAME_RIGHTS))? {
+            if let Some(node) = skip_dead(process_node(&Process::from(process)))? {
+                processes.push(node);
+            }
+        }
+    }
+    Ok(JobNode { koid: root.basic_info()?.koid, name: root.get_name()?, jobs, processes })
+}
+
+fn process_node(process: &Process) -> Result<ProcessNode, Status> {
+    let mut threads = Vec::new();
+    for koid in process.threads()? {
+        if let Some(thread) = skip_dead(process.get_child(koid, Rights::SAME_RIGHTS))? {
+            let thread = Thread::from(thread);
+            if let Some(info) = skip_dead(thread.info())? {
+                threads.push(ThreadNode { koid, name: thread.get_name()?, info });
+            }
+        }
+    }
+    Ok(ProcessNode {
+        koid: process.basic_info()?.koid,
+        name: process.get_name()?,
+        info: process.info()?,
+        threads,
+    })
+}
+
+// Treat a task which died after being listed, or while being inspected, as missing.
+fn skip_dead<T>(result: Result<T, Status>) -> Result<Option<T>, Status> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(Status::ErrNotFound) | Err(Status::ErrBadState) => Ok(None),
+        Err(status) => Err(status),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {JobOpts, ProcessOpts, ThreadOpts, ThreadState, job_default};
+
+    #[test]
+    fn walk_tree() {
+        let root = Job::create(&job_default(), JobOpts::Default).unwrap();
+        root.set_name("root").unwrap();
+        let child = Job::create(&root, JobOpts::Default).unwrap();
+        let (process, _vmar) = Process::create(&child, "proc", ProcessOpts::Default).unwrap();
+        let thread = Thread::create(&process, "thread", ThreadOpts::Default).unwrap();
+
+        let tree = task_tree(&root).unwrap();
+        assert_eq!(tree.name, "root");
+        assert!(tree.processes.is_empty());
+        assert_eq!(tree.jobs.len(), 1);
+        let child_node = &tree.jobs[0];
+        assert_eq!(child_node.koid, child.basic_info().unwrap().koid);
+        assert_eq!(child_node.processes.len(), 1);
+        let process_node = &child_node.processes[0];
+        assert_eq!(process_node.name, "proc");
+        assert!(!process_node.info.started);
+        assert_eq!(process_node.threads, vec![ThreadNode {
+            koid: thread.basic_info().unwrap().koid,
+            name: "thread".to_owned(),
+            info: ThreadInfo { state: Some(ThreadState::New) },
+        }]);
+    }
+}
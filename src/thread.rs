@@ -4,11 +4,383 @@
 
 //! Type-safe bindings for Zircon thread.
 
-use {AsHandleRef, HandleBased, Handle, HandleRef};
+use {AsHandleRef, Deadline, HandleBased, Handle, HandleRef, Mapping, ObjectType, Process};
+use {ReadWrite, Signals, Status, Task, Unowned, Vmo, VmoOpts, process_self};
+use {ThreadInfo, ThreadQuery, initial_stack_pointer, object_get_info_single, round_up_to_page};
+use {sys, into_result, usize_into_u32};
+use std::mem;
+use std::ops::Deref;
 
 /// An object representing a Zircon thread.
 ///
 /// As essentially a subtype of `Handle`, it can be freely interconverted.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Eq, PartialEq)]
 pub struct Thread(Handle);
-impl_handle_based!(Thread);
\ No newline at end of file
+impl_handle_based!(Thread, ObjectType::Thread);
+impl Task for Thread {}
+
+impl Thread {
+    /// Create a new thread within `process`. The thread does not run until it is started, with
+    /// `start`, or with `Process::start` if it is the first thread of a new process.
+    ///
+    /// Wraps the
+    /// [zx_thread_create](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/thread_create.md)
+    /// syscall.
+    pub fn create(process: &Process, name: &str, opts: ThreadOpts) -> Result<Thread, Status> {
+        let name_len = usize_into_u32(name.len()).map_err(|_| Status::ErrOutOfRange)?;
+        let mut out = 0;
+        let status = unsafe {
+            sys::zx_thread_create(process.raw_handle(), name.as_ptr(), name_len, opts as u32,
+                &mut out)
+        };
+        into_result(status, || Thread::from(Handle::owned(out)))
+    }
+
+    /// Start a thread in the current process which runs `f` on a stack of `stack_size` bytes,
+    /// rounded up to whole pages, and then exits. This is a thread below the level of
+    /// `std::thread`, for runtimes which manage their own threads.
+    ///
+    /// The thread has no thread-local storage, so `f` must not use anything which relies on
+    /// it, including `std::thread::current`, printing with `println!`, and panicking; doing so
+    /// crashes the process. The stack is unmapped once the thread has exited and the returned
+    /// `SpawnedThread` is dropped or joined.
+    pub fn spawn<F>(stack_size: usize, f: F) -> Result<SpawnedThread, Status>
+        where F: FnOnce() + Send + 'static
+    {
+        let stack_size = round_up_to_page(stack_size).ok_or(Status::ErrOutOfRange)?;
+        // The object is new, and its only handle is dropped here, so nothing else can write to
+        // the stack.
+        let stack = unsafe {
+            Vmo::create(stack_size as u64, VmoOpts::Default)?.map::<ReadWrite>(0, stack_size)?
+        };
+        let thread = Thread::create(&process_self(), "", ThreadOpts::Default)?;
+
+        // The closure is boxed twice so it can be passed as a thin pointer.
+        let closure: Box<Box<dyn FnOnce() + Send>> = Box::new(Box::new(f));
+        let closure = Box::into_raw(closure);
+        let sp = initial_stack_pointer(stack.addr() + stack_size);
+        // The trampoline takes ownership of the closure, and the stack stays mapped until the
+        // thread has exited.
+        let result = unsafe {
+            thread.start(trampoline as *const () as usize, sp, closure as usize, 0)
+        };
+        if let Err(status) = result {
+            drop(unsafe { Box::from_raw(closure) });
+            return Err(status);
+        }
+        Ok(SpawnedThread { thread, stack: Some(stack) })
+    }
+
+    /// Start the thread running at `entry`, with its stack pointer set to `stack` and `arg1`
+    /// and `arg2` as its first two arguments. This is for threads in a process which is already
+    /// running; the first thread of a process is started with `Process::start`.
+    ///
+    /// Wraps the
+    /// [zx_thread_start](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/thread_start.md)
+    /// syscall.
+    ///
+    /// # Safety
+    ///
+    /// The thread starts with nothing but the registers set here: `entry` must be the address
+    /// of an `extern "C" fn(usize, usize) -> !` which never returns, finishing with
+    /// `Thread::exit` instead, and `stack` must be the top of memory which stays mapped and is
+    /// not otherwise used until the thread has exited, aligned as the platform ABI requires at
+    /// function entry. The thread has no thread-local storage or other runtime state, so `entry`
+    /// must not use anything from `std` which relies on it. If the thread is in another process,
+    /// these are requirements on that process's memory instead.
+    pub unsafe fn start(&self, entry: usize, stack: usize, arg1: usize, arg2: usize)
+        -> Result<(), Status>
+    {
+        let status = sys::zx_thread_start(self.raw_handle(), entry, stack, arg1, arg2);
+        into_result(status, || ())
+    }
+
+    /// What the thread is doing.
+    ///
+    /// Wraps the `ZX_INFO_THREAD` topic of
+    /// [zx_object_get_info](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_get_info.md).
+    pub fn info(&self) -> Result<ThreadInfo, Status> {
+        object_get_info_single::<ThreadQuery>(self.as_handle_ref()).map(ThreadInfo::from)
+    }
+
+    /// Read the state of the thread of kind `S`, such as its general registers with
+    /// `GeneralRegs`. The thread must be suspended or stopped in an exception; otherwise this
+    /// fails with `ErrBadState`.
+    ///
+    /// Wraps the
+    /// [zx_thread_read_state](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/thread_read_state.md)
+    /// syscall.
+    pub fn read_state<S: ThreadStateKind>(&self) -> Result<S::State, Status> {
+        let len = usize_into_u32(mem::size_of::<S::State>()).map_err(|_| Status::ErrOutOfRange)?;
+        let mut actual = 0;
+        unsafe {
+            let mut state: S::State = mem::zeroed();
+            let status = sys::zx_thread_read_state(self.raw_handle(), S::KIND,
+                &mut state as *mut S::State as *mut u8, len, &mut actual);
+            into_result(status, || state)
+        }
+    }
+
+    /// Write the state of the thread of kind `S`, such as its general registers with
+    /// `GeneralRegs`. The thread must be suspended or stopped in an exception; otherwise this
+    /// fails with `ErrBadState`.
+    ///
+    /// Wraps the
+    /// [zx_thread_write_state](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/thread_write_state.md)
+    /// syscall.
+    pub fn write_state<S: ThreadStateKind>(&self, state: &S::State) -> Result<(), Status> {
+        let len = usize_into_u32(mem::size_of::<S::State>()).map_err(|_| Status::ErrOutOfRange)?;
+        let status = unsafe {
+            sys::zx_thread_write_state(self.raw_handle(), S::KIND,
+                state as *const S::State as *const u8, len)
+        };
+        into_result(status, || ())
+    }
+
+    /// Exit the current thread.
+    ///
+    /// Wraps the
+    /// [zx_thread_exit](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/thread_exit.md)
+    /// syscall.
+    ///
+    /// # Safety
+    ///
+    /// The thread exits immediately, without unwinding, so nothing on its stack is dropped and
+    /// thread-local destructors do not run. This must not be called from a thread started by
+    /// `std::thread`, which expects to clean up after it returns.
+    pub unsafe fn exit() -> ! {
+        sys::zx_thread_exit();
+        unreachable!("zx_thread_exit returned")
+    }
+}
+// The entry point of threads started by `Thread::spawn`, which runs the closure passed in
+// `closure` and exits.
+extern "C" fn trampoline(closure: usize, _arg2: usize) -> ! {
+    let closure = unsafe { Box::from_raw(closure as *mut Box<dyn FnOnce() + Send>) };
+    closure();
+    unsafe { Thread::exit() }
+}
+
+/// A thread started with `Thread::spawn`, which owns its stack.
+#[derive(Debug)]
+pub struct SpawnedThread {
+    thread: Thread,
+    // Taken when the stack is unmapped or leaked.
+    stack: Option<Mapping<'static, ReadWrite>>,
+}
+
+impl SpawnedThread {
+    /// Wait for the thread to exit, then unmap its stack.
+    pub fn join(mut self) -> Result<(), Status> {
+        self.thread.wait_handle(Signals::THREAD_TERMINATED, Deadline::INFINITE)?;
+        self.stack = None;
+        Ok(())
+    }
+}
+
+impl Deref for SpawnedThread {
+    type Target = Thread;
+    fn deref(&self) -> &Thread {
+        &self.thread
+    }
+}
+
+impl Drop for SpawnedThread {
+    fn drop(&mut self) {
+        if let Some(stack) = self.stack.take() {
+            // The stack cannot be unmapped while the thread may still be running on it.
+            let exited = self.thread.wait_handle(Signals::THREAD_TERMINATED,
+                Deadline::from_nanos(0));
+            if exited.is_err() {
+                mem::forget(stack);
+            }
+        }
+    }
+}
+
+/// The thread this is called on, as provided by the runtime.
+///
+/// The handle is borrowed from the runtime and is not closed when dropped. It is only valid
+/// while the thread is running, so should not be kept after the thread exits.
+pub fn thread_self() -> Unowned<'static, Thread> {
+    unsafe { Unowned::from_raw_handle(sys::zx_thread_self()) }
+}
+
+/// A kind of thread state which may be read with `Thread::read_state` and written with
+/// `Thread::write_state`, together with the type it is read into.
+///
+/// # Safety
+///
+/// The kernel writes the state directly into a `State` value, so `State` must have exactly the
+/// layout of the C type the kernel uses for `KIND`, and every bit pattern the kernel may write
+/// must be a valid `State`.
+pub unsafe trait ThreadStateKind {
+    /// The kind of state, one of the `ZX_THREAD_STATE_*` constants.
+    const KIND: u32;
+    /// The type of the state.
+    type State: Copy;
+}
+
+/// The general purpose registers of a thread (`ZX_THREAD_STATE_GENERAL_REGS`), whose layout
+/// depends on the architecture.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct GeneralRegs;
+
+unsafe impl ThreadStateKind for GeneralRegs {
+    const KIND: u32 = sys::ZX_THREAD_STATE_GENERAL_REGS;
+    type State = sys::zx_thread_state_general_regs_t;
+}
+
+/// Options for creating a thread.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ThreadOpts {
+    /// Default options.
+    Default = 0,
+}
+
+impl Default for ThreadOpts {
+    fn default() -> Self {
+        ThreadOpts::Default
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Event, EventOpts, ExceptionPortOpts, ExceptionType, ObjectType, PacketContents};
+    use {Port, PortOpts, ThreadState};
+    use std::ptr;
+
+    // Signals the event whose raw handle is `event`, then exits.
+    extern "C" fn signal_and_exit(event: usize, _arg2: usize) -> ! {
+        unsafe {
+            sys::zx_object_signal(event as sys::zx_handle_t, 0, sys::ZX_USER_SIGNAL_0.bits());
+            Thread::exit()
+        }
+    }
+
+    // Waits for the event whose raw handle is `event` to be signaled, then exits.
+    extern "C" fn wait_and_exit(event: usize, _arg2: usize) -> ! {
+        unsafe {
+            sys::zx_object_wait_one(event as sys::zx_handle_t, sys::ZX_USER_SIGNAL_0,
+                sys::ZX_TIME_INFINITE, ptr::null_mut());
+            Thread::exit()
+        }
+    }
+
+    #[test]
+    fn suspend_and_resume() {
+        let event = Event::create(EventOpts::Default).unwrap();
+        let thread = Thread::create(&process_self(), "waiter", ThreadOpts::Default).unwrap();
+        let vmo = Vmo::create(16 * 4096, VmoOpts::Default).unwrap();
+        let stack = unsafe { vmo.map::<ReadWrite>(0, 16 * 4096) }.unwrap();
+        let top = stack.addr() + stack.len();
+        let sp = if cfg!(target_arch = "x86_64") { top - 8 } else { top };
+        let entry = wait_and_exit as *const () as usize;
+        unsafe {
+            thread.start(entry, sp, event.raw_handle() as usize, 0).unwrap();
+        }
+
+        let token = thread.suspend().unwrap();
+        assert!(thread.wait_handle(Signals::THREAD_SUSPENDED, Deadline::INFINITE).is_ok());
+        assert!(token.task().read_state::<GeneralRegs>().is_ok());
+        assert_eq!(token.resume(), Ok(()));
+        assert!(thread.wait_handle(Signals::THREAD_RUNNING, Deadline::INFINITE).is_ok());
+
+        // Dropping the token resumes the thread too.
+        {
+            let _token = thread.suspend().unwrap();
+            assert!(thread.wait_handle(Signals::THREAD_SUSPENDED, Deadline::INFINITE).is_ok());
+        }
+        assert!(thread.wait_handle(Signals::THREAD_RUNNING, Deadline::INFINITE).is_ok());
+
+        assert_eq!(event.signal_handle(Signals::NONE, Signals::USER_0), Ok(()));
+        assert!(thread.wait_handle(Signals::THREAD_TERMINATED, Deadline::INFINITE).is_ok());
+    }
+
+    // Faults by writing to unmapped memory.
+    extern "C" fn fault(_arg1: usize, _arg2: usize) -> ! {
+        unsafe {
+            ptr::write_volatile(ptr::null_mut::<u8>(), 0);
+            Thread::exit()
+        }
+    }
+
+    #[test]
+    fn exception_port() {
+        let port = Port::create(PortOpts::Default).unwrap();
+        let thread = Thread::create(&process_self(), "faulter", ThreadOpts::Default).unwrap();
+        assert_eq!(thread.bind_exception_port(&port, 5, ExceptionPortOpts::Default), Ok(()));
+        assert_eq!(thread.bind_exception_port(&port, 5, ExceptionPortOpts::Default),
+            Err(Status::ErrAlreadyBound));
+
+        let vmo = Vmo::create(16 * 4096, VmoOpts::Default).unwrap();
+        let stack = unsafe { vmo.map::<ReadWrite>(0, 16 * 4096) }.unwrap();
+        let top = stack.addr() + stack.len();
+        let sp = if cfg!(target_arch = "x86_64") { top - 8 } else { top };
+        unsafe {
+            thread.start(fault as *const () as usize, sp, 0, 0).unwrap();
+        }
+
+        let packet = port.wait(Deadline::INFINITE).unwrap();
+        assert_eq!(packet.key(), 5);
+        match packet.contents() {
+            PacketContents::Exception(exception) => {
+                assert_eq!(exception.exception_type(), Some(ExceptionType::FatalPageFault));
+                assert_eq!(exception.pid(), process_self().basic_info().unwrap().koid);
+                assert_eq!(exception.tid(), thread.basic_info().unwrap().koid);
+            }
+            _ => panic!("wrong packet type"),
+        }
+        assert_eq!(thread.kill(), Ok(()));
+        assert!(thread.wait_handle(Signals::THREAD_TERMINATED, Deadline::INFINITE).is_ok());
+    }
+
+    #[test]
+    fn spawn_and_join() {
+        let event = Event::create(EventOpts::Default).unwrap();
+        let signaled = event.duplicate_handle(::Rights::SAME_RIGHTS).unwrap();
+        let thread = Thread::spawn(64 * 1024, move || {
+            signaled.signal_handle(Signals::NONE, Signals::USER_0).unwrap();
+        }).unwrap();
+        assert_eq!(thread.join(), Ok(()));
+        assert!(event.wait_handle(Signals::USER_0, Deadline::from_nanos(0)).is_ok());
+    }
+
+    #[test]
+    fn start_and_exit() {
+        let event = Event::create(EventOpts::Default).unwrap();
+        let thread = Thread::create(&process_self(), "raw", ThreadOpts::Default).unwrap();
+        assert_eq!(thread.get_name(), Ok("raw".to_owned()));
+
+        let vmo = Vmo::create(16 * 4096, VmoOpts::Default).unwrap();
+        let stack = unsafe { vmo.map::<ReadWrite>(0, 16 * 4096) }.unwrap();
+        // The stack pointer at function entry, as though the function had been called.
+        let top = stack.addr() + stack.len();
+        let sp = if cfg!(target_arch = "x86_64") { top - 8 } else { top };
+        let entry = signal_and_exit as *const () as usize;
+        unsafe {
+            thread.start(entry, sp, event.raw_handle() as usize, 0).unwrap();
+        }
+        assert!(thread.wait_handle(Signals::THREAD_TERMINATED, Deadline::INFINITE).is_ok());
+        assert!(event.wait_handle(Signals::USER_0, Deadline::INFINITE).is_ok());
+    }
+
+    #[test]
+    fn read_state_not_suspended() {
+        let thread = Thread::create(&process_self(), "idle", ThreadOpts::Default).unwrap();
+        assert_eq!(thread.info().unwrap().state, Some(ThreadState::New));
+        assert_eq!(thread.read_state::<GeneralRegs>().unwrap_err(), Status::ErrBadState);
+        let invalid = Thread::from(Handle::invalid());
+        assert_eq!(invalid.read_state::<GeneralRegs>().unwrap_err(), Status::ErrBadHandle);
+    }
+
+    #[test]
+    fn name_self() {
+        let thread = thread_self();
+        assert_eq!(thread.basic_info().unwrap().object_type, ObjectType::Thread);
+        assert_eq!(thread.set_name("worker"), Ok(()));
+        assert_eq!(thread.get_name(), Ok("worker".to_owned()));
+    }
+}
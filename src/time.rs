@@ -0,0 +1,128 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Type-safe durations and deadlines.
+
+use sys;
+use std::{ops, time};
+
+/// A span of time, measured in nanoseconds.
+///
+/// Unlike a `Deadline`, a `Duration` is relative; use `Deadline::after` to turn it into the
+/// point in time at which a wait should give up.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Duration(sys::zx_duration_t);
+
+impl Duration {
+    /// A duration of the given number of nanoseconds.
+    pub fn from_nanos(nanos: u64) -> Duration {
+        Duration(nanos)
+    }
+
+    /// A duration of the given number of microseconds, saturating on overflow.
+    pub fn from_micros(micros: u64) -> Duration {
+        Duration(micros.saturating_mul(1_000))
+    }
+
+    /// A duration of the given number of milliseconds, saturating on overflow.
+    pub fn from_millis(millis: u64) -> Duration {
+        Duration(millis.saturating_mul(1_000_000))
+    }
+
+    /// A duration of the given number of seconds, saturating on overflow.
+    pub fn from_seconds(seconds: u64) -> Duration {
+        Duration(seconds.saturating_mul(1_000_000_000))
+    }
+
+    /// The length of the duration in nanoseconds.
+    pub fn into_nanos(self) -> u64 {
+        self.0
+    }
+}
+
+impl From<time::Duration> for Duration {
+    /// Converts from a `std::time::Duration`, saturating if it does not fit in 64 bits of
+    /// nanoseconds.
+    fn from(duration: time::Duration) -> Self {
+        Duration::from_seconds(duration.as_secs())
+            + Duration::from_nanos(duration.subsec_nanos() as u64)
+    }
+}
+
+impl From<Duration> for time::Duration {
+    fn from(duration: Duration) -> Self {
+        time::Duration::new(duration.0 / 1_000_000_000, (duration.0 % 1_000_000_000) as u32)
+    }
+}
+
+impl ops::Add for Duration {
+    type Output = Duration;
+    fn add(self, other: Duration) -> Duration {
+        Duration(self.0.saturating_add(other.0))
+    }
+}
+
+/// A point in time on the monotonic clock at which a wait gives up, measured in nanoseconds
+/// since the system was powered on.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Deadline(sys::zx_time_t);
+
+impl Deadline {
+    /// A deadline which never passes.
+    pub const INFINITE: Deadline = Deadline(sys::ZX_TIME_INFINITE);
+
+    /// Compute the deadline `duration` from now.
+    ///
+    /// Wraps the
+    /// [zx_deadline_after](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/deadline_after.md)
+    /// syscall.
+    pub fn after(duration: Duration) -> Deadline {
+        Deadline(unsafe { sys::zx_deadline_after(duration.0) })
+    }
+
+    /// A deadline at the given number of nanoseconds on the monotonic clock.
+    pub fn from_nanos(nanos: sys::zx_time_t) -> Deadline {
+        Deadline(nanos)
+    }
+
+    /// The deadline as a number of nanoseconds on the monotonic clock.
+    pub fn into_nanos(self) -> sys::zx_time_t {
+        self.0
+    }
+}
+
+impl From<time::Duration> for Deadline {
+    /// The deadline which is the given `std::time::Duration` from now.
+    fn from(duration: time::Duration) -> Self {
+        Deadline::after(duration.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duration_units() {
+        assert_eq!(Duration::from_micros(3).into_nanos(), 3_000);
+        assert_eq!(Duration::from_millis(3).into_nanos(), 3_000_000);
+        assert_eq!(Duration::from_seconds(3).into_nanos(), 3_000_000_000);
+        assert_eq!(Duration::from_seconds(u64::MAX).into_nanos(), u64::MAX);
+    }
+
+    #[test]
+    fn duration_std_conversion() {
+        let std_duration = time::Duration::new(2, 5);
+        let duration: Duration = std_duration.into();
+        assert_eq!(duration.into_nanos(), 2_000_000_005);
+        assert_eq!(time::Duration::from(duration), std_duration);
+    }
+
+    #[test]
+    fn deadline_after() {
+        let deadline = Deadline::after(Duration::from_seconds(1));
+        assert!(deadline < Deadline::INFINITE);
+        assert!(deadline > Deadline::after(Duration::from_nanos(0)));
+    }
+}
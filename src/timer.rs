@@ -4,16 +4,16 @@
 
 //! Type-safe bindings for Zircon timer objects.
 
-use {AsHandleRef, ClockId, Duration, HandleBased, Handle, HandleRef, Status, Time};
+use {AsHandleRef, ClockId, Deadline, Duration, HandleBased, Handle, HandleRef, ObjectType, Status};
 use {sys, into_result};
 
 /// An object representing a Zircon
 /// [event pair](https://fuchsia.googlesource.com/zircon/+/master/docs/concepts.md#Other-IPC_Events_Event-Pairs_and-User-Signals).
 ///
 /// As essentially a subtype of `Handle`, it can be freely interconverted.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Eq, PartialEq)]
 pub struct Timer(Handle);
-impl_handle_based!(Timer);
+impl_handle_based!(Timer, ObjectType::Timer);
 
 impl Timer {
     /// Create a timer, an object that can signal when a specified point in time has been reached.
@@ -23,14 +23,16 @@ impl Timer {
     pub fn create(options: TimerOpts, clock_id: ClockId) -> Result<Timer, Status> {
         let mut out = 0;
         let status = unsafe { sys::zx_timer_create(options as u32, clock_id as u32, &mut out) };
-        into_result(status, || Self::from(Handle(out)))
+        into_result(status, || Self::from(Handle::owned(out)))
     }
 
     /// Start a one-shot timer that will fire when `deadline` passes. Wraps the
     /// [zx_timer_set](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/timer_set.md)
     /// syscall.
-    pub fn set(&self, deadline: Time, slack: Duration) -> Result<(), Status> {
-        let status = unsafe { sys::zx_timer_set(self.raw_handle(), deadline, slack) };
+    pub fn set(&self, deadline: Deadline, slack: Duration) -> Result<(), Status> {
+        let status = unsafe {
+            sys::zx_timer_set(self.raw_handle(), deadline.into_nanos(), slack.into_nanos())
+        };
         into_result(status, || ())
     }
 
@@ -60,7 +62,7 @@ impl Default for TimerOpts {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use {Duration, ZX_SIGNAL_LAST_HANDLE, ZX_TIMER_SIGNALED};
+    use {Duration, Signals};
     use deadline_after;
 
     #[test]
@@ -71,22 +73,22 @@ mod tests {
 
     #[test]
     fn timer_basic() {
-        let ten_ms: Duration = 10_000_000;
-        let twenty_ms: Duration = 20_000_000;
+        let ten_ms = Duration::from_millis(10);
+        let twenty_ms = Duration::from_millis(20);
 
         // Create a timer
         let timer = Timer::create(TimerOpts::Default, ClockId::Monotonic).unwrap();
 
         // Should not signal yet.
-        assert_eq!(timer.wait_handle(ZX_TIMER_SIGNALED, deadline_after(ten_ms)), Err(Status::ErrTimedOut));
+        assert_eq!(timer.wait_handle(Signals::TIMER_SIGNALED, deadline_after(ten_ms)), Err(Status::ErrTimedOut));
 
         // Set it, and soon it should signal.
-        assert_eq!(timer.set(ten_ms, 0), Ok(()));
-        assert_eq!(timer.wait_handle(ZX_TIMER_SIGNALED, deadline_after(twenty_ms)).unwrap(),
-            ZX_TIMER_SIGNALED | ZX_SIGNAL_LAST_HANDLE);
+        assert_eq!(timer.set(deadline_after(ten_ms), Duration::from_nanos(0)), Ok(()));
+        assert_eq!(timer.wait_handle(Signals::TIMER_SIGNALED, deadline_after(twenty_ms)).unwrap(),
+            Signals::TIMER_SIGNALED | Signals::LAST_HANDLE);
 
         // Cancel it, and it should stop signalling.
         assert_eq!(timer.cancel(), Ok(()));
-        assert_eq!(timer.wait_handle(ZX_TIMER_SIGNALED, deadline_after(ten_ms)), Err(Status::ErrTimedOut));
+        assert_eq!(timer.wait_handle(Signals::TIMER_SIGNALED, deadline_after(ten_ms)), Err(Status::ErrTimedOut));
     }
 }
@@ -0,0 +1,233 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Framing for call-style protocols, where each message starts with a transaction id.
+
+use {AsHandleRef, Channel, Deadline, Handle, HandleRef, MessageBuf, Status};
+use channel::TXID_LEN;
+use std::collections::{HashMap, HashSet};
+use std::io::IoSlice;
+use std::mem;
+
+/// The transaction id ('txid') at the start of a message, as used by `zx_channel_call`, or
+/// `None` if the message is too short to have one.
+pub fn message_txid(bytes: &[u8]) -> Option<u32> {
+    if bytes.len() < TXID_LEN {
+        return None;
+    }
+    Some(u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// A channel carrying requests and replies identified by transaction ids, allowing many
+/// requests to be in flight at once.
+///
+/// Each request is written with its txid in the first four bytes, in the same format as
+/// `Channel::call`, and the peer is expected to start its reply with the same txid. Replies are
+/// collected as they arrive and can be retrieved by txid, in any order.
+#[derive(Debug)]
+pub struct Transactions {
+    channel: Channel,
+    outstanding: HashSet<u32>,
+    replies: HashMap<u32, MessageBuf>,
+}
+
+impl Transactions {
+    /// Use a channel for transactions.
+    pub fn new(channel: Channel) -> Transactions {
+        Transactions { channel, outstanding: HashSet::new(), replies: HashMap::new() }
+    }
+
+    /// Stop using the channel for transactions. Any outstanding transactions and unclaimed
+    /// replies are discarded.
+    pub fn into_channel(self) -> Channel {
+        self.channel
+    }
+
+    /// Send a request, prefixing `body` with a fresh txid from `Channel::next_txid`, and return
+    /// the txid. Sharing the allocator with `Channel::call` means the two can be used on the
+    /// same channel without their txids colliding.
+    ///
+    /// As with `Channel::write`, the handles are removed from the vector if the request is
+    /// written successfully.
+    pub fn send(&mut self, body: &[u8], handles: &mut Vec<Handle>) -> Result<u32, Status> {
+        let mut txid = Channel::next_txid();
+        // The allocator only repeats after wrapping around, but skip anything still in use.
+        while self.outstanding.contains(&txid) || self.replies.contains_key(&txid) {
+            txid = Channel::next_txid();
+        }
+        let header = txid.to_ne_bytes();
+        self.channel.write_vectored(&[IoSlice::new(&header), IoSlice::new(body)], handles, 0)?;
+        self.outstanding.insert(txid);
+        Ok(txid)
+    }
+
+    /// Returns true if a request with the given txid has been sent, but no reply received.
+    pub fn is_outstanding(&self, txid: u32) -> bool {
+        self.outstanding.contains(&txid)
+    }
+
+    /// Give up on the transaction with the given txid, so that a reply which arrives later is
+    /// treated like one to a transaction which was never sent. Any reply already received is
+    /// discarded. Returns true if the transaction was outstanding or had an unclaimed reply.
+    pub fn abandon(&mut self, txid: u32) -> bool {
+        let outstanding = self.outstanding.remove(&txid);
+        self.replies.remove(&txid).is_some() || outstanding
+    }
+
+    /// Read any replies currently queued on the channel, without waiting, appending the txids
+    /// they were for to `txids`, and return the number read.
+    ///
+    /// Fails with `ErrIoDataIntegrity` if a message is too short to have a txid, or
+    /// `ErrNotFound` if it is for a transaction which is not outstanding; the offending
+    /// message is discarded, and later ones are left queued. Replies routed before the failure
+    /// have still been appended to `txids`.
+    pub fn poll(&mut self, txids: &mut Vec<u32>) -> Result<usize, Status> {
+        let mut count = 0;
+        let mut buf = MessageBuf::new();
+        while self.channel.try_read(0, &mut buf)? {
+            txids.push(self.route(mem::replace(&mut buf, MessageBuf::new()))?);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Take the reply to the transaction with the given txid, if it has been received. The
+    /// bytes of the reply still start with the txid.
+    pub fn take_reply(&mut self, txid: u32) -> Option<MessageBuf> {
+        self.replies.remove(&txid)
+    }
+
+    /// Wait for the reply to the transaction with the given txid, collecting any replies to
+    /// other transactions which arrive first. Fails with `ErrNotFound` if the transaction is
+    /// neither outstanding nor has an unclaimed reply.
+    ///
+    /// Unlike `poll`, messages which are too short to have a txid, or which are for
+    /// transactions that are not outstanding (such as a late reply to an abandoned
+    /// transaction), are discarded rather than failing the wait.
+    pub fn wait_reply(&mut self, txid: u32, deadline: Deadline) -> Result<MessageBuf, Status> {
+        loop {
+            if let Some(reply) = self.take_reply(txid) {
+                return Ok(reply);
+            }
+            if !self.is_outstanding(txid) {
+                return Err(Status::ErrNotFound);
+            }
+            let mut buf = MessageBuf::new();
+            self.channel.read_deadline(0, &mut buf, deadline)?;
+            match self.route(buf) {
+                Ok(_) | Err(Status::ErrNotFound) | Err(Status::ErrIoDataIntegrity) => {}
+                Err(status) => return Err(status),
+            }
+        }
+    }
+
+    // Record a reply against its transaction.
+    fn route(&mut self, reply: MessageBuf) -> Result<u32, Status> {
+        let txid = message_txid(reply.bytes()).ok_or(Status::ErrIoDataIntegrity)?;
+        if !self.outstanding.remove(&txid) {
+            return Err(Status::ErrNotFound);
+        }
+        self.replies.insert(txid, reply);
+        Ok(txid)
+    }
+}
+
+impl AsHandleRef for Transactions {
+    fn as_handle_ref(&self) -> HandleRef {
+        self.channel.as_handle_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ChannelOpts;
+
+    // Reply to each queued request with its txid followed by `reply`.
+    fn reply_all(server: &Channel, reply: &[u8]) {
+        let mut bufs = vec![];
+        server.drain(0, &mut bufs).unwrap();
+        for buf in bufs.iter().rev() {
+            let mut bytes = buf.bytes()[..TXID_LEN].to_vec();
+            bytes.extend_from_slice(reply);
+            server.write(&bytes, &mut vec![], 0).unwrap();
+        }
+    }
+
+    #[test]
+    fn transactions_route_replies() {
+        let (client, server) = Channel::create(ChannelOpts::Normal).unwrap();
+        let mut transactions = Transactions::new(client);
+        let first = transactions.send(b"first", &mut vec![]).unwrap();
+        let second = transactions.send(b"second", &mut vec![]).unwrap();
+        assert!(first != second);
+        assert!(transactions.is_outstanding(first));
+
+        // The replies arrive in the opposite order, but are matched by txid.
+        reply_all(&server, b"reply");
+        let reply = transactions.wait_reply(first, Deadline::INFINITE).unwrap();
+        assert_eq!(message_txid(reply.bytes()), Some(first));
+        assert_eq!(&reply.bytes()[TXID_LEN..], b"reply");
+        assert!(!transactions.is_outstanding(second));
+        assert!(transactions.take_reply(second).is_some());
+        assert_eq!(transactions.wait_reply(second, Deadline::INFINITE).unwrap_err(),
+            Status::ErrNotFound);
+    }
+
+    #[test]
+    fn transactions_poll() {
+        let (client, server) = Channel::create(ChannelOpts::Normal).unwrap();
+        let mut transactions = Transactions::new(client);
+        let mut txids = vec![];
+        assert_eq!(transactions.poll(&mut txids), Ok(0));
+        let txid = transactions.send(b"", &mut vec![]).unwrap();
+        reply_all(&server, b"");
+        assert_eq!(transactions.poll(&mut txids), Ok(1));
+        assert_eq!(txids, vec![txid]);
+
+        // A reply to a transaction which was never sent is rejected, but replies routed before
+        // it are still reported.
+        let txid = transactions.send(b"", &mut vec![]).unwrap();
+        reply_all(&server, b"");
+        server.write(&0u32.to_ne_bytes(), &mut vec![], 0).unwrap();
+        txids.clear();
+        assert_eq!(transactions.poll(&mut txids), Err(Status::ErrNotFound));
+        assert_eq!(txids, vec![txid]);
+        assert!(transactions.take_reply(txid).is_some());
+        server.write(b"ab", &mut vec![], 0).unwrap();
+        assert_eq!(transactions.poll(&mut txids), Err(Status::ErrIoDataIntegrity));
+    }
+
+    #[test]
+    fn transactions_abandon() {
+        let (client, server) = Channel::create(ChannelOpts::Normal).unwrap();
+        let mut transactions = Transactions::new(client);
+        let txid = transactions.send(b"", &mut vec![]).unwrap();
+        assert!(transactions.abandon(txid));
+        assert!(!transactions.is_outstanding(txid));
+        assert!(!transactions.abandon(txid));
+
+        // The late reply is discarded rather than kept for a transaction nobody is waiting on.
+        reply_all(&server, b"late");
+        assert_eq!(transactions.poll(&mut vec![]), Err(Status::ErrNotFound));
+        assert!(transactions.take_reply(txid).is_none());
+        assert_eq!(transactions.wait_reply(txid, Deadline::INFINITE).unwrap_err(),
+            Status::ErrNotFound);
+    }
+
+    #[test]
+    fn transactions_wait_skips_stray_replies() {
+        let (client, server) = Channel::create(ChannelOpts::Normal).unwrap();
+        let mut transactions = Transactions::new(client);
+        let txid = transactions.send(b"", &mut vec![]).unwrap();
+
+        // A reply to a transaction which was never sent, and a malformed message, arrive first.
+        server.write(&0u32.to_ne_bytes(), &mut vec![], 0).unwrap();
+        server.write(b"ab", &mut vec![], 0).unwrap();
+        server.write(&txid.to_ne_bytes(), &mut vec![], 0).unwrap();
+        let reply = transactions.wait_reply(txid, Deadline::INFINITE).unwrap();
+        assert_eq!(message_txid(reply.bytes()), Some(txid));
+        assert_eq!(transactions.poll(&mut vec![]), Ok(0));
+    }
+}
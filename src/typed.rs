@@ -0,0 +1,174 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Handles whose rights are tracked in their type.
+
+use {AsHandleRef, HandleBased, HandleRef, Rights, Status, Vmo};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// A set of rights known at compile time, used as the type parameter of typed handles such as
+/// `TypedVmo`.
+pub trait RightsMarker {
+    /// The rights a handle must have to be given this type.
+    fn rights() -> Rights;
+}
+
+/// Marks rights which include `Rights::READ`.
+pub trait CanRead: RightsMarker {}
+
+/// Marks rights which include `Rights::WRITE`.
+pub trait CanWrite: RightsMarker {}
+
+/// The handle may be read from, but not written to.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ReadOnly {}
+
+impl RightsMarker for ReadOnly {
+    fn rights() -> Rights {
+        Rights::READ
+    }
+}
+impl CanRead for ReadOnly {}
+
+/// The handle may be both read from and written to.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ReadWrite {}
+
+impl RightsMarker for ReadWrite {
+    fn rights() -> Rights {
+        Rights::READ | Rights::WRITE
+    }
+}
+impl CanRead for ReadWrite {}
+impl CanWrite for ReadWrite {}
+
+/// A virtual memory object whose handle is known to have at least the rights given by `R`.
+///
+/// Operations are only available when `R` guarantees the rights they need, so for example
+/// `write` cannot be called on a `TypedVmo<ReadOnly>`. Use `TypedVmo::from_vmo` to check the
+/// rights of an ordinary `Vmo` at runtime.
+#[derive(Eq, PartialEq)]
+pub struct TypedVmo<R: RightsMarker> {
+    vmo: Vmo,
+    rights: PhantomData<R>,
+}
+
+impl<R: RightsMarker> TypedVmo<R> {
+    /// Convert from an ordinary `Vmo`, checking that the handle has the rights required by `R`.
+    /// On failure the original `Vmo` is returned, or any error from looking up its rights.
+    ///
+    /// Wraps the
+    /// [zx_object_get_info](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_get_info.md)
+    /// syscall with `ZX_INFO_HANDLE_BASIC`.
+    pub fn from_vmo(vmo: Vmo) -> Result<TypedVmo<R>, (Vmo, Status)> {
+        match vmo.basic_info() {
+            Ok(ref info) if info.rights.contains(R::rights()) =>
+                Ok(TypedVmo { vmo, rights: PhantomData }),
+            Ok(_) => Err((vmo, Status::ErrAccessDenied)),
+            Err(status) => Err((vmo, status)),
+        }
+    }
+
+    /// Convert into an ordinary `Vmo`, forgetting about its rights.
+    pub fn into_vmo(self) -> Vmo {
+        self.vmo
+    }
+
+    /// Get the size of the virtual memory object.
+    ///
+    /// Wraps the
+    /// [zx_vmo_get_size](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/vmo_get_size.md)
+    /// syscall.
+    pub fn get_size(&self) -> Result<u64, Status> {
+        self.vmo.get_size()
+    }
+
+    /// Create a read-only duplicate of this handle.
+    ///
+    /// Wraps the
+    /// [zx_handle_duplicate](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/handle_duplicate.md)
+    /// syscall.
+    pub fn duplicate_read_only(&self) -> Result<TypedVmo<ReadOnly>, Status> where R: CanRead {
+        let vmo = self.vmo.duplicate_with().retain(ReadOnly::rights() | Rights::DUPLICATE
+            | Rights::TRANSFER | Rights::MAP | Rights::GET_PROPERTY).build()?;
+        Ok(TypedVmo { vmo, rights: PhantomData })
+    }
+}
+
+impl<R: CanRead> TypedVmo<R> {
+    /// Read from the virtual memory object.
+    ///
+    /// Wraps the
+    /// [zx_vmo_read](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/vmo_read.md)
+    /// syscall.
+    pub fn read(&self, data: &mut [u8], offset: u64) -> Result<usize, Status> {
+        self.vmo.read(data, offset)
+    }
+}
+
+impl<R: CanWrite> TypedVmo<R> {
+    /// Write to the virtual memory object.
+    ///
+    /// Wraps the
+    /// [zx_vmo_write](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/vmo_write.md)
+    /// syscall.
+    pub fn write(&self, data: &[u8], offset: u64) -> Result<usize, Status> {
+        self.vmo.write(data, offset)
+    }
+
+    /// Attempt to change the size of the virtual memory object.
+    ///
+    /// Wraps the
+    /// [zx_vmo_set_size](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/vmo_set_size.md)
+    /// syscall.
+    pub fn set_size(&self, size: u64) -> Result<(), Status> {
+        self.vmo.set_size(size)
+    }
+}
+
+impl<R: RightsMarker> AsHandleRef for TypedVmo<R> {
+    fn as_handle_ref(&self) -> HandleRef {
+        self.vmo.as_handle_ref()
+    }
+}
+
+impl<R: RightsMarker> From<TypedVmo<R>> for Vmo {
+    fn from(vmo: TypedVmo<R>) -> Vmo {
+        vmo.vmo
+    }
+}
+
+impl<R: RightsMarker> fmt::Debug for TypedVmo<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TypedVmo<{:?}>({:?})", R::rights(), self.vmo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use VmoOpts;
+
+    #[test]
+    fn typed_vmo_read_write() {
+        let vmo = Vmo::create(16, VmoOpts::Default).unwrap();
+        let vmo: TypedVmo<ReadWrite> = TypedVmo::from_vmo(vmo).unwrap();
+        assert_eq!(vmo.write(b"hello", 0), Ok(5));
+
+        let read_only = vmo.duplicate_read_only().unwrap();
+        let mut buf = [0; 5];
+        assert_eq!(read_only.read(&mut buf, 0), Ok(5));
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[test]
+    fn typed_vmo_checks_rights() {
+        let vmo = Vmo::create(16, VmoOpts::Default).unwrap();
+        let read_only = vmo.duplicate_with().remove(Rights::WRITE).build().unwrap();
+        let (read_only, status) = TypedVmo::<ReadWrite>::from_vmo(read_only).unwrap_err();
+        assert_eq!(status, Status::ErrAccessDenied);
+        assert!(TypedVmo::<ReadOnly>::from_vmo(read_only).is_ok());
+    }
+}
@@ -0,0 +1,153 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Channels which carry messages of a single type, encoded by a pluggable codec.
+
+use {AsHandleRef, Channel, ChannelOpts, Handle, HandleRef, MessageBuf, Status};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// A way of encoding messages to bytes and decoding them again, for use with `TypedChannel`.
+///
+/// Handles are not part of the encoding; they are carried alongside the bytes.
+pub trait Codec {
+    /// The type of the messages.
+    type Message;
+
+    /// Encode a message, appending it to `bytes`.
+    fn encode(message: &Self::Message, bytes: &mut Vec<u8>) -> Result<(), Status>;
+
+    /// Decode a message from the bytes of a received message.
+    fn decode(bytes: &[u8]) -> Result<Self::Message, Status>;
+}
+
+/// A codec whose messages are just the bytes themselves.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BytesCodec {}
+
+impl Codec for BytesCodec {
+    type Message = Vec<u8>;
+
+    fn encode(message: &Vec<u8>, bytes: &mut Vec<u8>) -> Result<(), Status> {
+        bytes.extend_from_slice(message);
+        Ok(())
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Vec<u8>, Status> {
+        Ok(bytes.to_vec())
+    }
+}
+
+/// A channel endpoint which sends and receives messages of type `C::Message`, encoded with the
+/// codec `C`.
+pub struct TypedChannel<C: Codec> {
+    channel: Channel,
+    codec: PhantomData<C>,
+}
+
+impl<C: Codec> TypedChannel<C> {
+    /// Create a pair of connected typed channel endpoints.
+    ///
+    /// Wraps the
+    /// [zx_channel_create](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/channel_create.md)
+    /// syscall.
+    pub fn create(opts: ChannelOpts) -> Result<(TypedChannel<C>, TypedChannel<C>), Status> {
+        let (channel0, channel1) = Channel::create(opts)?;
+        Ok((TypedChannel::from_channel(channel0), TypedChannel::from_channel(channel1)))
+    }
+
+    /// Wrap an untyped channel. The peer is assumed to use the same codec.
+    pub fn from_channel(channel: Channel) -> TypedChannel<C> {
+        TypedChannel { channel, codec: PhantomData }
+    }
+
+    /// Unwrap the underlying untyped channel.
+    pub fn into_channel(self) -> Channel {
+        self.channel
+    }
+
+    /// Encode a message and write it to the channel, together with the given handles.
+    ///
+    /// As with `Channel::write`, the handles are removed from the vector if the message is
+    /// written successfully.
+    pub fn send(&self, message: &C::Message, handles: &mut Vec<Handle>) -> Result<(), Status> {
+        let mut bytes = Vec::new();
+        C::encode(message, &mut bytes)?;
+        self.channel.write(&bytes, handles, 0)
+    }
+
+    /// Read a message from the channel and decode it. Any handles sent with the message are
+    /// left in `buf`, from which they can be taken with `MessageBuf::take_handle`.
+    pub fn recv(&self, buf: &mut MessageBuf) -> Result<C::Message, Status> {
+        self.channel.read(0, buf)?;
+        C::decode(buf.bytes())
+    }
+}
+
+impl<C: Codec> AsHandleRef for TypedChannel<C> {
+    fn as_handle_ref(&self) -> HandleRef {
+        self.channel.as_handle_ref()
+    }
+}
+
+impl<C: Codec> fmt::Debug for TypedChannel<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TypedChannel({:?})", self.channel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Event, EventOpts, HandleBased};
+
+    // Encodes a pair of integers, as an example of a codec with a fixed layout.
+    enum PairCodec {}
+
+    impl Codec for PairCodec {
+        type Message = (u32, u32);
+
+        fn encode(message: &(u32, u32), bytes: &mut Vec<u8>) -> Result<(), Status> {
+            bytes.extend_from_slice(&message.0.to_le_bytes());
+            bytes.extend_from_slice(&message.1.to_le_bytes());
+            Ok(())
+        }
+
+        fn decode(bytes: &[u8]) -> Result<(u32, u32), Status> {
+            if bytes.len() != 8 {
+                return Err(Status::ErrInvalidArgs);
+            }
+            Ok((u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+                u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]])))
+        }
+    }
+
+    #[test]
+    fn typed_channel_send_recv() {
+        let (p1, p2) = TypedChannel::<PairCodec>::create(ChannelOpts::Normal).unwrap();
+        let event = Event::create(EventOpts::Default).unwrap();
+        let mut handles = vec![event.into_handle()];
+        assert!(p1.send(&(3, 4), &mut handles).is_ok());
+        assert!(handles.is_empty());
+
+        let mut buf = MessageBuf::new();
+        assert_eq!(p2.recv(&mut buf), Ok((3, 4)));
+        assert_eq!(buf.n_handles(), 1);
+    }
+
+    #[test]
+    fn typed_channel_decode_error() {
+        let (p1, p2) = Channel::create(ChannelOpts::Normal).unwrap();
+        let p2 = TypedChannel::<PairCodec>::from_channel(p2);
+        let mut empty = vec![];
+        assert!(p1.write(b"short", &mut empty, 0).is_ok());
+        let mut buf = MessageBuf::new();
+        assert_eq!(p2.recv(&mut buf), Err(Status::ErrInvalidArgs));
+
+        let p1 = TypedChannel::<BytesCodec>::from_channel(p1);
+        let p2 = TypedChannel::<BytesCodec>::from_channel(p2.into_channel());
+        assert!(p1.send(&b"bytes".to_vec(), &mut empty).is_ok());
+        assert_eq!(p2.recv(&mut buf), Ok(b"bytes".to_vec()));
+    }
+}
@@ -0,0 +1,215 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Fifos which carry elements of a single plain-old-data type.
+
+use {AsHandleRef, Deadline, Fifo, FifoOpts, HandleRef, Status};
+use std::{fmt, mem, slice};
+use std::marker::PhantomData;
+
+/// A type which can be sent through a fifo as an element, by copying its bytes.
+///
+/// # Safety
+///
+/// Elements are read by having the kernel write bytes directly into a buffer of them, so the
+/// type must have no padding, so that writing one does not expose uninitialized memory, and
+/// every bit pattern must be a valid value.
+pub unsafe trait FifoEntry: Copy {}
+
+unsafe impl FifoEntry for u8 {}
+unsafe impl FifoEntry for u16 {}
+unsafe impl FifoEntry for u32 {}
+unsafe impl FifoEntry for u64 {}
+unsafe impl FifoEntry for i8 {}
+unsafe impl FifoEntry for i16 {}
+unsafe impl FifoEntry for i32 {}
+unsafe impl FifoEntry for i64 {}
+
+/// A fifo endpoint whose elements are values of type `T`.
+pub struct TypedFifo<T: FifoEntry> {
+    fifo: Fifo,
+    elem: PhantomData<T>,
+}
+
+impl<T: FifoEntry> TypedFifo<T> {
+    /// Create a pair of fifos holding up to `elem_count` elements of type `T`. Fails with
+    /// `ErrInvalidArgs` if `T` is zero-sized, or too large to be an element.
+    ///
+    /// Wraps the
+    /// [zx_fifo_create](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/fifo_create.md)
+    /// syscall.
+    pub fn create(elem_count: u32, opts: FifoOpts)
+        -> Result<(TypedFifo<T>, TypedFifo<T>), Status>
+    {
+        let elem_size = mem::size_of::<T>();
+        if elem_size == 0 || elem_size > u32::MAX as usize {
+            return Err(Status::ErrInvalidArgs);
+        }
+        let (fifo0, fifo1) = Fifo::create(elem_count, elem_size as u32, opts)?;
+        unsafe { Ok((TypedFifo::from_fifo(fifo0), TypedFifo::from_fifo(fifo1))) }
+    }
+
+    /// Wrap an untyped fifo.
+    ///
+    /// # Safety
+    ///
+    /// The fifo's element size must be the size of `T`. The kernel does not report the element
+    /// size of a fifo, so this cannot be checked, and with any other size reads could leave
+    /// partly written elements, or count more elements than fit in the buffer.
+    pub unsafe fn from_fifo(fifo: Fifo) -> TypedFifo<T> {
+        TypedFifo { fifo, elem: PhantomData }
+    }
+
+    /// Unwrap the underlying untyped fifo.
+    pub fn into_fifo(self) -> Fifo {
+        self.fifo
+    }
+
+    /// Write as many of `elems` as there is room for, returning the number written. See
+    /// `Fifo::write`.
+    pub fn write(&self, elems: &[T]) -> Result<u32, Status> {
+        self.fifo.write(as_bytes(elems))
+    }
+
+    /// Read as many elements as are available into the start of `elems`, returning the number
+    /// read. See `Fifo::read`.
+    pub fn read(&self, elems: &mut [T]) -> Result<u32, Status> {
+        self.fifo.read(as_bytes_mut(elems))
+    }
+
+    /// Write as many of `elems` as there is room for, waiting for the fifo to become writable
+    /// if necessary, and return the number written. See `Fifo::write_deadline`.
+    pub fn write_deadline(&self, elems: &[T], deadline: Deadline) -> Result<u32, Status> {
+        self.fifo.write_deadline(as_bytes(elems), deadline)
+    }
+
+    /// Read as many elements as are available into the start of `elems`, waiting for the fifo
+    /// to become readable if necessary, and return the number read. See `Fifo::read_deadline`.
+    pub fn read_deadline(&self, elems: &mut [T], deadline: Deadline) -> Result<u32, Status> {
+        self.fifo.read_deadline(as_bytes_mut(elems), deadline)
+    }
+
+    /// Write all of `elems`, waiting for room as necessary. Fails with `ErrTimedOut` if they
+    /// have not all been written before the deadline, or `ErrPeerClosed` if the peer is closed;
+    /// some of the elements may have been written in either case.
+    pub fn write_all_deadline(&self, mut elems: &[T], deadline: Deadline) -> Result<(), Status> {
+        while !elems.is_empty() {
+            let actual = self.write_deadline(elems, deadline)?;
+            elems = &elems[actual as usize..];
+        }
+        Ok(())
+    }
+
+    /// Read exactly enough elements to fill `elems`, waiting for them as necessary. Fails with
+    /// `ErrTimedOut` if it has not been filled before the deadline, or `ErrPeerClosed` if the
+    /// peer is closed first; in either case the elements which were read are lost.
+    pub fn read_exact_deadline(&self, mut elems: &mut [T], deadline: Deadline)
+        -> Result<(), Status>
+    {
+        while !elems.is_empty() {
+            let actual = self.read_deadline(elems, deadline)?;
+            elems = &mut elems[actual as usize..];
+        }
+        Ok(())
+    }
+}
+
+// The bytes of a slice of elements.
+fn as_bytes<T: FifoEntry>(elems: &[T]) -> &[u8] {
+    unsafe { slice::from_raw_parts(elems.as_ptr() as *const u8, mem::size_of_val(elems)) }
+}
+
+// The bytes of a slice of elements, for reading into. Any bytes the kernel writes are a valid
+// `T`, as `T: FifoEntry`.
+fn as_bytes_mut<T: FifoEntry>(elems: &mut [T]) -> &mut [u8] {
+    let len = mem::size_of_val(elems);
+    unsafe { slice::from_raw_parts_mut(elems.as_mut_ptr() as *mut u8, len) }
+}
+
+impl<T: FifoEntry> AsHandleRef for TypedFifo<T> {
+    fn as_handle_ref(&self) -> HandleRef {
+        self.fifo.as_handle_ref()
+    }
+}
+
+impl<T: FifoEntry> fmt::Debug for TypedFifo<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TypedFifo({:?})", self.fifo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Duration;
+    use std::thread;
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+    struct Request {
+        opcode: u32,
+        length: u32,
+        offset: u64,
+    }
+
+    unsafe impl FifoEntry for Request {}
+    unsafe impl FifoEntry for () {}
+
+    #[test]
+    fn typed_fifo() {
+        let (fifo1, fifo2) = TypedFifo::<Request>::create(2, FifoOpts::Default).unwrap();
+        let requests = [
+            Request { opcode: 1, length: 512, offset: 0 },
+            Request { opcode: 2, length: 1024, offset: 4096 },
+            Request { opcode: 3, length: 0, offset: 0 },
+        ];
+        assert_eq!(fifo1.write(&requests), Ok(2));
+
+        let mut received = [Request::default(); 3];
+        assert_eq!(fifo2.read(&mut received), Ok(2));
+        assert_eq!(&received[..2], &requests[..2]);
+    }
+
+    #[test]
+    fn typed_fifo_deadline() {
+        let (fifo1, fifo2) = TypedFifo::<u64>::create(1, FifoOpts::Default).unwrap();
+        let ten_ms = Deadline::after(Duration::from_millis(10));
+        assert_eq!(fifo1.write_deadline(&[1, 2], Deadline::INFINITE), Ok(1));
+        assert_eq!(fifo1.write_deadline(&[3], ten_ms), Err(Status::ErrTimedOut));
+        let mut received = [0; 2];
+        assert_eq!(fifo2.read_deadline(&mut received, Deadline::INFINITE), Ok(1));
+        assert_eq!(received[0], 1);
+    }
+
+    #[test]
+    fn typed_fifo_all_and_exact() {
+        let (fifo1, fifo2) = TypedFifo::<u32>::create(2, FifoOpts::Default).unwrap();
+        let reader = thread::spawn(move || {
+            let mut received = [0; 5];
+            fifo2.read_exact_deadline(&mut received, Deadline::INFINITE).map(|()| received)
+        });
+
+        // Five elements do not fit in the fifo at once, so this takes several writes.
+        assert_eq!(fifo1.write_all_deadline(&[1, 2, 3, 4, 5], Deadline::INFINITE), Ok(()));
+        assert_eq!(reader.join().unwrap(), Ok([1, 2, 3, 4, 5]));
+        assert_eq!(fifo1.write_all_deadline(&[6], Deadline::INFINITE),
+            Err(Status::ErrPeerClosed));
+    }
+
+    #[test]
+    fn zero_sized() {
+        assert_eq!(TypedFifo::<()>::create(2, FifoOpts::Default).unwrap_err(),
+            Status::ErrInvalidArgs);
+    }
+
+    #[test]
+    fn untyped_round_trip() {
+        let (fifo1, fifo2) = TypedFifo::<u32>::create(4, FifoOpts::Default).unwrap();
+        let fifo1 = unsafe { TypedFifo::<u32>::from_fifo(fifo1.into_fifo()) };
+        assert_eq!(fifo1.write(&[7, 8]), Ok(2));
+        let mut bytes = [0; 8];
+        assert_eq!(fifo2.into_fifo().read(&mut bytes), Ok(2));
+        assert_eq!(bytes, [7, 0, 0, 0, 8, 0, 0, 0]);
+    }
+}
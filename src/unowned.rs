@@ -0,0 +1,68 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! A handle wrapper which does not own its handle.
+
+use {AsHandleRef, Handle, HandleBased, HandleRef};
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ops::Deref;
+
+/// A handle which is borrowed rather than owned, such as one of the handles the runtime gives
+/// the current process, and so is not closed when dropped.
+///
+/// Duplicate it with `HandleBased::duplicate_handle` to get a handle which can be owned.
+pub struct Unowned<'a, T: HandleBased> {
+    inner: ManuallyDrop<T>,
+    marker: PhantomData<&'a T>,
+}
+
+impl<'a, T: HandleBased> Unowned<'a, T> {
+    /// Wrap a raw handle without taking ownership of it.
+    ///
+    /// # Safety
+    ///
+    /// The handle must stay open for the lifetime `'a`, and must not be closed through the
+    /// returned value.
+    pub unsafe fn from_raw_handle(raw: ::sys::zx_handle_t) -> Self {
+        // Not created with `Handle::owned`, as the handle is not owned.
+        Unowned { inner: ManuallyDrop::new(T::from(Handle(raw))), marker: PhantomData }
+    }
+}
+
+impl<'a, T: HandleBased> Deref for Unowned<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<'a, T: HandleBased> AsHandleRef for Unowned<'a, T> {
+    fn as_handle_ref(&self) -> HandleRef {
+        self.inner.as_handle_ref()
+    }
+}
+
+impl<'a, T: HandleBased + fmt::Debug> fmt::Debug for Unowned<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Event, EventOpts};
+
+    #[test]
+    fn drop_does_not_close() {
+        let event = Event::create(EventOpts::Default).unwrap();
+        {
+            let unowned = unsafe { Unowned::<Event>::from_raw_handle(event.raw_handle()) };
+            assert_eq!(unowned.raw_handle(), event.raw_handle());
+        }
+        assert!(event.basic_info().is_ok());
+    }
+}
@@ -0,0 +1,411 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Type-safe bindings for Zircon virtual memory address regions.
+
+use {AsHandleRef, HandleBased, Handle, HandleRef, ObjectType, Status, VmarFlags, Vmo};
+use {VmarInfo, VmarQuery, is_page_aligned, object_get_info_single, page_size};
+use {round_up_to_page, sys, into_result};
+use std::{mem, ptr};
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+/// An object representing a Zircon
+/// [virtual memory address region](https://fuchsia.googlesource.com/zircon/+/master/docs/objects/vm_address_region.md),
+/// a contiguous range of an address space in which virtual memory objects can be mapped and
+/// child regions allocated.
+///
+/// As essentially a subtype of `Handle`, it can be freely interconverted.
+#[derive(Eq, PartialEq)]
+pub struct Vmar(Handle);
+impl_handle_based!(Vmar, ObjectType::Vmar);
+
+impl Vmar {
+    /// The root region of the current process's address space, as provided by the vDSO.
+    ///
+    /// The handle is borrowed from the process and is never closed; duplicate it to get a handle
+    /// which can be owned.
+    pub fn root_self() -> &'static Vmar {
+        static ROOT_VMAR: AtomicPtr<Vmar> = AtomicPtr::new(ptr::null_mut());
+        let root = ROOT_VMAR.load(Ordering::Acquire);
+        if !root.is_null() {
+            return unsafe { &*root };
+        }
+        // Not created with `Handle::owned`, as this crate does not own the handle. The `Vmar` is
+        // leaked, so that it is never dropped and the handle never closed.
+        let new = Box::into_raw(Box::new(Vmar(Handle(unsafe { sys::zx_vmar_root_self() }))));
+        match ROOT_VMAR.compare_exchange(ptr::null_mut(), new, Ordering::AcqRel,
+            Ordering::Acquire)
+        {
+            Ok(_) => unsafe { &*new },
+            Err(existing) => {
+                // Another thread got there first; discard ours without closing the handle.
+                mem::forget(*unsafe { Box::from_raw(new) });
+                unsafe { &*existing }
+            }
+        }
+    }
+
+    /// Allocate a child region of `size` bytes, returning it together with its address. The
+    /// offset is only used with `VmarFlags::SPECIFIC`; the `CAN_MAP_*` flags give the mappings
+    /// the child allows.
+    ///
+    /// Wraps the
+    /// [zx_vmar_allocate](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/vmar_allocate.md)
+    /// syscall.
+    pub fn allocate(&self, offset: usize, size: usize, flags: VmarFlags)
+        -> Result<(Vmar, usize), Status>
+    {
+        flags.check_allocate()?;
+        let mut handle = 0;
+        let mut addr = 0;
+        let status = unsafe {
+            sys::zx_vmar_allocate(self.raw_handle(), offset, size, flags.bits(), &mut handle,
+                &mut addr)
+        };
+        into_result(status, || (Vmar::from(Handle::owned(handle)), addr))
+    }
+
+    /// The range of addresses the region covers. Offsets passed to `map` and `allocate` are
+    /// relative to its base.
+    ///
+    /// Wraps the `ZX_INFO_VMAR` topic of
+    /// [zx_object_get_info](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_get_info.md).
+    pub fn info(&self) -> Result<VmarInfo, Status> {
+        object_get_info_single::<VmarQuery>(self.as_handle_ref()).map(VmarInfo::from)
+    }
+
+    /// Start building a child region of `size` bytes, for when more control over its placement
+    /// is needed than `allocate` gives, such as alignment.
+    pub fn allocate_with(&self, size: usize) -> AllocateOptions<'_> {
+        AllocateOptions { parent: self, size, align: 0, offset: None, flags: VmarFlags::NONE }
+    }
+
+    /// Map `len` bytes of a virtual memory object, starting at `vmo_offset`, into the region,
+    /// returning the address of the mapping. The offset into the region is only used with
+    /// `VmarFlags::SPECIFIC`; the `PERM_*` flags give the permissions of the mapping.
+    ///
+    /// Fails with `ErrInvalidArgs` if `flags` includes flags which only apply to `allocate` or
+    /// `VmarFlags::SPECIFIC_OVERWRITE` (see `map_overwrite`), if `len` is zero, or if
+    /// `vmo_offset` (or `vmar_offset`, with `VmarFlags::SPECIFIC`) is not page-aligned, or with
+    /// `ErrAccessDenied` if the permissions are not all in `allowed_perms`.
+    ///
+    /// Wraps the
+    /// [zx_vmar_map](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/vmar_map.md)
+    /// syscall.
+    pub fn map(&self, vmar_offset: usize, vmo: &Vmo, vmo_offset: u64, len: usize,
+        flags: VmarFlags) -> Result<usize, Status>
+    {
+        flags.check_map()?;
+        self.map_checked(vmar_offset, vmo, vmo_offset, len, flags)
+    }
+
+    /// Map `len` bytes of a virtual memory object, starting at `vmo_offset`, at exactly
+    /// `vmar_offset` in the region, replacing any existing mappings there, and return the
+    /// address of the mapping. `flags` gives the `PERM_*` flags of the mapping, and is
+    /// otherwise checked as for `map`.
+    ///
+    /// Wraps the
+    /// [zx_vmar_map](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/vmar_map.md)
+    /// syscall with `VmarFlags::SPECIFIC_OVERWRITE`.
+    ///
+    /// # Safety
+    ///
+    /// As with `unmap`, nothing may access the memory which was previously mapped in the range
+    /// afterwards, which in particular means there must be no Rust references into it.
+    pub unsafe fn map_overwrite(&self, vmar_offset: usize, vmo: &Vmo, vmo_offset: u64,
+        len: usize, flags: VmarFlags) -> Result<usize, Status>
+    {
+        flags.check_map()?;
+        let flags = (flags - VmarFlags::SPECIFIC) | VmarFlags::SPECIFIC_OVERWRITE;
+        self.map_checked(vmar_offset, vmo, vmo_offset, len, flags)
+    }
+
+    // Map, once the flags have been checked.
+    fn map_checked(&self, vmar_offset: usize, vmo: &Vmo, vmo_offset: u64, len: usize,
+        flags: VmarFlags) -> Result<usize, Status>
+    {
+        let specific = flags.intersects(VmarFlags::SPECIFIC | VmarFlags::SPECIFIC_OVERWRITE);
+        if len == 0 || vmo_offset & (page_size() as u64 - 1) != 0
+            || (specific && !is_page_aligned(vmar_offset))
+        {
+            return Err(Status::ErrInvalidArgs);
+        }
+        if !self.allowed_perms()?.contains(flags.perms()) {
+            return Err(Status::ErrAccessDenied);
+        }
+        let mut addr = 0;
+        let status = unsafe {
+            sys::zx_vmar_map(self.raw_handle(), vmar_offset, vmo.raw_handle(), vmo_offset, len,
+                flags.bits(), &mut addr)
+        };
+        into_result(status, || addr)
+    }
+
+    /// The permissions, as `PERM_*` flags, which mappings in the region may have. These follow
+    /// from the `CAN_MAP_*` flags the region was allocated with, which are reflected in the
+    /// rights of its handle.
+    pub fn allowed_perms(&self) -> Result<VmarFlags, Status> {
+        self.basic_info().map(|info| VmarFlags::perms_for_rights(info.rights))
+    }
+
+    /// Unmap the pages covering the `len` bytes at `addr`, which must lie within the region.
+    /// Fails with `ErrInvalidArgs` if `addr` is not page-aligned.
+    ///
+    /// Wraps the
+    /// [zx_vmar_unmap](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/vmar_unmap.md)
+    /// syscall.
+    ///
+    /// # Safety
+    ///
+    /// Nothing may access the unmapped memory afterwards, which in particular means there must
+    /// be no Rust references into it.
+    pub unsafe fn unmap(&self, addr: usize, len: usize) -> Result<(), Status> {
+        let len = check_page_range(addr, len)?;
+        let status = sys::zx_vmar_unmap(self.raw_handle(), addr, len);
+        into_result(status, || ())
+    }
+
+    /// Change the permissions of the pages covering the `len` bytes at `addr` to the `PERM_*`
+    /// flags. Fails with `ErrInvalidArgs` if `addr` is not page-aligned.
+    ///
+    /// Wraps the
+    /// [zx_vmar_protect](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/vmar_protect.md)
+    /// syscall.
+    ///
+    /// # Safety
+    ///
+    /// Nothing may access the memory in a way the new permissions do not allow, which in
+    /// particular means there must be no Rust references to memory which is no longer readable,
+    /// and no mutable references to memory which is no longer writable.
+    pub unsafe fn protect(&self, addr: usize, len: usize, flags: VmarFlags)
+        -> Result<(), Status>
+    {
+        let len = check_page_range(addr, len)?;
+        let status = sys::zx_vmar_protect(self.raw_handle(), addr, len, flags.bits());
+        into_result(status, || ())
+    }
+
+    /// Unmap everything in the region and destroy it and all of its children, so that nothing
+    /// more can be mapped in it. The handle stays open.
+    ///
+    /// Wraps the
+    /// [zx_vmar_destroy](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/vmar_destroy.md)
+    /// syscall.
+    ///
+    /// # Safety
+    ///
+    /// As with `unmap`, nothing may access the memory which was mapped in the region afterwards.
+    pub unsafe fn destroy(&self) -> Result<(), Status> {
+        let status = sys::zx_vmar_destroy(self.raw_handle());
+        into_result(status, || ())
+    }
+}
+
+// Check that a range of addresses starts on a page boundary, and round its length up to cover
+// whole pages.
+fn check_page_range(addr: usize, len: usize) -> Result<usize, Status> {
+    if !is_page_aligned(addr) {
+        return Err(Status::ErrInvalidArgs);
+    }
+    round_up_to_page(len).ok_or(Status::ErrOutOfRange)
+}
+
+/// A builder for allocating a child region, created by `Vmar::allocate_with`.
+///
+/// For example
+///
+/// ```no_run
+/// # use zircon::{Vmar, VmarFlags};
+/// let (region, addr) = Vmar::root_self().allocate_with(1 << 20)
+///     .align(1 << 16)
+///     .can_map(VmarFlags::CAN_MAP_READ | VmarFlags::CAN_MAP_WRITE)
+///     .build()
+///     .unwrap();
+/// ```
+///
+/// allocates a 1MiB region on a 64KiB boundary, in which readable and writable mappings can be
+/// made.
+#[derive(Debug)]
+pub struct AllocateOptions<'a> {
+    parent: &'a Vmar,
+    size: usize,
+    align: usize,
+    offset: Option<usize>,
+    flags: VmarFlags,
+}
+
+impl<'a> AllocateOptions<'a> {
+    /// Place the region at an address which is a multiple of `align`, which must be a power of
+    /// two. Regions are always page-aligned, so smaller alignments have no effect.
+    ///
+    /// The kernel cannot align regions itself, so a larger region is reserved to carve the
+    /// aligned one from, and up to `align` bytes of address space around it are left unused.
+    pub fn align(mut self, align: usize) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Place the region at exactly `offset` bytes into the parent, which must allow this with
+    /// `VmarFlags::CAN_MAP_SPECIFIC`.
+    pub fn at_offset(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Allow the given `CAN_MAP_*` mappings in the region, in addition to any allowed already.
+    pub fn can_map(mut self, flags: VmarFlags) -> Self {
+        self.flags |= flags;
+        self
+    }
+
+    /// Place allocations in the region close together.
+    pub fn compact(mut self) -> Self {
+        self.flags |= VmarFlags::COMPACT;
+        self
+    }
+
+    /// Allocate the region, returning it together with its address.
+    ///
+    /// Fails with `ErrInvalidArgs` if the alignment is not a power of two, or the offset is
+    /// not aligned.
+    pub fn build(self) -> Result<(Vmar, usize), Status> {
+        self.flags.check_allocate()?;
+        if self.align != 0 && !self.align.is_power_of_two() {
+            return Err(Status::ErrInvalidArgs);
+        }
+        match self.offset {
+            Some(offset) if self.align > 1 && offset % self.align != 0 =>
+                Err(Status::ErrInvalidArgs),
+            Some(offset) =>
+                self.parent.allocate(offset, self.size, self.flags | VmarFlags::SPECIFIC),
+            None if self.align <= page_size() => self.parent.allocate(0, self.size, self.flags),
+            None => {
+                // Reserve enough space to be sure of finding an aligned address, then allocate
+                // the region inside it at that address.
+                let reserve_size = self.size.checked_add(self.align - page_size())
+                    .ok_or(Status::ErrOutOfRange)?;
+                let (reserve, base) = self.parent.allocate(0, reserve_size,
+                    self.flags | VmarFlags::CAN_MAP_SPECIFIC)?;
+                let offset = base.wrapping_neg() & (self.align - 1);
+                reserve.allocate(offset, self.size, self.flags | VmarFlags::SPECIFIC)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use VmoOpts;
+    use std::slice;
+
+    // Allocate a region from the root region of the process.
+    fn allocate_region(size: usize, flags: VmarFlags) -> (Vmar, usize) {
+        Vmar::root_self().allocate(0, size, flags).unwrap()
+    }
+
+    #[test]
+    fn vmar_map_unmap() {
+        let (vmar, base) = allocate_region(16384, VmarFlags::CAN_MAP_READ
+            | VmarFlags::CAN_MAP_WRITE | VmarFlags::CAN_MAP_SPECIFIC);
+        let vmo = Vmo::create(4096, VmoOpts::Default).unwrap();
+        assert_eq!(vmo.write_all(b"hello", 0), Ok(()));
+
+        let addr = vmar.map(8192, &vmo, 0, 4096, VmarFlags::PERM_READ | VmarFlags::SPECIFIC)
+            .unwrap();
+        assert_eq!(addr, base + 8192);
+        assert_eq!(unsafe { slice::from_raw_parts(addr as *const u8, 5) }, b"hello");
+
+        // The region does not allow executable mappings, and flags for allocating are rejected.
+        assert_eq!(vmar.allowed_perms(), Ok(VmarFlags::PERM_READ | VmarFlags::PERM_WRITE));
+        assert_eq!(vmar.map(0, &vmo, 0, 4096, VmarFlags::PERM_READ | VmarFlags::COMPACT),
+            Err(Status::ErrInvalidArgs));
+        assert_eq!(vmar.map(0, &vmo, 0, 4096, VmarFlags::PERM_EXECUTE),
+            Err(Status::ErrAccessDenied));
+        assert_eq!(vmar.map(0, &vmo, 1, 4096, VmarFlags::PERM_READ), Err(Status::ErrInvalidArgs));
+        assert_eq!(vmar.map(8192, &vmo, 0, 4096,
+            VmarFlags::PERM_READ | VmarFlags::SPECIFIC_OVERWRITE), Err(Status::ErrInvalidArgs));
+        unsafe {
+            assert_eq!(vmar.protect(addr, 4096, VmarFlags::PERM_READ | VmarFlags::PERM_WRITE),
+                Ok(()));
+            assert_eq!(vmar.unmap(addr + 1, 4096), Err(Status::ErrInvalidArgs));
+            // The length is rounded up to whole pages.
+            assert_eq!(vmar.unmap(addr, 1), Ok(()));
+        }
+    }
+
+    #[test]
+    fn vmar_map_overwrite() {
+        let (vmar, base) = allocate_region(8192, VmarFlags::CAN_MAP_READ
+            | VmarFlags::CAN_MAP_SPECIFIC);
+        let first = Vmo::create(4096, VmoOpts::Default).unwrap();
+        let second = Vmo::create(4096, VmoOpts::Default).unwrap();
+        assert_eq!(first.write_all(b"first", 0), Ok(()));
+        assert_eq!(second.write_all(b"second", 0), Ok(()));
+
+        let addr = vmar.map(4096, &first, 0, 4096, VmarFlags::PERM_READ | VmarFlags::SPECIFIC)
+            .unwrap();
+        assert_eq!(addr, base + 4096);
+        // Without overwriting, the occupied range cannot be mapped again.
+        assert!(vmar.map(4096, &second, 0, 4096, VmarFlags::PERM_READ | VmarFlags::SPECIFIC)
+            .is_err());
+        unsafe {
+            assert_eq!(vmar.map_overwrite(4096, &second, 0, 4096, VmarFlags::PERM_READ),
+                Ok(addr));
+            assert_eq!(slice::from_raw_parts(addr as *const u8, 6), b"second");
+        }
+    }
+
+    #[test]
+    fn vmar_root_self() {
+        let root = Vmar::root_self();
+        assert_eq!(root.raw_handle(), unsafe { sys::zx_vmar_root_self() });
+        assert_eq!(root.object_type(), Ok(ObjectType::Vmar));
+        assert!(::std::ptr::eq(root, Vmar::root_self()));
+    }
+
+    #[test]
+    fn vmar_info() {
+        let (vmar, base) = allocate_region(16384, VmarFlags::CAN_MAP_SPECIFIC);
+        assert_eq!(vmar.info(), Ok(VmarInfo { base, len: 16384 }));
+        let root = Vmar::root_self().info().unwrap();
+        assert!(root.base <= base && base + 16384 <= root.base + root.len);
+    }
+
+    #[test]
+    fn vmar_allocate_with() {
+        let align = 1 << 20;
+        let (vmar, addr) = Vmar::root_self().allocate_with(8192)
+            .align(align)
+            .can_map(VmarFlags::CAN_MAP_READ)
+            .build()
+            .unwrap();
+        assert_eq!(addr % align, 0);
+        assert_eq!(vmar.allowed_perms(), Ok(VmarFlags::PERM_READ));
+
+        let (parent, base) = allocate_region(16384, VmarFlags::CAN_MAP_SPECIFIC);
+        let (_, addr) = parent.allocate_with(4096).at_offset(8192).build().unwrap();
+        assert_eq!(addr, base + 8192);
+        assert_eq!(parent.allocate_with(4096).align(3).build().unwrap_err(),
+            Status::ErrInvalidArgs);
+        assert_eq!(parent.allocate_with(4096).align(8192).at_offset(4096).build().unwrap_err(),
+            Status::ErrInvalidArgs);
+    }
+
+    #[test]
+    fn vmar_allocate_destroy() {
+        let (vmar, base) = allocate_region(16384, VmarFlags::CAN_MAP_READ
+            | VmarFlags::CAN_MAP_SPECIFIC);
+        let (child, addr) = vmar.allocate(4096, 8192, VmarFlags::CAN_MAP_READ
+            | VmarFlags::SPECIFIC).unwrap();
+        assert_eq!(addr, base + 4096);
+
+        let vmo = Vmo::create(4096, VmoOpts::Default).unwrap();
+        assert!(child.map(0, &vmo, 0, 4096, VmarFlags::PERM_READ).is_ok());
+        unsafe {
+            assert_eq!(vmar.destroy(), Ok(()));
+        }
+        assert_eq!(child.map(0, &vmo, 0, 4096, VmarFlags::PERM_READ), Err(Status::ErrBadState));
+    }
+}
@@ -0,0 +1,173 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Type-safe flags for mapping into and allocating virtual memory address regions.
+
+use {sys, Rights, Status};
+use std::fmt;
+
+/// Flags for `Vmar::map` and `Vmar::allocate`, giving the permissions of a mapping or the
+/// mappings a region allows, and where it should be placed.
+///
+/// Flags can be combined with the `|` operator, for example
+/// `VmarFlags::PERM_READ | VmarFlags::PERM_WRITE`.
+#[repr(C)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct VmarFlags(sys::zx_vmar_flags_t);
+
+const NAMES: &[(VmarFlags, &str)] = &[
+    (VmarFlags::PERM_READ, "PERM_READ"),
+    (VmarFlags::PERM_WRITE, "PERM_WRITE"),
+    (VmarFlags::PERM_EXECUTE, "PERM_EXECUTE"),
+    (VmarFlags::COMPACT, "COMPACT"),
+    (VmarFlags::SPECIFIC, "SPECIFIC"),
+    (VmarFlags::SPECIFIC_OVERWRITE, "SPECIFIC_OVERWRITE"),
+    (VmarFlags::CAN_MAP_SPECIFIC, "CAN_MAP_SPECIFIC"),
+    (VmarFlags::CAN_MAP_READ, "CAN_MAP_READ"),
+    (VmarFlags::CAN_MAP_WRITE, "CAN_MAP_WRITE"),
+    (VmarFlags::CAN_MAP_EXECUTE, "CAN_MAP_EXECUTE"),
+];
+
+impl VmarFlags {
+    /// No flags.
+    pub const NONE: VmarFlags = VmarFlags(sys::ZX_VM_FLAG_NONE);
+    /// The mapping may be read.
+    pub const PERM_READ: VmarFlags = VmarFlags(sys::ZX_VM_FLAG_PERM_READ);
+    /// The mapping may be written.
+    pub const PERM_WRITE: VmarFlags = VmarFlags(sys::ZX_VM_FLAG_PERM_WRITE);
+    /// The mapping may be executed.
+    pub const PERM_EXECUTE: VmarFlags = VmarFlags(sys::ZX_VM_FLAG_PERM_EXECUTE);
+    /// Place allocations in the region close together, rather than spreading them out.
+    pub const COMPACT: VmarFlags = VmarFlags(sys::ZX_VM_FLAG_COMPACT);
+    /// Place the mapping or region at exactly the given offset.
+    pub const SPECIFIC: VmarFlags = VmarFlags(sys::ZX_VM_FLAG_SPECIFIC);
+    /// Like `SPECIFIC`, but replace any existing mappings in the range.
+    pub const SPECIFIC_OVERWRITE: VmarFlags = VmarFlags(sys::ZX_VM_FLAG_SPECIFIC_OVERWRITE);
+    /// The region allows mappings and regions to be placed at specific offsets.
+    pub const CAN_MAP_SPECIFIC: VmarFlags = VmarFlags(sys::ZX_VM_FLAG_CAN_MAP_SPECIFIC);
+    /// The region allows readable mappings.
+    pub const CAN_MAP_READ: VmarFlags = VmarFlags(sys::ZX_VM_FLAG_CAN_MAP_READ);
+    /// The region allows writable mappings.
+    pub const CAN_MAP_WRITE: VmarFlags = VmarFlags(sys::ZX_VM_FLAG_CAN_MAP_WRITE);
+    /// The region allows executable mappings.
+    pub const CAN_MAP_EXECUTE: VmarFlags = VmarFlags(sys::ZX_VM_FLAG_CAN_MAP_EXECUTE);
+}
+
+impl VmarFlags {
+    /// Just the `PERM_*` flags.
+    pub fn perms(&self) -> VmarFlags {
+        *self & (VmarFlags::PERM_READ | VmarFlags::PERM_WRITE | VmarFlags::PERM_EXECUTE)
+    }
+
+    /// The `PERM_*` flags corresponding to the `CAN_MAP_*` flags, that is, the permissions of
+    /// the mappings allowed by a region allocated with these flags.
+    pub fn allowed_perms(&self) -> VmarFlags {
+        let mut perms = VmarFlags::NONE;
+        if self.contains(VmarFlags::CAN_MAP_READ) {
+            perms |= VmarFlags::PERM_READ;
+        }
+        if self.contains(VmarFlags::CAN_MAP_WRITE) {
+            perms |= VmarFlags::PERM_WRITE;
+        }
+        if self.contains(VmarFlags::CAN_MAP_EXECUTE) {
+            perms |= VmarFlags::PERM_EXECUTE;
+        }
+        perms
+    }
+
+    /// The `PERM_*` flags allowed by a handle to a region with the given rights.
+    pub fn perms_for_rights(rights: Rights) -> VmarFlags {
+        let mut perms = VmarFlags::NONE;
+        if rights.contains(Rights::READ) {
+            perms |= VmarFlags::PERM_READ;
+        }
+        if rights.contains(Rights::WRITE) {
+            perms |= VmarFlags::PERM_WRITE;
+        }
+        if rights.contains(Rights::EXECUTE) {
+            perms |= VmarFlags::PERM_EXECUTE;
+        }
+        perms
+    }
+
+    /// Check that the flags are meaningful for `Vmar::map`, failing with `ErrInvalidArgs` if
+    /// not. `SPECIFIC_OVERWRITE` is rejected, as replacing existing mappings is only allowed
+    /// through the unsafe `Vmar::map_overwrite`.
+    pub fn check_map(&self) -> Result<(), Status> {
+        let valid = VmarFlags::PERM_READ | VmarFlags::PERM_WRITE | VmarFlags::PERM_EXECUTE
+            | VmarFlags::SPECIFIC;
+        if valid.contains(*self) { Ok(()) } else { Err(Status::ErrInvalidArgs) }
+    }
+
+    /// Check that the flags are meaningful for `Vmar::allocate`, failing with `ErrInvalidArgs`
+    /// if not.
+    pub fn check_allocate(&self) -> Result<(), Status> {
+        let valid = VmarFlags::CAN_MAP_READ | VmarFlags::CAN_MAP_WRITE
+            | VmarFlags::CAN_MAP_EXECUTE | VmarFlags::CAN_MAP_SPECIFIC | VmarFlags::COMPACT
+            | VmarFlags::SPECIFIC;
+        if valid.contains(*self) { Ok(()) } else { Err(Status::ErrInvalidArgs) }
+    }
+}
+
+impl Default for VmarFlags {
+    fn default() -> Self {
+        VmarFlags::NONE
+    }
+}
+
+impl_flags!(VmarFlags, sys::zx_vmar_flags_t);
+
+/// Lists the names of the flags, separated by `|`, for example `PERM_READ | PERM_WRITE`.
+impl fmt::Display for VmarFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("NONE");
+        }
+        let mut first = true;
+        for &(flag, name) in NAMES {
+            if self.contains(flag) {
+                if !first {
+                    f.write_str(" | ")?;
+                }
+                f.write_str(name)?;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for VmarFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "VmarFlags({})", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vmar_flags_format() {
+        let flags = VmarFlags::PERM_READ | VmarFlags::PERM_WRITE;
+        assert!(flags.contains(VmarFlags::PERM_READ));
+        assert!(!flags.contains(VmarFlags::PERM_EXECUTE));
+        assert_eq!(format!("{:?}", flags), "VmarFlags(PERM_READ | PERM_WRITE)");
+        assert_eq!(VmarFlags::default().to_string(), "NONE");
+    }
+
+    #[test]
+    fn vmar_flags_validation() {
+        let flags = VmarFlags::PERM_READ | VmarFlags::SPECIFIC;
+        assert_eq!(flags.perms(), VmarFlags::PERM_READ);
+        assert_eq!(flags.check_map(), Ok(()));
+        assert_eq!(flags.check_allocate(), Err(Status::ErrInvalidArgs));
+        assert_eq!(VmarFlags::CAN_MAP_WRITE.check_map(), Err(Status::ErrInvalidArgs));
+        assert_eq!(VmarFlags::SPECIFIC_OVERWRITE.check_map(), Err(Status::ErrInvalidArgs));
+        assert_eq!((VmarFlags::CAN_MAP_READ | VmarFlags::CAN_MAP_EXECUTE).allowed_perms(),
+            VmarFlags::PERM_READ | VmarFlags::PERM_EXECUTE);
+        assert_eq!(VmarFlags::perms_for_rights(Rights::READ | Rights::MAP),
+            VmarFlags::PERM_READ);
+    }
+}
@@ -4,17 +4,19 @@
 
 //! Type-safe bindings for Zircon vmo objects.
 
-use {AsHandleRef, Cookied, HandleBased, Handle, HandleRef, Status};
-use {sys, into_result};
-use std::{mem, ptr};
+use {AsHandleRef, Cookied, HandleBased, Handle, HandleRef, ObjectType, Status};
+use {VmoInfo, VmoQuery, object_get_info_single};
+use {sys, into_result, round_up_to_page, with_gathered};
+use std::{cmp, mem, ptr};
+use std::io::{self, IoSlice, Read, Write};
 
 /// An object representing a Zircon
 /// [virtual memory object](https://fuchsia.googlesource.com/zircon/+/master/docs/objects/vm_object.md).
 ///
 /// As essentially a subtype of `Handle`, it can be freely interconverted.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Eq, PartialEq)]
 pub struct Vmo(Handle);
-impl_handle_based!(Vmo);
+impl_handle_based!(Vmo, ObjectType::Vmo);
 impl Cookied for Vmo {}
 
 impl Vmo {
@@ -29,7 +31,17 @@ impl Vmo {
         let mut handle = 0;
         let status = unsafe { sys::zx_vmo_create(size, options as u32, &mut handle) };
         into_result(status, ||
-            Vmo::from(Handle(handle)))
+            Vmo::from(Handle::owned(handle)))
+    }
+
+    /// Create a virtual memory object holding a copy of `data`, returning it together with the
+    /// length of the data. The object's size is rounded up to a whole number of pages, so the
+    /// length must be used to find where the data ends.
+    pub fn create_from(data: &[u8]) -> Result<(Vmo, usize), Status> {
+        let size = round_up_to_page(data.len()).ok_or(Status::ErrOutOfRange)?;
+        let vmo = Vmo::create(size as u64, VmoOpts::Default)?;
+        vmo.write_all(data, 0)?;
+        Ok((vmo, data.len()))
     }
 
     /// Read from a virtual memory object.
@@ -56,6 +68,106 @@ impl Vmo {
         }
     }
 
+    /// Read exactly enough bytes to fill `data`. Fails with `ErrOutOfRange` if the object ends
+    /// before `data` is filled.
+    pub fn read_exact(&self, data: &mut [u8], offset: u64) -> Result<(), Status> {
+        if self.read(data, offset)? != data.len() {
+            return Err(Status::ErrOutOfRange);
+        }
+        Ok(())
+    }
+
+    /// Read `len` bytes starting at `offset` into a new vector. Fails with `ErrOutOfRange` if
+    /// the object ends first.
+    pub fn read_to_vec(&self, offset: u64, len: usize) -> Result<Vec<u8>, Status> {
+        let mut data = vec![0; len];
+        self.read_exact(&mut data, offset)?;
+        Ok(data)
+    }
+
+    /// Write the contents of several buffers, one after another, starting at `offset`, with a
+    /// single syscall. Returns the total number of bytes written, which is short if the object
+    /// ends first.
+    ///
+    /// The buffers are gathered into a per-thread scratch buffer, which is reused between
+    /// calls unless it grows beyond 64 KiB.
+    pub fn write_vectored(&self, bufs: &[IoSlice], offset: u64) -> Result<usize, Status> {
+        if bufs.len() == 1 {
+            return self.write(&bufs[0], offset);
+        }
+        with_gathered(bufs, |data| self.write(data, offset))
+    }
+
+    /// Write all of `data`. Fails with `ErrOutOfRange` if the object ends before all of it has
+    /// been written; the part which fits is written anyway.
+    pub fn write_all(&self, data: &[u8], offset: u64) -> Result<(), Status> {
+        if self.write(data, offset)? != data.len() {
+            return Err(Status::ErrOutOfRange);
+        }
+        Ok(())
+    }
+
+    /// Copy everything from `reader` into the object, starting at `offset`, and return the
+    /// number of bytes copied. Fails with an error of kind `Other` (from `ErrOutOfRange`) if the
+    /// object is too small to hold it all.
+    ///
+    /// The data is copied in chunks through an internal buffer.
+    pub fn write_from<R: Read + ?Sized>(&self, reader: &mut R, offset: u64) -> io::Result<u64> {
+        let mut buf = vec![0; COPY_CHUNK_SIZE];
+        let mut total = 0;
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) => return Ok(total),
+                Ok(n) => n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            self.write_all(&buf[..n], offset + total).map_err(Status::into_io_error)?;
+            total += n as u64;
+        }
+    }
+
+    /// Copy `len` bytes of the object, starting at `offset`, to `writer`. Fails with an error
+    /// of kind `Other` (from `ErrOutOfRange`) if the object ends first.
+    ///
+    /// The data is copied in chunks through an internal buffer.
+    pub fn read_into<W: Write + ?Sized>(&self, writer: &mut W, offset: u64, len: u64)
+        -> io::Result<()>
+    {
+        let mut buf = vec![0; cmp::min(len, COPY_CHUNK_SIZE as u64) as usize];
+        let mut done = 0;
+        while done < len {
+            let n = cmp::min(len - done, buf.len() as u64) as usize;
+            self.read_exact(&mut buf[..n], offset + done).map_err(Status::into_io_error)?;
+            writer.write_all(&buf[..n])?;
+            done += n as u64;
+        }
+        Ok(())
+    }
+
+    /// Set `len` bytes starting at `offset` to `byte`. Fails with `ErrOutOfRange` if the range
+    /// extends past the end of the object, after filling the part which fits.
+    ///
+    /// The range is written in page-sized chunks from a buffer on the stack, so no memory is
+    /// allocated however large the range is.
+    pub fn fill(&self, byte: u8, offset: u64, len: u64) -> Result<(), Status> {
+        let chunk = [byte; FILL_CHUNK_SIZE];
+        let mut done = 0;
+        while done < len {
+            let n = cmp::min(len - done, FILL_CHUNK_SIZE as u64) as usize;
+            let chunk_offset = offset.checked_add(done).ok_or(Status::ErrOutOfRange)?;
+            self.write_all(&chunk[..n], chunk_offset)?;
+            done += n as u64;
+        }
+        Ok(())
+    }
+
+    /// Set `len` bytes starting at `offset` to zero. Unlike decommitting, this works for
+    /// ranges which are not page-aligned, and leaves the memory committed.
+    pub fn zero_range(&self, offset: u64, len: u64) -> Result<(), Status> {
+        self.fill(0, offset, len)
+    }
+
     /// Get the size of a virtual memory object.
     ///
     /// Wraps the `zx_vmo_get_size` syscall.
@@ -65,7 +177,9 @@ impl Vmo {
         into_result(status, || size)
     }
 
-    /// Attempt to change the size of a virtual memory object.
+    /// Attempt to change the size of a virtual memory object. Fails with `ErrUnavailable`, the
+    /// status the kernel reports, if it was created with `VmoOpts::NonResizable`; `info` tells
+    /// whether an object is resizable without trying.
     ///
     /// Wraps the `zx_vmo_set_size` syscall.
     pub fn set_size(&self, size: u64) -> Result<(), Status> {
@@ -73,6 +187,15 @@ impl Vmo {
         into_result(status, || ())
     }
 
+    /// Information about the virtual memory object, such as how much memory is committed to it
+    /// and which object it was cloned from.
+    ///
+    /// Wraps the `ZX_INFO_VMO` topic of
+    /// [zx_object_get_info](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/object_get_info.md).
+    pub fn info(&self) -> Result<VmoInfo, Status> {
+        object_get_info_single::<VmoQuery>(self.as_handle_ref()).map(VmoInfo::from)
+    }
+
     /// Perform an operation on a range of a virtual memory object.
     ///
     /// Wraps the
@@ -85,6 +208,30 @@ impl Vmo {
         into_result(status, || ())
     }
 
+    /// Set the cache policy used when the virtual memory object is mapped, for example to map
+    /// device memory uncached. The handle must have `Rights::MAP`, and the policy can only be
+    /// changed while the object has no committed pages, mappings or clones; otherwise this fails
+    /// with `ErrBadState`.
+    ///
+    /// Wraps the
+    /// [zx_vmo_set_cache_policy](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/vmo_set_cache_policy.md)
+    /// syscall.
+    pub fn set_cache_policy(&self, policy: CachePolicy) -> Result<(), Status> {
+        let status = unsafe { sys::zx_vmo_set_cache_policy(self.raw_handle(), policy as u32) };
+        into_result(status, || ())
+    }
+
+    /// Lock a range of the virtual memory object, so that it cannot be decommitted while, for
+    /// example, a DMA transfer is using it. The range is unlocked when the returned guard is
+    /// dropped.
+    ///
+    /// The kernel does not support locking yet, so this currently fails with
+    /// `ErrNotSupported`.
+    pub fn lock_range(&self, offset: u64, size: u64) -> Result<VmoLockGuard<'_>, Status> {
+        self.op_range(VmoOp::Lock, offset, size)?;
+        Ok(VmoLockGuard { vmo: self, offset, size })
+    }
+
     /// Look up a list of physical addresses corresponding to the pages held by the VMO from
     /// `offset` to `offset`+`size`, and store them in `buffer`.
     ///
@@ -101,26 +248,75 @@ impl Vmo {
         into_result(status, || ())
     }
 
-    /// Create a new virtual memory object that clones a range of this one.
+    /// Create a new virtual memory object that clones a range of this one. Same as
+    /// `create_child`.
+    pub fn clone(&self, options: VmoCloneOpts, offset: u64, size: u64) -> Result<Vmo, Status> {
+        self.create_child(options, offset, size)
+    }
+
+    /// Create a child virtual memory object holding the `size` bytes of this one starting at
+    /// `offset`. With `VmoCloneOpts::CopyOnWrite`, pages are shared with this object until one
+    /// of them writes to them, which makes the child a cheap snapshot for loaders and caches.
     ///
     /// Wraps the
     /// [zx_vmo_clone](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/vmo_clone.md)
     /// syscall.
-    pub fn clone(&self, options: VmoCloneOpts, offset: u64, size: u64) -> Result<Vmo, Status> {
+    pub fn create_child(&self, options: VmoCloneOpts, offset: u64, size: u64)
+        -> Result<Vmo, Status>
+    {
         let mut out = 0;
         let status = unsafe {
             sys::zx_vmo_clone(self.raw_handle(), options as u32, offset, size, &mut out)
         };
-        into_result(status, || Vmo::from(Handle(out)))
+        into_result(status, || Vmo::from(Handle::owned(out)))
+    }
+}
+
+/// A locked range of a virtual memory object, returned by `Vmo::lock_range`, which is unlocked
+/// when dropped.
+#[derive(Debug)]
+pub struct VmoLockGuard<'a> {
+    vmo: &'a Vmo,
+    offset: u64,
+    size: u64,
+}
+
+impl<'a> VmoLockGuard<'a> {
+    /// The offset of the locked range.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The size of the locked range.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+impl<'a> Drop for VmoLockGuard<'a> {
+    fn drop(&mut self) {
+        let _ = self.vmo.op_range(VmoOp::Unlock, self.offset, self.size);
     }
 }
 
-/// Options for creating virtual memory objects. None supported yet.
+// The size of the chunks written by `Vmo::fill`.
+const FILL_CHUNK_SIZE: usize = 4096;
+
+// The size of the buffer used by `Vmo::write_from` and `Vmo::read_into`.
+const COPY_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Options for creating virtual memory objects.
 #[repr(u32)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum VmoOpts {
-    /// Default options.
+    /// Default options, creating a resizable object.
     Default = 0,
+    /// Create an object whose size is fixed, so that mappings of it cannot be invalidated by
+    /// another holder of a handle shrinking it.
+    ///
+    /// Kernels which predate this option reject any options to `zx_vmo_create`, so creation
+    /// fails with `ErrInvalidArgs` on them.
+    NonResizable = sys::ZX_VMO_NON_RESIZABLE,
 }
 
 impl Default for VmoOpts {
@@ -129,6 +325,12 @@ impl Default for VmoOpts {
     }
 }
 
+/// Operations which can be performed on a range of a virtual memory object with
+/// `Vmo::op_range`.
+///
+/// `Commit` and `Decommit` let a service back a range with memory ahead of time, or release
+/// the memory behind a range back to the kernel, without destroying and recreating the VMO.
+/// The cache operations maintain buffers shared with hardware.
 #[repr(u32)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum VmoOp {
@@ -136,20 +338,46 @@ pub enum VmoOp {
     Commit = sys::ZX_VMO_OP_COMMIT,
     /// Release a range of pages previously committed to the VMO from `offset` to `offset`+`size`.
     Decommit = sys::ZX_VMO_OP_DECOMMIT,
-    // Presently unsupported.
+    /// Pin a range, so that it cannot be decommitted. Presently unsupported by the kernel; see
+    /// `Vmo::lock_range`.
     Lock = sys::ZX_VMO_OP_LOCK,
-    // Presently unsupported.
+    /// Unpin a range pinned with `Lock`. Presently unsupported by the kernel.
     Unlock = sys::ZX_VMO_OP_UNLOCK,
-    /// Perform a cache sync operation.
+    /// Make the instruction cache coherent with the data cache for the range, for example
+    /// after writing code into it.
     CacheSync = sys::ZX_VMO_OP_CACHE_SYNC,
-    /// Perform a cache invalidation operation.
+    /// Discard cached data for the range, so that data written to memory by hardware (such as
+    /// through DMA) is seen by later reads.
     CacheInvalidate = sys::ZX_VMO_OP_CACHE_INVALIDATE,
-    /// Perform a cache clean operation.
+    /// Write cached data for the range back to memory, so that hardware reading it (such as
+    /// through DMA) sees the latest writes.
     CacheClean = sys::ZX_VMO_OP_CACHE_CLEAN,
-    /// Perform cache clean and invalidation operations together.
+    /// Write cached data for the range back to memory and then discard it, for buffers which
+    /// hardware both reads and writes.
     CacheCleanInvalidate = sys::ZX_VMO_OP_CACHE_CLEAN_INVALIDATE,
 }
 
+/// Cache policies for the memory of a virtual memory object, set with
+/// `Vmo::set_cache_policy`.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CachePolicy {
+    /// Normal cached memory.
+    Cached = sys::zx_cache_policy_t::ZX_CACHE_POLICY_CACHED as u32,
+    /// Uncached memory.
+    Uncached = sys::zx_cache_policy_t::ZX_CACHE_POLICY_UNCACHED as u32,
+    /// Uncached memory with the stricter ordering required for device registers.
+    UncachedDevice = sys::zx_cache_policy_t::ZX_CACHE_POLICY_UNCACHED_DEVICE as u32,
+    /// Uncached memory where writes may be combined, as is usual for framebuffers.
+    WriteCombining = sys::zx_cache_policy_t::ZX_CACHE_POLICY_WRITE_COMBINING as u32,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        CachePolicy::Cached
+    }
+}
+
 #[repr(u32)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum VmoCloneOpts {
@@ -166,6 +394,7 @@ impl Default for VmoCloneOpts {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use {HandleBased, Rights, page_size};
 
     #[test]
     fn vmo_get_size() {
@@ -186,6 +415,15 @@ mod tests {
         assert_eq!(new_size, vmo.get_size().unwrap());
     }
 
+    #[test]
+    fn vmo_non_resizable() {
+        let vmo = Vmo::create(12, VmoOpts::NonResizable).unwrap();
+        assert_eq!(vmo.set_size(23), Err(Status::ErrUnavailable));
+        assert_eq!(vmo.get_size(), Ok(12));
+        assert!(!vmo.info().unwrap().is_resizable());
+        assert!(Vmo::create(12, VmoOpts::Default).unwrap().info().unwrap().is_resizable());
+    }
+
     #[test]
     fn vmo_read_write() {
         let mut vec1 = vec![0; 16];
@@ -200,11 +438,115 @@ mod tests {
         assert_eq!(b"b123f", &vec1[0..5]);
     }
 
+    #[test]
+    fn vmo_read_exact_write_all() {
+        let vmo = Vmo::create(16, VmoOpts::Default).unwrap();
+        assert_eq!(vmo.write_all(b"abcdef", 0), Ok(()));
+        assert_eq!(vmo.read_to_vec(2, 3), Ok(b"cde".to_vec()));
+        let mut buf = [0; 4];
+        assert_eq!(vmo.read_exact(&mut buf, 12), Ok(()));
+        assert_eq!(&buf, &[0; 4]);
+
+        // Transfers which run off the end are errors, rather than short.
+        assert_eq!(vmo.read_exact(&mut buf, 14), Err(Status::ErrOutOfRange));
+        assert_eq!(vmo.read_to_vec(10, 10), Err(Status::ErrOutOfRange));
+        assert_eq!(vmo.write_all(b"abcdef", 12), Err(Status::ErrOutOfRange));
+    }
+
+    #[test]
+    fn vmo_info() {
+        let vmo = Vmo::create(8192, VmoOpts::Default).unwrap();
+        let info = vmo.info().unwrap();
+        assert_eq!(info.koid, vmo.basic_info().unwrap().koid);
+        assert_eq!(info.size_bytes, 8192);
+        assert_eq!(info.committed_bytes, 0);
+        assert_eq!(info.parent_koid, 0);
+
+        assert_eq!(vmo.op_range(VmoOp::Commit, 0, 4096), Ok(()));
+        assert_eq!(vmo.info().unwrap().committed_bytes, 4096);
+
+        let child = vmo.create_child(VmoCloneOpts::CopyOnWrite, 0, 4096).unwrap();
+        let child_info = child.info().unwrap();
+        assert_eq!(child_info.parent_koid, info.koid);
+        assert!(child_info.is_cow_clone());
+        assert_eq!(vmo.info().unwrap().num_children, 1);
+    }
+
+    #[test]
+    fn vmo_create_from() {
+        let (vmo, len) = Vmo::create_from(b"hello").unwrap();
+        assert_eq!(len, 5);
+        assert_eq!(vmo.get_size(), Ok(page_size() as u64));
+        assert_eq!(vmo.read_to_vec(0, len), Ok(b"hello".to_vec()));
+
+        let data = vec![7; page_size()];
+        let (vmo, len) = Vmo::create_from(&data).unwrap();
+        assert_eq!(vmo.get_size(), Ok(page_size() as u64));
+        assert_eq!(vmo.read_to_vec(0, len), Ok(data));
+    }
+
+    #[test]
+    fn vmo_copy_io() {
+        let data: Vec<u8> = (0..100000u32).map(|i| i as u8).collect();
+        let vmo = Vmo::create(200000, VmoOpts::Default).unwrap();
+        assert_eq!(vmo.write_from(&mut &data[..], 10).unwrap(), 100000);
+
+        let mut copy = vec![];
+        assert!(vmo.read_into(&mut copy, 10, 100000).is_ok());
+        assert_eq!(copy, data);
+
+        // Copies which do not fit in the object fail.
+        assert!(vmo.write_from(&mut &data[..], 150000).is_err());
+        assert!(vmo.read_into(&mut copy, 150000, 100000).is_err());
+    }
+
+    #[test]
+    fn vmo_fill() {
+        let vmo = Vmo::create(10000, VmoOpts::Default).unwrap();
+        assert_eq!(vmo.fill(0xff, 0, 10000), Ok(()));
+        assert_eq!(vmo.zero_range(3, 9000), Ok(()));
+        let data = vmo.read_to_vec(0, 10000).unwrap();
+        assert_eq!(&data[..3], &[0xff; 3]);
+        assert!(data[3..9003].iter().all(|&b| b == 0));
+        assert!(data[9003..].iter().all(|&b| b == 0xff));
+
+        assert_eq!(vmo.zero_range(9999, 2), Err(Status::ErrOutOfRange));
+        assert_eq!(vmo.fill(1, u64::MAX, 1), Err(Status::ErrOutOfRange));
+    }
+
+    #[test]
+    fn vmo_write_vectored() {
+        let vmo = Vmo::create(16, VmoOpts::Default).unwrap();
+        let bufs = [IoSlice::new(b"head"), IoSlice::new(b""), IoSlice::new(b"payload")];
+        assert_eq!(vmo.write_vectored(&bufs, 2), Ok(11));
+        assert_eq!(vmo.read_to_vec(2, 11), Ok(b"headpayload".to_vec()));
+
+        // The write is cut short at the end of the object.
+        assert_eq!(vmo.write_vectored(&bufs, 8), Ok(8));
+        assert_eq!(vmo.read_to_vec(8, 8), Ok(b"headpayl".to_vec()));
+    }
+
     #[test]
     fn vmo_op_range_unsupported() {
         let vmo = Vmo::create(12, VmoOpts::Default).unwrap();
         assert_eq!(vmo.op_range(VmoOp::Lock, 0, 1), Err(Status::ErrNotSupported));
         assert_eq!(vmo.op_range(VmoOp::Unlock, 0, 1), Err(Status::ErrNotSupported));
+        assert_eq!(vmo.lock_range(0, 1).unwrap_err(), Status::ErrNotSupported);
+    }
+
+    #[test]
+    fn vmo_op_range_commit_decommit() {
+        let vmo = Vmo::create(8192, VmoOpts::Default).unwrap();
+        assert_eq!(vmo.op_range(VmoOp::Commit, 0, 8192), Ok(()));
+        assert_eq!(vmo.write(b"data", 0), Ok(4));
+
+        // Decommitting releases the pages, so their contents are lost.
+        assert_eq!(vmo.op_range(VmoOp::Decommit, 0, 8192), Ok(()));
+        let mut read_buffer = vec![1; 4];
+        assert_eq!(vmo.read(&mut read_buffer, 0), Ok(4));
+        assert_eq!(read_buffer, vec![0; 4]);
+
+        assert_eq!(vmo.op_range(VmoOp::Commit, 8192, 4096), Err(Status::ErrOutOfRange));
     }
 
     #[test]
@@ -237,6 +579,37 @@ mod tests {
         assert_eq!(vmo.op_range(VmoOp::CacheCleanInvalidate, 0, 12), Ok(()));
     }
 
+    #[test]
+    fn vmo_create_child() {
+        let original = Vmo::create(8192, VmoOpts::Default).unwrap();
+        assert_eq!(original.write(b"header", 0), Ok(6));
+        assert_eq!(original.write(b"body", 4096), Ok(4));
+
+        // The child starts at the given offset of the original.
+        let child = original.create_child(VmoCloneOpts::CopyOnWrite, 4096, 4096).unwrap();
+        assert_eq!(child.get_size(), Ok(4096));
+        let mut read_buffer = vec![0; 4];
+        assert_eq!(child.read(&mut read_buffer, 0), Ok(4));
+        assert_eq!(&read_buffer, b"body");
+
+        // Writes to the child are not seen by the original.
+        assert_eq!(child.write(b"BODY", 0), Ok(4));
+        assert_eq!(original.read(&mut read_buffer, 4096), Ok(4));
+        assert_eq!(&read_buffer, b"body");
+    }
+
+    #[test]
+    fn vmo_set_cache_policy() {
+        let vmo = Vmo::create(4096, VmoOpts::Default).unwrap();
+        let unmappable = vmo.duplicate_with().remove(Rights::MAP).build().unwrap();
+        assert_eq!(unmappable.set_cache_policy(CachePolicy::Uncached),
+            Err(Status::ErrAccessDenied));
+
+        // Once pages have been committed, the policy can no longer be changed.
+        assert_eq!(vmo.op_range(VmoOp::Commit, 0, 4096), Ok(()));
+        assert_eq!(vmo.set_cache_policy(CachePolicy::WriteCombining), Err(Status::ErrBadState));
+    }
+
     #[test]
     fn vmo_clone() {
         let original = Vmo::create(12, VmoOpts::Default).unwrap();
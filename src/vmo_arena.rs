@@ -0,0 +1,195 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Sub-allocation of buffers from a single virtual memory object.
+
+use {AsHandleRef, HandleRef, Status, Vmo, VmoOpts};
+
+/// A buffer allocated from a `VmoArena`: the range of `len` bytes at `offset` in the arena's
+/// virtual memory object.
+///
+/// Buffers deliberately do not implement `Clone`, so that each is returned to the arena at most
+/// once.
+#[derive(Debug, Eq, PartialEq)]
+pub struct ArenaBuffer {
+    offset: u64,
+    len: u64,
+}
+
+impl ArenaBuffer {
+    /// The offset of the buffer in the arena's virtual memory object.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The length of the buffer in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns true if the buffer is empty, which allocated buffers never are.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Check that `len` bytes at `offset` lie within the buffer.
+    fn check_range(&self, offset: u64, len: usize) -> Result<(), Status> {
+        match offset.checked_add(len as u64) {
+            Some(end) if end <= self.len => Ok(()),
+            _ => Err(Status::ErrOutOfRange),
+        }
+    }
+}
+
+/// Hands out buffers from one large virtual memory object, for protocols which pass many small
+/// buffers as (vmo, offset, len) triples rather than creating a virtual memory object for each.
+///
+/// Freed buffers are kept on a free list, merged with their neighbours, and reused by later
+/// allocations.
+#[derive(Debug)]
+pub struct VmoArena {
+    vmo: Vmo,
+    // Free ranges as (offset, len), sorted by offset, with no two adjacent.
+    free: Vec<(u64, u64)>,
+}
+
+impl VmoArena {
+    /// Create an arena backed by a new virtual memory object of the given size.
+    pub fn new(size: u64) -> Result<VmoArena, Status> {
+        Vmo::create(size, VmoOpts::Default).and_then(VmoArena::from_vmo)
+    }
+
+    /// Create an arena which hands out buffers from the whole of an existing virtual memory
+    /// object.
+    pub fn from_vmo(vmo: Vmo) -> Result<VmoArena, Status> {
+        let size = vmo.get_size()?;
+        let free = if size == 0 { vec![] } else { vec![(0, size)] };
+        Ok(VmoArena { vmo, free })
+    }
+
+    /// The virtual memory object the buffers are allocated from.
+    pub fn vmo(&self) -> &Vmo {
+        &self.vmo
+    }
+
+    /// Allocate a buffer of `len` bytes, whose offset is a multiple of `align`.
+    ///
+    /// Fails with `ErrInvalidArgs` if `len` is zero or `align` is not a power of two, or
+    /// `ErrNoMemory` if there is no free range large enough.
+    pub fn alloc(&mut self, len: u64, align: u64) -> Result<ArenaBuffer, Status> {
+        if len == 0 || !align.is_power_of_two() {
+            return Err(Status::ErrInvalidArgs);
+        }
+        for i in 0..self.free.len() {
+            let (start, free_len) = self.free[i];
+            let offset = match start.checked_add(align - 1) {
+                Some(end) => end & !(align - 1),
+                None => continue,
+            };
+            let padding = offset - start;
+            if padding > free_len || free_len - padding < len {
+                continue;
+            }
+            let remaining = free_len - padding - len;
+            self.free.remove(i);
+            if remaining > 0 {
+                self.free.insert(i, (offset + len, remaining));
+            }
+            if padding > 0 {
+                self.free.insert(i, (start, padding));
+            }
+            return Ok(ArenaBuffer { offset, len });
+        }
+        Err(Status::ErrNoMemory)
+    }
+
+    /// Return a buffer to the arena so that its range can be reused.
+    ///
+    /// The buffer must have been allocated from this arena.
+    pub fn free(&mut self, buffer: ArenaBuffer) {
+        let ArenaBuffer { mut offset, mut len } = buffer;
+        let i = self.free.iter().position(|&(start, _)| start > offset).unwrap_or(self.free.len());
+        debug_assert!(i == self.free.len() || offset + len <= self.free[i].0);
+        debug_assert!(i == 0 || self.free[i - 1].0 + self.free[i - 1].1 <= offset);
+
+        // Merge with the following free range, then the preceding one.
+        if i < self.free.len() && offset + len == self.free[i].0 {
+            len += self.free.remove(i).1;
+        }
+        if i > 0 && self.free[i - 1].0 + self.free[i - 1].1 == offset {
+            offset = self.free[i - 1].0;
+            len += self.free[i - 1].1;
+            self.free[i - 1] = (offset, len);
+        } else {
+            self.free.insert(i, (offset, len));
+        }
+    }
+
+    /// Write `data` into a buffer, starting `offset` bytes into it. Fails with `ErrOutOfRange`
+    /// if it does not fit in the buffer.
+    pub fn write(&self, buffer: &ArenaBuffer, data: &[u8], offset: u64) -> Result<(), Status> {
+        buffer.check_range(offset, data.len())?;
+        self.vmo.write_all(data, buffer.offset + offset)
+    }
+
+    /// Read from a buffer into `data`, starting `offset` bytes into it. Fails with
+    /// `ErrOutOfRange` if `data` extends past the end of the buffer.
+    pub fn read(&self, buffer: &ArenaBuffer, data: &mut [u8], offset: u64) -> Result<(), Status> {
+        buffer.check_range(offset, data.len())?;
+        self.vmo.read_exact(data, buffer.offset + offset)
+    }
+}
+
+impl AsHandleRef for VmoArena {
+    fn as_handle_ref(&self) -> HandleRef {
+        self.vmo.as_handle_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arena_alloc_free() {
+        let mut arena = VmoArena::new(4096).unwrap();
+        let a = arena.alloc(100, 1).unwrap();
+        let b = arena.alloc(100, 64).unwrap();
+        assert_eq!(a.offset(), 0);
+        assert_eq!(b.offset(), 128);
+
+        // The padding before b is reused for a small enough buffer.
+        let c = arena.alloc(28, 1).unwrap();
+        assert_eq!(c.offset(), 100);
+        assert_eq!(arena.alloc(4096, 1), Err(Status::ErrNoMemory));
+
+        // Once everything is freed, the free ranges are merged back together.
+        arena.free(b);
+        arena.free(a);
+        arena.free(c);
+        assert_eq!(arena.free, vec![(0, 4096)]);
+        assert_eq!(arena.alloc(4096, 1).unwrap().len(), 4096);
+    }
+
+    #[test]
+    fn arena_invalid_args() {
+        let mut arena = VmoArena::new(4096).unwrap();
+        assert_eq!(arena.alloc(0, 1), Err(Status::ErrInvalidArgs));
+        assert_eq!(arena.alloc(10, 3), Err(Status::ErrInvalidArgs));
+    }
+
+    #[test]
+    fn arena_read_write() {
+        let mut arena = VmoArena::new(4096).unwrap();
+        let a = arena.alloc(8, 8).unwrap();
+        let b = arena.alloc(8, 8).unwrap();
+        assert_eq!(arena.write(&a, b"hello", 3), Ok(()));
+        assert_eq!(arena.write(&b, b"world", 4), Err(Status::ErrOutOfRange));
+
+        let mut data = [0; 8];
+        assert_eq!(arena.read(&a, &mut data, 0), Ok(()));
+        assert_eq!(&data, b"\0\0\0hello");
+        assert_eq!(arena.read(&b, &mut data, 1), Err(Status::ErrOutOfRange));
+    }
+}
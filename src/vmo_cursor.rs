@@ -0,0 +1,152 @@
+// Copyright 2017 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Access to virtual memory objects through the `std::io` traits.
+
+use {AsHandleRef, HandleRef, Status, Vmo};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// A virtual memory object together with a current position, implementing `std::io::Read`,
+/// `Write` and `Seek`, so that code written against `std::io` can read and write VMOs.
+///
+/// Reads stop at the end of the object, and writes are cut short there: the object is not
+/// grown to fit.
+#[derive(Debug)]
+pub struct VmoCursor {
+    vmo: Vmo,
+    position: u64,
+}
+
+impl VmoCursor {
+    /// Wrap a virtual memory object, starting at position 0.
+    pub fn new(vmo: Vmo) -> VmoCursor {
+        VmoCursor { vmo, position: 0 }
+    }
+
+    /// Unwrap the virtual memory object.
+    pub fn into_inner(self) -> Vmo {
+        self.vmo
+    }
+
+    /// The virtual memory object.
+    pub fn get_ref(&self) -> &Vmo {
+        &self.vmo
+    }
+
+    /// The current position.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Set the current position, which may be beyond the end of the object.
+    pub fn set_position(&mut self, position: u64) {
+        self.position = position;
+    }
+}
+
+impl Read for VmoCursor {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let actual = match self.vmo.read(buf, self.position) {
+            // Reading at or past the end of the object.
+            Err(Status::ErrOutOfRange) => 0,
+            result => result.map_err(Status::into_io_error)?,
+        };
+        self.position += actual as u64;
+        Ok(actual)
+    }
+}
+
+impl Write for VmoCursor {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let actual = match self.vmo.write(buf, self.position) {
+            Err(Status::ErrOutOfRange) => 0,
+            result => result.map_err(Status::into_io_error)?,
+        };
+        self.position += actual as u64;
+        Ok(actual)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for VmoCursor {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(position) => {
+                self.position = position;
+                return Ok(position);
+            }
+            SeekFrom::Current(offset) => (self.position, offset),
+            SeekFrom::End(offset) => (self.vmo.get_size().map_err(Status::into_io_error)?, offset),
+        };
+        let position = if offset >= 0 {
+            base.checked_add(offset as u64)
+        } else {
+            base.checked_sub(offset.wrapping_neg() as u64)
+        };
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                "seek to a negative or overflowing position")),
+        }
+    }
+}
+
+impl AsHandleRef for VmoCursor {
+    fn as_handle_ref(&self) -> HandleRef {
+        self.vmo.as_handle_ref()
+    }
+}
+
+impl From<Vmo> for VmoCursor {
+    fn from(vmo: Vmo) -> Self {
+        VmoCursor::new(vmo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use VmoOpts;
+
+    #[test]
+    fn vmo_cursor_read_write() {
+        let mut cursor = VmoCursor::new(Vmo::create(16, VmoOpts::Default).unwrap());
+        cursor.write_all(b"hello world").unwrap();
+        assert_eq!(cursor.position(), 11);
+
+        cursor.set_position(6);
+        let mut word = [0; 5];
+        cursor.read_exact(&mut word).unwrap();
+        assert_eq!(&word, b"world");
+
+        // Reading stops at the end of the object, and writing past it fails.
+        let mut rest = vec![];
+        assert_eq!(cursor.read_to_end(&mut rest).unwrap(), 5);
+        assert_eq!(cursor.write(b"more").unwrap(), 0);
+        assert_eq!(cursor.write_all(b"more").unwrap_err().kind(), io::ErrorKind::WriteZero);
+    }
+
+    #[test]
+    fn vmo_cursor_seek() {
+        let mut cursor = VmoCursor::new(Vmo::create(16, VmoOpts::Default).unwrap());
+        assert_eq!(cursor.seek(SeekFrom::End(-4)).unwrap(), 12);
+        assert_eq!(cursor.seek(SeekFrom::Current(2)).unwrap(), 14);
+        assert_eq!(cursor.seek(SeekFrom::Start(3)).unwrap(), 3);
+        assert_eq!(cursor.seek(SeekFrom::Current(-4)).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput);
+        assert_eq!(cursor.position(), 3);
+
+        // The offset is added or subtracted without overflowing, whatever its magnitude.
+        assert_eq!(cursor.seek(SeekFrom::Start(u64::MAX)).unwrap(), u64::MAX);
+        assert_eq!(cursor.seek(SeekFrom::Current(i64::MIN)).unwrap(), i64::MAX as u64);
+        assert_eq!(cursor.seek(SeekFrom::Current(i64::MIN)).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput);
+    }
+}
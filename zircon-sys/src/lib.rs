@@ -10,6 +10,7 @@ extern crate bitflags;
 use std::{cmp, fmt};
 
 pub type zx_handle_t = i32;
+pub const ZX_HANDLE_INVALID: zx_handle_t = 0;
 
 pub type zx_status_t = i32;
 
@@ -18,6 +19,7 @@ pub type zx_addr_t = usize;
 pub type zx_paddr_t = usize;
 pub type zx_vaddr_t = usize;
 pub type zx_off_t = u64;
+pub type zx_koid_t = u64;
 
 // Auto-generated using tools/gen_status.py
 pub const ZX_OK                    : zx_status_t = 0;
@@ -127,6 +129,12 @@ bitflags! {
         const ZX_SOCKET_READABLE          = ZX_OBJECT_SIGNAL_0.bits,
         const ZX_SOCKET_WRITABLE          = ZX_OBJECT_SIGNAL_1.bits,
         const ZX_SOCKET_PEER_CLOSED       = ZX_OBJECT_SIGNAL_2.bits,
+        const ZX_SOCKET_READ_DISABLED     = ZX_OBJECT_SIGNAL_4.bits,
+        const ZX_SOCKET_WRITE_DISABLED    = ZX_OBJECT_SIGNAL_5.bits,
+        const ZX_SOCKET_CONTROL_READABLE  = ZX_OBJECT_SIGNAL_6.bits,
+        const ZX_SOCKET_CONTROL_WRITABLE  = ZX_OBJECT_SIGNAL_7.bits,
+        const ZX_SOCKET_ACCEPT            = ZX_OBJECT_SIGNAL_8.bits,
+        const ZX_SOCKET_SHARE             = ZX_OBJECT_SIGNAL_9.bits,
 
         // Port
         const ZX_PORT_READABLE            = ZX_OBJECT_READABLE.bits,
@@ -151,6 +159,8 @@ bitflags! {
 
         // Thread
         const ZX_THREAD_TERMINATED        = ZX_OBJECT_SIGNAL_3.bits,
+        const ZX_THREAD_RUNNING           = ZX_OBJECT_SIGNAL_4.bits,
+        const ZX_THREAD_SUSPENDED         = ZX_OBJECT_SIGNAL_5.bits,
 
         // Log
         const ZX_LOG_READABLE             = ZX_OBJECT_READABLE.bits,
@@ -181,6 +191,135 @@ bitflags! {
     }
 }
 
+// Topics for zx_object_get_info.
+pub type zx_object_info_topic_t = u32;
+pub const ZX_INFO_NONE: zx_object_info_topic_t = 0;
+pub const ZX_INFO_HANDLE_VALID: zx_object_info_topic_t = 1;
+pub const ZX_INFO_HANDLE_BASIC: zx_object_info_topic_t = 2;
+pub const ZX_INFO_PROCESS: zx_object_info_topic_t = 3;
+pub const ZX_INFO_PROCESS_THREADS: zx_object_info_topic_t = 4;
+pub const ZX_INFO_RESOURCE_CHILDREN: zx_object_info_topic_t = 5;
+pub const ZX_INFO_RESOURCE_RECORDS: zx_object_info_topic_t = 6;
+pub const ZX_INFO_VMAR: zx_object_info_topic_t = 7;
+pub const ZX_INFO_JOB_CHILDREN: zx_object_info_topic_t = 8;
+pub const ZX_INFO_JOB_PROCESSES: zx_object_info_topic_t = 9;
+pub const ZX_INFO_THREAD: zx_object_info_topic_t = 10;
+pub const ZX_INFO_THREAD_EXCEPTION_REPORT: zx_object_info_topic_t = 11;
+pub const ZX_INFO_TASK_STATS: zx_object_info_topic_t = 12;
+pub const ZX_INFO_SOCKET: zx_object_info_topic_t = 22;
+pub const ZX_INFO_VMO: zx_object_info_topic_t = 23;
+
+// Types of kernel objects.
+pub type zx_obj_type_t = u32;
+pub const ZX_OBJ_TYPE_NONE: zx_obj_type_t = 0;
+pub const ZX_OBJ_TYPE_PROCESS: zx_obj_type_t = 1;
+pub const ZX_OBJ_TYPE_THREAD: zx_obj_type_t = 2;
+pub const ZX_OBJ_TYPE_VMO: zx_obj_type_t = 3;
+pub const ZX_OBJ_TYPE_CHANNEL: zx_obj_type_t = 4;
+pub const ZX_OBJ_TYPE_EVENT: zx_obj_type_t = 5;
+pub const ZX_OBJ_TYPE_PORT: zx_obj_type_t = 6;
+pub const ZX_OBJ_TYPE_INTERRUPT: zx_obj_type_t = 9;
+pub const ZX_OBJ_TYPE_PCI_DEVICE: zx_obj_type_t = 11;
+pub const ZX_OBJ_TYPE_LOG: zx_obj_type_t = 12;
+pub const ZX_OBJ_TYPE_SOCKET: zx_obj_type_t = 14;
+pub const ZX_OBJ_TYPE_RESOURCE: zx_obj_type_t = 15;
+pub const ZX_OBJ_TYPE_EVENT_PAIR: zx_obj_type_t = 16;
+pub const ZX_OBJ_TYPE_JOB: zx_obj_type_t = 17;
+pub const ZX_OBJ_TYPE_VMAR: zx_obj_type_t = 18;
+pub const ZX_OBJ_TYPE_FIFO: zx_obj_type_t = 19;
+pub const ZX_OBJ_TYPE_GUEST: zx_obj_type_t = 20;
+pub const ZX_OBJ_TYPE_VCPU: zx_obj_type_t = 21;
+pub const ZX_OBJ_TYPE_TIMER: zx_obj_type_t = 22;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct zx_info_handle_basic_t {
+    pub koid: zx_koid_t,
+    pub rights: zx_rights_t,
+    pub type_: zx_obj_type_t,
+    pub related_koid: zx_koid_t,
+    pub props: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct zx_info_process_t {
+    pub return_code: i64,
+    pub started: bool,
+    pub exited: bool,
+    pub debugger_attached: bool,
+    pub padding1: [u8; 5],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct zx_info_thread_t {
+    pub state: u32,
+    pub wait_exception_port_type: u32,
+}
+
+// States of a thread, for zx_info_thread_t.
+pub const ZX_THREAD_STATE_NEW: u32 = 0;
+pub const ZX_THREAD_STATE_RUNNING: u32 = 1;
+pub const ZX_THREAD_STATE_SUSPENDED: u32 = 2;
+pub const ZX_THREAD_STATE_BLOCKED: u32 = 3;
+pub const ZX_THREAD_STATE_DYING: u32 = 4;
+pub const ZX_THREAD_STATE_DEAD: u32 = 5;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct zx_info_task_stats_t {
+    pub mem_mapped_bytes: usize,
+    pub mem_private_bytes: usize,
+    pub mem_shared_bytes: usize,
+    pub mem_scaled_shared_bytes: usize,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct zx_info_socket_t {
+    pub options: u32,
+    pub rx_buf_max: usize,
+    pub rx_buf_size: usize,
+    pub tx_buf_max: usize,
+    pub tx_buf_size: usize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct zx_info_vmo_t {
+    pub koid: zx_koid_t,
+    pub name: [u8; ZX_MAX_NAME_LEN],
+    pub size_bytes: u64,
+    pub parent_koid: zx_koid_t,
+    pub num_children: usize,
+    pub num_mappings: usize,
+    pub share_count: usize,
+    pub flags: u32,
+    pub padding1: [u8; 4],
+    pub committed_bytes: u64,
+    pub handle_rights: zx_rights_t,
+    pub cache_policy: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct zx_info_vmar_t {
+    pub base: usize,
+    pub len: usize,
+}
+
+// Flags for zx_info_vmo_t.
+pub const ZX_INFO_VMO_TYPE_PAGED: u32 = 1 << 0;
+pub const ZX_INFO_VMO_RESIZABLE: u32 = 1 << 1;
+pub const ZX_INFO_VMO_IS_COW_CLONE: u32 = 1 << 2;
+
+// Object properties.
+pub const ZX_PROP_NAME: u32 = 3;
+
+// Maximum length of an object name, including the terminating NUL.
+pub const ZX_MAX_NAME_LEN: usize = 32;
+
 // clock ids
 pub const ZX_CLOCK_MONOTONIC: u32 = 0;
 
@@ -188,7 +327,16 @@ pub const ZX_CLOCK_MONOTONIC: u32 = 0;
 pub const ZX_CPRNG_DRAW_MAX_LEN: usize = 256;
 pub const ZX_CPRNG_ADD_ENTROPY_MAX_LEN: usize = 256;
 
+// Channel limits.
+pub const ZX_CHANNEL_MAX_MSG_BYTES: u32 = 65536;
+pub const ZX_CHANNEL_MAX_MSG_HANDLES: u32 = 64;
+
 // Socket flags and limits.
+pub const ZX_SOCKET_STREAM: u32 = 0;
+pub const ZX_SOCKET_DATAGRAM: u32 = 1 << 0;
+pub const ZX_SOCKET_HAS_CONTROL: u32 = 1 << 1;
+pub const ZX_SOCKET_HAS_ACCEPT: u32 = 1 << 2;
+pub const ZX_SOCKET_CONTROL: u32 = 1 << 2;
 pub const ZX_SOCKET_HALF_CLOSE: u32 = 1;
 
 // VM Object opcodes
@@ -205,11 +353,15 @@ pub const ZX_VMO_OP_CACHE_CLEAN_INVALIDATE: u32 = 9;
 // VM Object clone flags
 pub const ZX_VMO_CLONE_COPY_ON_WRITE: u32 = 1;
 
+// VM Object creation options
+pub const ZX_VMO_NON_RESIZABLE: u32 = 1 << 1;
+
 // Mapping flags to vmar routines
 bitflags! {
     #[repr(C)]
     pub flags zx_vmar_flags_t: u32 {
     // flags to vmar routines
+        const ZX_VM_FLAG_NONE               = 0,
         const ZX_VM_FLAG_PERM_READ          = 1  << 0,
         const ZX_VM_FLAG_PERM_WRITE         = 1  << 1,
         const ZX_VM_FLAG_PERM_EXECUTE       = 1  << 2,
@@ -319,18 +471,80 @@ pub struct zx_pci_resource_t {
 pub type zx_rrec_t = [u8; 64];
 
 // Ports V2
-#[repr(u32)]
+pub type zx_packet_type_t = u32;
+
+pub const ZX_PKT_TYPE_USER: zx_packet_type_t = 0x00;
+pub const ZX_PKT_TYPE_SIGNAL_ONE: zx_packet_type_t = 0x01;
+pub const ZX_PKT_TYPE_SIGNAL_REP: zx_packet_type_t = 0x02;
+// The low byte of the type of exception packets, whose full type is one of the ZX_EXCP_*
+// values below.
+pub const ZX_PKT_TYPE_EXCEPTION: zx_packet_type_t = 0x08;
+pub const ZX_PKT_TYPE_MASK: zx_packet_type_t = 0xff;
+
+// Exception types, as the types of exception packets.
+pub const ZX_EXCP_GENERAL: zx_packet_type_t = 0x008;
+pub const ZX_EXCP_FATAL_PAGE_FAULT: zx_packet_type_t = 0x108;
+pub const ZX_EXCP_UNDEFINED_INSTRUCTION: zx_packet_type_t = 0x208;
+pub const ZX_EXCP_SW_BREAKPOINT: zx_packet_type_t = 0x308;
+pub const ZX_EXCP_HW_BREAKPOINT: zx_packet_type_t = 0x408;
+pub const ZX_EXCP_UNALIGNED_ACCESS: zx_packet_type_t = 0x508;
+pub const ZX_EXCP_THREAD_STARTING: zx_packet_type_t = 0x8008;
+pub const ZX_EXCP_THREAD_EXITING: zx_packet_type_t = 0x8108;
+pub const ZX_EXCP_POLICY_ERROR: zx_packet_type_t = 0x8208;
+
+// The processargs protocol, used for the bootstrap message sent to a new process.
+pub const ZX_PROCARGS_PROTOCOL: u32 = 0x4150585d;
+pub const ZX_PROCARGS_VERSION: u32 = 0x0001000;
+
+#[repr(C)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-pub enum zx_packet_type_t {
-    ZX_PKT_TYPE_USER = 0,
-    ZX_PKT_TYPE_SIGNAL_ONE = 1,
-    ZX_PKT_TYPE_SIGNAL_REP = 2,
+pub struct zx_proc_args_t {
+    pub protocol: u32,
+    pub version: u32,
+    pub handle_info_off: u32,
+    pub args_off: u32,
+    pub args_num: u32,
+    pub environ_off: u32,
+    pub environ_num: u32,
 }
 
-impl Default for zx_packet_type_t {
-    fn default() -> Self {
-        zx_packet_type_t::ZX_PKT_TYPE_USER
-    }
+// Types of the handles in the processargs handle table, in the low byte of their handle info.
+pub const PA_PROC_SELF: u32 = 0x01;
+pub const PA_THREAD_SELF: u32 = 0x02;
+pub const PA_JOB_DEFAULT: u32 = 0x03;
+pub const PA_VMAR_ROOT: u32 = 0x04;
+pub const PA_VMAR_LOADED: u32 = 0x05;
+pub const PA_SVC_LOADER: u32 = 0x10;
+pub const PA_VMO_VDSO: u32 = 0x11;
+pub const PA_VMO_STACK: u32 = 0x13;
+pub const PA_VMO_EXECUTABLE: u32 = 0x14;
+pub const PA_VMO_BOOTDATA: u32 = 0x1A;
+pub const PA_VMO_BOOTFS: u32 = 0x1B;
+pub const PA_VMO_KERNEL_FILE: u32 = 0x1C;
+pub const PA_NS_DIR: u32 = 0x20;
+pub const PA_FDIO_REMOTE: u32 = 0x32;
+pub const PA_FDIO_PIPE: u32 = 0x33;
+pub const PA_FDIO_EVENT: u32 = 0x34;
+pub const PA_FDIO_LOGGER: u32 = 0x35;
+pub const PA_FDIO_SOCKET: u32 = 0x36;
+pub const PA_USER0: u32 = 0xF0;
+pub const PA_USER1: u32 = 0xF1;
+pub const PA_USER2: u32 = 0xF2;
+
+// Options for zx_task_resume.
+pub const ZX_RESUME_EXCEPTION: u32 = 1;
+pub const ZX_RESUME_TRY_NEXT: u32 = 2;
+
+// Options for zx_task_bind_exception_port.
+pub const ZX_EXCEPTION_PORT_DEBUGGER: u32 = 1;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct zx_packet_exception_t {
+    pub pid: u64,
+    pub tid: u64,
+    pub reserved0: u64,
+    pub reserved1: u64,
 }
 
 #[repr(C)]
@@ -441,6 +655,81 @@ impl cmp::PartialEq for zx_guest_packet_t {
 
 impl cmp::Eq for zx_guest_packet_t {}
 
+// Options for zx_job_set_policy.
+pub const ZX_JOB_POL_RELATIVE: u32 = 0;
+pub const ZX_JOB_POL_ABSOLUTE: u32 = 1;
+
+// Topics for zx_job_set_policy.
+pub const ZX_JOB_POL_BASIC: u32 = 0;
+
+// Conditions for ZX_JOB_POL_BASIC policies.
+pub const ZX_POL_BAD_HANDLE: u32 = 0;
+pub const ZX_POL_WRONG_OBJECT: u32 = 1;
+pub const ZX_POL_VMAR_WX: u32 = 2;
+pub const ZX_POL_NEW_ANY: u32 = 3;
+pub const ZX_POL_NEW_VMO: u32 = 4;
+pub const ZX_POL_NEW_CHANNEL: u32 = 5;
+pub const ZX_POL_NEW_EVENT: u32 = 6;
+pub const ZX_POL_NEW_EVPAIR: u32 = 7;
+pub const ZX_POL_NEW_PORT: u32 = 8;
+pub const ZX_POL_NEW_SOCKET: u32 = 9;
+pub const ZX_POL_NEW_FIFO: u32 = 10;
+pub const ZX_POL_NEW_TIMER: u32 = 11;
+pub const ZX_POL_NEW_PROCESS: u32 = 12;
+
+// Actions for ZX_JOB_POL_BASIC policies.
+pub const ZX_POL_ACTION_ALLOW: u32 = 0;
+pub const ZX_POL_ACTION_DENY: u32 = 1;
+pub const ZX_POL_ACTION_ALLOW_EXCEPTION: u32 = 2;
+pub const ZX_POL_ACTION_DENY_EXCEPTION: u32 = 3;
+pub const ZX_POL_ACTION_KILL: u32 = 4;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct zx_policy_basic_t {
+    pub condition: u32,
+    pub policy: u32,
+}
+
+// Kinds of state for zx_thread_read_state and zx_thread_write_state.
+pub const ZX_THREAD_STATE_REGSET0: u32 = 0;
+pub const ZX_THREAD_STATE_GENERAL_REGS: u32 = ZX_THREAD_STATE_REGSET0;
+
+#[cfg(target_arch="x86_64")]
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct zx_thread_state_general_regs_t {
+    pub rax: u64,
+    pub rbx: u64,
+    pub rcx: u64,
+    pub rdx: u64,
+    pub rsi: u64,
+    pub rdi: u64,
+    pub rbp: u64,
+    pub rsp: u64,
+    pub r8: u64,
+    pub r9: u64,
+    pub r10: u64,
+    pub r11: u64,
+    pub r12: u64,
+    pub r13: u64,
+    pub r14: u64,
+    pub r15: u64,
+    pub rip: u64,
+    pub rflags: u64,
+}
+
+#[cfg(target_arch="aarch64")]
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct zx_thread_state_general_regs_t {
+    pub r: [u64; 30],
+    pub lr: u64,
+    pub sp: u64,
+    pub pc: u64,
+    pub cpsr: u64,
+}
+
 #[cfg(target_arch="x86_64")]
 #[repr(C)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -457,4 +746,34 @@ pub struct zx_vcpu_create_args_t {
     pub ip: zx_vaddr_t,
 }
 
-include!("definitions.rs");
\ No newline at end of file
+include!("definitions.rs");
+
+// Syscalls which are newer than the sysgen output in definitions.rs. These should move there
+// when it is next regenerated.
+#[link(name = "zircon")]
+extern {
+    pub fn zx_socket_share(
+        handle: zx_handle_t,
+        socket_to_share: zx_handle_t
+        ) -> zx_status_t;
+
+    pub fn zx_socket_accept(
+        handle: zx_handle_t,
+        out_socket: *mut zx_handle_t
+        ) -> zx_status_t;
+}
+
+// Functions provided by the C runtime rather than the kernel, giving the handles the process
+// was started with.
+#[link(name = "c")]
+extern {
+    pub fn zx_process_self() -> zx_handle_t;
+
+    pub fn zx_thread_self() -> zx_handle_t;
+
+    pub fn zx_job_default() -> zx_handle_t;
+
+    pub fn zx_get_startup_handle(
+        hnd_info: u32
+        ) -> zx_handle_t;
+}
\ No newline at end of file